@@ -78,6 +78,7 @@ impl From<CompileOptions> for prql_compiler::Options {
             signature_comment: o.signature_comment,
             // TODO: add support for this
             color: false,
+            ..Default::default()
         }
     }
 }
@@ -29,6 +29,7 @@ pub extern "system" fn Java_org_prql_prql4j_PrqlCompiler_toSql(
         signature_comment: signature != 0,
         // TODO: add support for this
         color: false,
+        ..Default::default()
     };
     let result = prql_compiler::compile(&prql_query, &opt);
     java_string_with_exception(result, &mut env)
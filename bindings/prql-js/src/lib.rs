@@ -43,6 +43,11 @@ pub fn rq_to_sql(rq_json: &str) -> Option<String> {
     )
 }
 
+#[wasm_bindgen]
+pub fn format_prql(prql_query: &str) -> Option<String> {
+    return_or_throw(prql_compiler::format_prql(prql_query))
+}
+
 /// Compilation options for SQL backend of the compiler.
 #[wasm_bindgen]
 #[derive(Clone)]
@@ -112,6 +117,7 @@ impl From<CompileOptions> for prql_compiler::Options {
             signature_comment: o.signature_comment,
             // TODO: offer this option in the API
             color: false,
+            ..Default::default()
         }
     }
 }
@@ -75,6 +75,13 @@ describe("prql-js", () => {
     });
   });
 
+  describe("format_prql", () => {
+    it("should format prql, keeping comments", () => {
+      const res = prql.format_prql("from a | take 10 # a few");
+      assert.equal(res, "from a\ntake 10  # a few\n");
+    });
+  });
+
   describe("CompileOptions", () => {
     it("should be able to create from constructor", () => {
       const opts = new prql.CompileOptions();
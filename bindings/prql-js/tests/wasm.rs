@@ -0,0 +1,10 @@
+#![cfg(target_family = "wasm")]
+
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+fn compile() {
+    let sql = prql_js::compile("from t | take 1", None).unwrap();
+
+    assert!(sql.starts_with("SELECT\n  *\nFROM\n  t\nLIMIT\n  1\n"));
+}
@@ -329,5 +329,6 @@ fn convert_options(o: &Options) -> Result<prql_compiler::Options, prql_compiler:
         signature_comment: o.signature_comment,
         // TODO: add support for this
         color: false,
+        ..Default::default()
     })
 }
@@ -104,6 +104,7 @@ fn convert_options(
         signature_comment: o.signature_comment,
         // TODO: offer support
         color: false,
+        ..Default::default()
     })
 }
 
@@ -10,9 +10,12 @@ pub use literal::{Literal, ValueAndUnit};
 pub use ops::{BinOp, UnOp};
 
 use enum_as_inner::EnumAsInner;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::Span;
+use crate::{schema, Span};
 
 impl Expr {
     pub fn new(kind: ExprKind) -> Self {
@@ -24,11 +27,23 @@ impl Expr {
     }
 }
 
+impl JsonSchema for Expr {
+    fn schema_name() -> String {
+        "Expr".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let alias = gen.subschema_for::<String>();
+        schema::flattened_kind::<ExprKind>(gen, vec![("alias", alias, false)])
+    }
+}
+
 // The following code is tested by the tests_misc crate to match expr.rs in prql_compiler.
 
 /// Expr is anything that has a value and thus a type.
 /// If it cannot contain nested Exprs, is should be under [ExprKind::Literal].
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Expr {
     #[serde(flatten)]
     pub kind: ExprKind,
@@ -40,7 +55,10 @@ pub struct Expr {
     pub alias: Option<String>,
 }
 
-#[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, strum::AsRefStr)]
+#[derive(
+    Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, JsonSchema, strum::AsRefStr,
+)]
+#[serde(rename_all = "PascalCase")]
 pub enum ExprKind {
     Ident(Ident),
     Literal(Literal),
@@ -65,21 +83,24 @@ pub enum ExprKind {
     Internal(String),
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct BinaryExpr {
     pub left: Box<Expr>,
     pub op: BinOp,
     pub right: Box<Expr>,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct UnaryExpr {
     pub op: UnOp,
     pub expr: Box<Expr>,
 }
 
 /// Function call.
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct FuncCall {
     pub name: Box<Expr>,
     pub args: Vec<Expr>,
@@ -89,7 +110,8 @@ pub struct FuncCall {
 
 /// Function called with possibly missing positional arguments.
 /// May also contain environment that is needed to evaluate the body.
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct Func {
     /// Type requirement for the function body expression.
     pub return_ty: Option<Box<Expr>>,
@@ -104,7 +126,8 @@ pub struct Func {
     pub named_params: Vec<FuncParam>,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct FuncParam {
     pub name: String,
 
@@ -115,7 +138,8 @@ pub struct FuncParam {
 }
 
 /// A value and a series of functions that are to be applied to that value one after another.
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct Pipeline {
     pub exprs: Vec<Expr>,
 }
@@ -1,5 +1,8 @@
 use std::fmt::Write;
 
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{ArrayValidation, InstanceType, Schema, SchemaObject};
+use schemars::JsonSchema;
 use serde::{self, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
 
 /// A name. Generally columns, tables, functions, variables.
@@ -126,6 +129,26 @@ impl<'de> Deserialize<'de> for Ident {
     }
 }
 
+impl JsonSchema for Ident {
+    fn schema_name() -> String {
+        "Ident".to_string()
+    }
+
+    /// The parts of the ident, as serialized by [Ident::serialize].
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::Array.into()),
+            array: Some(Box::new(ArrayValidation {
+                items: Some(gen.subschema_for::<String>().into()),
+                min_items: Some(1),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
 pub fn display_ident(f: &mut std::fmt::Formatter, ident: &Ident) -> Result<(), std::fmt::Error> {
     for part in &ident.path {
         display_ident_part(f, part)?;
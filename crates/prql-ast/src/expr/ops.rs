@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(
@@ -9,9 +10,11 @@ use serde::{Deserialize, Serialize};
     Hash,
     Serialize,
     Deserialize,
+    JsonSchema,
     strum::Display,
     strum::EnumString,
 )]
+#[serde(rename_all = "PascalCase")]
 pub enum UnOp {
     #[strum(to_string = "-")]
     Neg,
@@ -32,9 +35,11 @@ pub enum UnOp {
     Hash,
     Serialize,
     Deserialize,
+    JsonSchema,
     strum::Display,
     strum::EnumString,
 )]
+#[serde(rename_all = "PascalCase")]
 pub enum BinOp {
     #[strum(to_string = "*")]
     Mul,
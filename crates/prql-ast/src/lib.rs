@@ -1,4 +1,5 @@
 pub mod expr;
+mod schema;
 mod span;
 pub mod stmt;
 
@@ -0,0 +1,42 @@
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
+
+/// Schema of a struct whose `kind` is an externally tagged enum that is
+/// flattened into it.
+///
+/// Each variant of the enum allows only its own tag, so the other fields of the
+/// struct are added to every variant, rather than next to them.
+pub(crate) fn flattened_kind<K: JsonSchema>(
+    gen: &mut SchemaGenerator,
+    fields: Vec<(&str, Schema, bool)>,
+) -> Schema {
+    let kind = K::json_schema(gen).into_object();
+    let variants = (kind.subschemas)
+        .and_then(|subschemas| subschemas.one_of)
+        .unwrap_or_default();
+
+    let variants = variants
+        .into_iter()
+        .map(|variant| {
+            let mut variant = variant.into_object();
+            let object = variant.object();
+            for (name, schema, required) in &fields {
+                object.properties.insert(name.to_string(), schema.clone());
+                if *required {
+                    object.required.insert(name.to_string());
+                }
+            }
+            variant.into()
+        })
+        .collect();
+
+    SchemaObject {
+        subschemas: Some(Box::new(SubschemaValidation {
+            one_of: Some(variants),
+            ..Default::default()
+        })),
+        ..Default::default()
+    }
+    .into()
+}
@@ -1,27 +1,45 @@
 use std::collections::HashMap;
 
 use enum_as_inner::EnumAsInner;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
 use semver::VersionReq;
 use serde::{Deserialize, Serialize};
 
-use crate::{expr::Expr, Span};
+use crate::{expr::Expr, schema, Span};
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
 pub struct QueryDef {
+    #[schemars(with = "Option<String>")]
     pub version: Option<VersionReq>,
     #[serde(default)]
     pub other: HashMap<String, String>,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
 pub enum VarDefKind {
     Let,
     Into,
 }
 
+impl JsonSchema for Stmt {
+    fn schema_name() -> String {
+        "Stmt".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let annotations = gen.subschema_for::<Vec<Annotation>>();
+        schema::flattened_kind::<StmtKind>(gen, vec![("annotations", annotations, true)])
+    }
+}
+
 // The following code is tested by the tests_misc crate to match stmt.rs in prql_compiler.
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Stmt {
     #[serde(flatten)]
     pub kind: StmtKind,
@@ -31,7 +49,8 @@ pub struct Stmt {
     pub annotations: Vec<Annotation>,
 }
 
-#[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
 pub enum StmtKind {
     QueryDef(Box<QueryDef>),
     Main(Box<Expr>),
@@ -40,7 +59,8 @@ pub enum StmtKind {
     ModuleDef(ModuleDef),
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct VarDef {
     pub name: String,
     pub value: Box<Expr>,
@@ -48,19 +68,22 @@ pub struct VarDef {
     pub kind: VarDefKind,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct TypeDef {
     pub name: String,
     pub value: Option<Box<Expr>>,
 }
 
-#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct ModuleDef {
     pub name: String,
     pub stmts: Vec<Stmt>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct Annotation {
     pub expr: Box<Expr>,
 }
@@ -1,7 +1,6 @@
-//! A very simple benchmark for a single example, basically copied from the
-//! [criterion quick
-//! start](https://github.com/bheisler/criterion.rs#quickstart). At time of
-//! writing, this ran at 300mics on my laptop.
+//! Benchmarks of each stage of the compiler — parsing, resolving and
+//! translating into SQL — on queries of a range of shapes, which live in
+//! `benches/queries`.
 
 // TODO: add a small GHA workflow for this to run on "full tests".
 
@@ -13,16 +12,35 @@ cfg_if::cfg_if! {
     if #[cfg(target_family = "wasm")] {
         fn main() {    panic!("Not used in wasm (but it seems cargo insists we have a `main` function).")}
     } else {
-        use criterion::{criterion_group, criterion_main, Criterion};
-        use prql_compiler::{ErrorMessages, Options, compile};
+        use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+        use prql_compiler::{compile, compile_stages, pl_to_rq, prql_to_pl, rq_to_sql, Options};
 
-        const CONTENT: &str = include_str!("../examples/compile-files/queries/variables.prql");
-        fn compile_query() -> Result<String, ErrorMessages> {
-            compile(CONTENT, &Options::default())
-        }
+        const QUERIES: [(&str, &str); 5] = [
+            ("variables", include_str!("../examples/compile-files/queries/variables.prql")),
+            ("simple", include_str!("queries/simple.prql")),
+            ("multi_join", include_str!("queries/multi_join.prql")),
+            ("window", include_str!("queries/window.prql")),
+            ("nested", include_str!("queries/nested.prql")),
+        ];
 
         fn criterion_benchmark(c: &mut Criterion) {
-            c.bench_function("variables-query", |b| b.iter(compile_query));
+            let options = Options::default();
+
+            for (name, prql) in QUERIES {
+                let (pl, rq, _) = compile_stages(prql, &options).unwrap();
+
+                let mut group = c.benchmark_group(name);
+                group.bench_function("parse", |b| b.iter(|| prql_to_pl(prql)));
+                group.bench_function("resolve", |b| {
+                    b.iter_batched(|| pl.clone(), pl_to_rq, BatchSize::SmallInput)
+                });
+                group.bench_function("translate", |b| {
+                    let translate = |rq| rq_to_sql(rq, &options);
+                    b.iter_batched(|| rq.clone(), translate, BatchSize::SmallInput)
+                });
+                group.bench_function("compile", |b| b.iter(|| compile(prql, &options)));
+                group.finish();
+            }
         }
 
         criterion_group!(benches, criterion_benchmark);
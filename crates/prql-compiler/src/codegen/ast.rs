@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 
 use prql_ast::expr::*;
@@ -294,7 +295,8 @@ impl WriteSource for Stmt {
                 if let Some(version) = &query.version {
                     r += &format!(r#" version:"{}""#, version);
                 }
-                for (key, value) in &query.other {
+                // sorted, so the output does not depend on the order of the map
+                for (key, value) in query.other.iter().sorted() {
                     r += &format!(" {key}:{value}");
                 }
                 r += "\n";
@@ -0,0 +1,183 @@
+//! Comments are not part of the AST, so they are lost when PRQL is parsed and
+//! written back. To preserve them in `format`, we lex both the original source
+//! and the formatted code and attach each comment to the token that it
+//! follows or precedes.
+
+use anyhow::Result;
+use prql_parser::chumsky::Parser;
+use prql_parser::lexer::{lexer, Token};
+
+use crate::error::{Error, WithErrorInfo};
+
+struct Comment {
+    text: String,
+
+    /// Number of significant tokens before this comment.
+    anchor: usize,
+
+    /// True when the comment is on its own line. Otherwise it trails the
+    /// preceding token.
+    own_line: bool,
+}
+
+/// Inserts comments from `source` into `formatted`, which should be the result
+/// of formatting `source`.
+///
+/// If the token sequences of the two do not match, comments cannot be placed
+/// reliably, so an error is returned rather than dropping them.
+pub fn insert_comments(source: &str, formatted: &str) -> Result<String> {
+    let Ok(source_tokens) = lexer().parse(source) else {
+        return Err(cannot_place_comments());
+    };
+
+    let mut comments = Vec::new();
+    let mut source_significant = Vec::new();
+    let mut prev: Option<&Token> = None;
+    for (token, _) in &source_tokens {
+        if let Token::Comment(text) = token {
+            comments.push(Comment {
+                text: text.clone(),
+                anchor: source_significant.len(),
+                own_line: matches!(prev, None | Some(Token::NewLine | Token::Comment(_))),
+            });
+        } else if is_significant(token) {
+            source_significant.push(token);
+        }
+        prev = Some(token);
+    }
+    if comments.is_empty() {
+        return Ok(formatted.to_string());
+    }
+
+    let Ok(formatted_tokens) = lexer().parse(formatted) else {
+        return Err(cannot_place_comments());
+    };
+
+    // line of each char of the formatted code
+    let line_of_char: Vec<usize> = formatted
+        .chars()
+        .scan(0, |line, c| {
+            let res = *line;
+            if c == '\n' {
+                *line += 1;
+            }
+            Some(res)
+        })
+        .collect();
+
+    let formatted_significant: Vec<_> = formatted_tokens
+        .iter()
+        .filter(|(t, _)| is_significant(t))
+        .map(|(t, span)| (t, line_of_char[span.start]))
+        .collect();
+
+    let matches = source_significant.len() == formatted_significant.len()
+        && (source_significant.iter())
+            .zip(&formatted_significant)
+            .all(|(s, (f, _))| is_similar(s, f));
+    if !matches {
+        return Err(cannot_place_comments());
+    }
+
+    let lines: Vec<&str> = formatted.lines().collect();
+    let mut before = vec![Vec::new(); lines.len() + 1];
+    let mut after = vec![Vec::new(); lines.len()];
+    for comment in comments {
+        if comment.own_line || comment.anchor == 0 {
+            let line =
+                (formatted_significant.get(comment.anchor)).map_or(lines.len(), |(_, line)| *line);
+            before[line].push(comment.text);
+        } else {
+            let (_, line) = formatted_significant[comment.anchor - 1];
+            after[line].push(comment.text);
+        }
+    }
+
+    let mut res = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let indent = &line[..line.len() - line.trim_start().len()];
+
+        for text in &before[index] {
+            res += &format!("{indent}#{text}\n");
+        }
+
+        res += line;
+        let mut trailing = after[index].iter();
+        if let Some(text) = trailing.next() {
+            res += &format!("  #{text}");
+        }
+        res += "\n";
+        for text in trailing {
+            res += &format!("{indent}#{text}\n");
+        }
+    }
+    for text in &before[lines.len()] {
+        res += &format!("#{text}\n");
+    }
+
+    if !formatted.ends_with('\n') {
+        res.pop();
+    }
+    Ok(res)
+}
+
+fn cannot_place_comments() -> anyhow::Error {
+    Error::new_simple("cannot place the comments of the source into the formatted code")
+        .push_hint("the source is left unformatted, so the comments are not lost")
+        .into()
+}
+
+/// Tokens that are preserved by formatting. Others (new lines, pipes,
+/// parenthesis and commas) may be added or removed by the formatter.
+fn is_significant(token: &Token) -> bool {
+    !matches!(
+        token,
+        Token::NewLine | Token::Comment(_) | Token::Control('(' | ')' | '|' | ',')
+    )
+}
+
+fn is_similar(a: &Token, b: &Token) -> bool {
+    match (a, b) {
+        (Token::Ident(a), Token::Ident(b)) => a == b,
+        (Token::Keyword(a), Token::Keyword(b)) => a == b,
+        (Token::Control(a), Token::Control(b)) => a == b,
+        // literals may be re-formatted and ranges may change spacing
+        _ => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_snapshot;
+
+    use super::insert_comments;
+
+    #[test]
+    fn test_insert_comments() {
+        let source = r#"
+# Tracks that are longer than 5 minutes
+from tracks
+# only the long ones
+filter length > 300 # in seconds
+select {title, length}  # that's all we need
+"#;
+        let formatted = "from tracks\nfilter length > 300\nselect {title, length}\n";
+
+        assert_snapshot!(insert_comments(source, formatted).unwrap(), @r###"
+        # Tracks that are longer than 5 minutes
+        from tracks
+        # only the long ones
+        filter length > 300  # in seconds
+        select {title, length}  # that's all we need
+        "###);
+    }
+
+    #[test]
+    fn test_insert_comments_mismatch() {
+        // comments are never dropped, even if they cannot be placed
+        let source = "from tracks  # all of them\n";
+        let formatted = "from albums\n";
+
+        assert!(insert_comments(source, formatted).is_err());
+    }
+}
@@ -1,8 +1,10 @@
 mod ast;
+mod comments;
 mod literal;
 mod pl;
 
 pub use ast::{write_expr, write_stmts};
+pub use comments::insert_comments;
 pub use literal::DisplayLiteral;
 
 pub trait WriteSource {
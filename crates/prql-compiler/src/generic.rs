@@ -6,6 +6,10 @@ use crate::ir::generic::Range;
 pub struct ColumnSort<T> {
     pub direction: SortDirection,
     pub column: T,
+
+    /// Position of nulls. When None, the database default is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nulls: Option<NullsOrder>,
 }
 
 #[derive(Debug, Clone, Serialize, Default, Deserialize, PartialEq, Eq)]
@@ -15,6 +19,12 @@ pub enum SortDirection {
     Desc,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct WindowFrame<T> {
     pub kind: WindowKind,
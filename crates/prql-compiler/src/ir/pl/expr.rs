@@ -16,6 +16,7 @@ use super::{Lineage, TransformCall, Ty, TyOrExpr};
 /// Expr is anything that has a value and thus a type.
 /// If it cannot contain nested Exprs, is should be under [ExprKind::Literal].
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Expr {
     #[serde(flatten)]
     pub kind: ExprKind,
@@ -61,6 +62,7 @@ pub struct Expr {
 }
 
 #[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, strum::AsRefStr)]
+#[serde(rename_all = "PascalCase")]
 pub enum ExprKind {
     Ident(Ident),
     All {
@@ -95,6 +97,7 @@ pub enum ExprKind {
 
 /// Function call.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct FuncCall {
     pub name: Box<Expr>,
     pub args: Vec<Expr>,
@@ -105,6 +108,7 @@ pub struct FuncCall {
 /// Function called with possibly missing positional arguments.
 /// May also contain environment that is needed to evaluate the body.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Func {
     /// Name of the function. Used for user-facing messages only.
     pub name_hint: Option<Ident>,
@@ -130,6 +134,7 @@ pub struct Func {
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct FuncParam {
     pub name: String,
 
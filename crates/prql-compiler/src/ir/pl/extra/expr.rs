@@ -17,6 +17,7 @@ impl FuncCall {
 
 /// An expression that may have already been converted to a type.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, EnumAsInner)]
+#[serde(rename_all = "PascalCase")]
 pub enum TyOrExpr {
     Ty(Ty),
     Expr(Box<Expr>),
@@ -35,6 +36,7 @@ pub type ColumnSort = crate::generic::ColumnSort<Box<Expr>>;
 
 /// FuncCall with better typing. Returns the modified table.
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TransformCall {
     pub input: Box<Expr>,
 
@@ -54,6 +56,7 @@ pub struct TransformCall {
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, strum::AsRefStr, EnumAsInner)]
+#[serde(rename_all = "PascalCase")]
 pub enum TransformKind {
     Derive {
         assigns: Vec<Expr>,
@@ -61,6 +64,11 @@ pub enum TransformKind {
     Select {
         assigns: Vec<Expr>,
     },
+    /// Does not change the relation, but makes following selects retain
+    /// `columns`.
+    Keep {
+        columns: Vec<Expr>,
+    },
     Filter {
         filter: Box<Expr>,
     },
@@ -73,6 +81,24 @@ pub enum TransformKind {
     Take {
         range: Range,
     },
+    /// Keeps the last `rows` rows, according to the preceding sort.
+    Tail {
+        rows: i64,
+    },
+    /// Keeps a random sample of rows, of which there are approximately
+    /// `percent` percent of the input.
+    Sample {
+        percent: i64,
+    },
+    /// Keeps `rows` random rows.
+    SampleRows {
+        rows: i64,
+    },
+    /// Keeps the first row of each group of rows that have the same values
+    /// of `by`, according to the preceding sort.
+    DistinctOn {
+        by: Vec<Expr>,
+    },
     Join {
         side: JoinSide,
         with: Box<Expr>,
@@ -85,6 +111,8 @@ pub enum TransformKind {
     Window {
         kind: WindowKind,
         range: Range,
+        /// Partitions of the window, in addition to the keys of an enclosing `group`.
+        partition: Vec<Expr>,
         pipeline: Box<Expr>,
     },
     Append(Box<Expr>),
@@ -98,6 +126,7 @@ pub enum TransformKind {
 /// > since they can't be serialized to YAML at the moment. We may add this back
 /// > in the future, or flatten it up to [crate::ast::rq::RelationKind]
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum TableExternRef {
     /// Actual table in a database, that we can refer to by name in SQL
     LocalTable(String),
@@ -110,6 +139,7 @@ pub enum TableExternRef {
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum JoinSide {
     Inner,
     Left,
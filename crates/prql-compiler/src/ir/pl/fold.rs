@@ -201,6 +201,7 @@ pub fn fold_column_sort<T: ?Sized + PlFold>(
     Ok(ColumnSort {
         direction: sort_column.direction,
         column: Box::new(fold.fold_expr(*sort_column.column)?),
+        nulls: sort_column.nulls,
     })
 }
 
@@ -241,6 +242,9 @@ pub fn fold_transform_kind<T: ?Sized + PlFold>(
         Select { assigns } => Select {
             assigns: fold.fold_exprs(assigns)?,
         },
+        Keep { columns } => Keep {
+            columns: fold.fold_exprs(columns)?,
+        },
         Filter { filter } => Filter {
             filter: Box::new(fold.fold_expr(*filter)?),
         },
@@ -253,6 +257,12 @@ pub fn fold_transform_kind<T: ?Sized + PlFold>(
         Take { range } => Take {
             range: fold_range(fold, range)?,
         },
+        Tail { rows } => Tail { rows },
+        Sample { percent } => Sample { percent },
+        SampleRows { rows } => SampleRows { rows },
+        DistinctOn { by } => DistinctOn {
+            by: fold.fold_exprs(by)?,
+        },
         Join { side, with, filter } => Join {
             side,
             with: Box::new(fold.fold_expr(*with)?),
@@ -266,10 +276,12 @@ pub fn fold_transform_kind<T: ?Sized + PlFold>(
         Window {
             kind,
             range,
+            partition,
             pipeline,
         } => Window {
             kind,
             range: fold_range(fold, range)?,
+            partition: fold.fold_exprs(partition)?,
             pipeline: Box::new(fold.fold_expr(*pipeline)?),
         },
         Loop(pipeline) => Loop(Box::new(fold.fold_expr(*pipeline)?)),
@@ -10,6 +10,7 @@ use super::Ident;
 /// Represents the object that is manipulated by the pipeline transforms.
 /// Similar to a view in a database or a data frame.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Lineage {
     pub columns: Vec<LineageColumn>,
 
@@ -21,6 +22,7 @@ pub struct Lineage {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct LineageInput {
     /// Id of the node in AST that declares this input.
     pub id: usize,
@@ -33,6 +35,7 @@ pub struct LineageInput {
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, EnumAsInner)]
+#[serde(rename_all = "PascalCase")]
 pub enum LineageColumn {
     Single {
         name: Option<Ident>,
@@ -8,6 +8,7 @@ pub use prql_ast::stmt::QueryDef;
 use super::expr::Expr;
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum VarDefKind {
     Let,
     Into,
@@ -17,6 +18,7 @@ pub enum VarDefKind {
 // The following code is tested by the tests_misc crate to match stmt.rs in prql_ast.
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Stmt {
     #[serde(skip)]
     pub id: Option<usize>,
@@ -29,6 +31,7 @@ pub struct Stmt {
 }
 
 #[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub enum StmtKind {
     QueryDef(Box<QueryDef>),
     VarDef(VarDef),
@@ -37,6 +40,7 @@ pub enum StmtKind {
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct VarDef {
     pub name: Option<String>,
     pub value: Box<Expr>,
@@ -45,18 +49,21 @@ pub struct VarDef {
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TypeDef {
     pub name: String,
     pub value: Option<Box<Expr>>,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct ModuleDef {
     pub name: String,
     pub stmts: Vec<Stmt>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Annotation {
     pub expr: Box<Expr>,
 }
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use super::Literal;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, EnumAsInner)]
+#[serde(rename_all = "PascalCase")]
 pub enum TyKind {
     /// Type of a built-in primitive type
     Primitive(PrimitiveSet),
@@ -31,6 +32,7 @@ pub enum TyKind {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, EnumAsInner)]
+#[serde(rename_all = "PascalCase")]
 pub enum TupleField {
     /// Named tuple element.
     Single(Option<String>, Option<Ty>),
@@ -41,6 +43,7 @@ pub enum TupleField {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Ty {
     pub kind: TyKind,
 
@@ -52,6 +55,7 @@ pub struct Ty {
 #[derive(
     Debug, Clone, Serialize, Deserialize, PartialEq, Eq, strum::EnumString, strum::Display,
 )]
+#[serde(rename_all = "PascalCase")]
 pub enum PrimitiveSet {
     #[strum(to_string = "int")]
     Int,
@@ -71,6 +75,7 @@ pub enum PrimitiveSet {
 
 // Type of a function
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct TyFunc {
     pub args: Vec<Option<Ty>>,
     pub return_ty: Box<Option<Ty>>,
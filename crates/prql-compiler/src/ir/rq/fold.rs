@@ -186,6 +186,8 @@ pub fn fold_transform<T: ?Sized + RqFold>(
             sort: fold_column_sorts(fold, take.sort)?,
             range: take.range,
         }),
+        Sample { percent } => Sample { percent },
+        SampleRows { rows } => SampleRows { rows },
         Join { side, with, filter } => Join {
             side,
             with: fold.fold_table_ref(with)?,
@@ -207,6 +209,7 @@ pub fn fold_column_sorts<T: ?Sized + RqFold>(
             Ok(ColumnSort {
                 column: fold.fold_cid(s.column)?,
                 direction: s.direction,
+                nulls: s.nulls,
             })
         })
         .try_collect()
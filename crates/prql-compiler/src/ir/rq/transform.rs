@@ -20,6 +20,14 @@ pub enum Transform {
     },
     Sort(Vec<ColumnSort<CId>>),
     Take(Take),
+    /// Random sample of rows of a table, as a percentage of its rows.
+    Sample {
+        percent: i64,
+    },
+    /// Random sample of rows, with the given number of rows.
+    SampleRows {
+        rows: i64,
+    },
     Join {
         side: JoinSide,
         with: TableRef,
@@ -96,6 +96,7 @@ pub use error::{
 };
 pub use ir::Span;
 
+use error::WithErrorInfo;
 use once_cell::sync::Lazy;
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -125,6 +126,7 @@ pub static COMPILER_VERSION: Lazy<Version> = Lazy::new(|| {
 ///     target: Target::Sql(Some(Dialect::SQLite)),
 ///     signature_comment: false,
 ///     color: false,
+///     ..Default::default()
 /// };
 /// let sql = compile(&prql, &opts).unwrap();
 /// println!("PRQL: {}\nSQLite: {}", prql, &sql);
@@ -137,7 +139,15 @@ pub fn compile(prql: &str, options: &Options) -> Result<String, ErrorMessages> {
     semantic::load_std_lib(&mut sources);
 
     parser::parse(&sources)
-        .and_then(|ast| semantic::resolve_and_lower(ast, &[]))
+        .and_then(|ast| {
+            // whitespace and comments do not produce any statements
+            if ast.sources.get(&PathBuf::new()).map_or(true, Vec::is_empty) {
+                return Err(Error::new_simple("empty query")
+                    .push_hint("a query starts with a relation, for example `from employees`")
+                    .into());
+            }
+            semantic::resolve_and_lower(ast, &[])
+        })
         .and_then(|rq| sql::compile(rq, options))
         .map_err(error::downcast)
         .map_err(|e| e.composed(&prql.into()))
@@ -183,7 +193,11 @@ impl FromStr for Target {
         Err(Error::new(Reason::NotFound {
             name: format!("{s:?}"),
             namespace: "target".to_string(),
-        }))
+        })
+        .push_hint(format!(
+            "available targets are: {}",
+            Target::names().join(", ")
+        )))
     }
 }
 
@@ -214,6 +228,76 @@ pub struct Options {
     ///   colors coming back from this library.
     /// - Strip colors from the output (possibly also with a library such as `anstream`)
     pub color: bool,
+
+    /// Wraps the query into a statement that displays its query plan,
+    /// instead of its results.
+    ///
+    /// Defaults to None.
+    pub explain: Option<sql::Explain>,
+
+    /// How GROUP BY refers to keys that are also in the projection. Falls
+    /// back to repeating the expressions when the dialect does not support
+    /// the style.
+    ///
+    /// Defaults to [sql::GroupByStyle::Expressions].
+    pub group_by_style: sql::GroupByStyle,
+
+    /// How conditions of joins are expressed. Falls back to `ON` when the
+    /// dialect does not support `USING`.
+    ///
+    /// Defaults to [sql::JoinStyle::On].
+    pub join_style: sql::JoinStyle,
+
+    /// Emits relations that are referenced only once as sub-queries in
+    /// `FROM` or `JOIN`, instead of declaring them with `WITH`.
+    ///
+    /// Defaults to false.
+    pub inline_single_use_ctes: bool,
+
+    /// Precedes each CTE that was introduced by splitting a pipeline into
+    /// multiple SELECTs with a comment that explains the split, i.e.
+    /// `-- boundary: aggregate after take`.
+    ///
+    /// Defaults to false.
+    pub explain_splits: bool,
+
+    /// Which identifiers are quoted.
+    ///
+    /// Defaults to [sql::QuotingStyle::CasePreserving].
+    pub quoting_style: sql::QuotingStyle,
+
+    /// Emits casts that return NULL when a value cannot be converted, instead
+    /// of failing the query. Currently only BigQuery has these (`SAFE_CAST`);
+    /// for other dialects, plain casts are emitted.
+    ///
+    /// Defaults to false.
+    pub safe_cast: bool,
+
+    /// Computes aggregations that the dialect cannot compute exactly with an
+    /// approximation, if it has one (i.e. `median` in BigQuery). Otherwise,
+    /// these aggregations fail to compile.
+    ///
+    /// Defaults to false.
+    pub allow_approximations: bool,
+
+    /// Version of the SQL standard that the generated SQL must conform to.
+    /// Queries that need newer constructs fail to compile.
+    ///
+    /// Defaults to [sql::SqlStandard::Sql2016].
+    pub sql_standard: sql::SqlStandard,
+
+    /// Computes the arguments of window functions in a preceding SELECT, so
+    /// that window functions refer to them by name instead of repeating
+    /// their expressions.
+    ///
+    /// Defaults to false.
+    pub materialize_window_args: bool,
+
+    /// Wraps the whole query, including its `WITH` clause, in parentheses
+    /// and omits the trailing new line, so it can be embedded as a subquery.
+    ///
+    /// Defaults to false.
+    pub wrap_in_parens: bool,
 }
 
 impl Default for Options {
@@ -223,6 +307,17 @@ impl Default for Options {
             target: Target::Sql(None),
             signature_comment: true,
             color: false,
+            explain: None,
+            group_by_style: sql::GroupByStyle::Expressions,
+            join_style: sql::JoinStyle::On,
+            inline_single_use_ctes: false,
+            explain_splits: false,
+            quoting_style: sql::QuotingStyle::CasePreserving,
+            safe_cast: false,
+            allow_approximations: false,
+            sql_standard: sql::SqlStandard::Sql2016,
+            materialize_window_args: false,
+            wrap_in_parens: false,
         }
     }
 }
@@ -251,6 +346,61 @@ impl Options {
         self
     }
 
+    pub fn with_explain(mut self, explain: sql::Explain) -> Self {
+        self.explain = Some(explain);
+        self
+    }
+
+    pub fn with_group_by_style(mut self, group_by_style: sql::GroupByStyle) -> Self {
+        self.group_by_style = group_by_style;
+        self
+    }
+
+    pub fn with_join_style(mut self, join_style: sql::JoinStyle) -> Self {
+        self.join_style = join_style;
+        self
+    }
+
+    pub fn with_inline_single_use_ctes(mut self, inline_single_use_ctes: bool) -> Self {
+        self.inline_single_use_ctes = inline_single_use_ctes;
+        self
+    }
+
+    pub fn with_explain_splits(mut self, explain_splits: bool) -> Self {
+        self.explain_splits = explain_splits;
+        self
+    }
+
+    pub fn with_quoting_style(mut self, quoting_style: sql::QuotingStyle) -> Self {
+        self.quoting_style = quoting_style;
+        self
+    }
+
+    pub fn with_safe_cast(mut self, safe_cast: bool) -> Self {
+        self.safe_cast = safe_cast;
+        self
+    }
+
+    pub fn with_allow_approximations(mut self, allow_approximations: bool) -> Self {
+        self.allow_approximations = allow_approximations;
+        self
+    }
+
+    pub fn with_sql_standard(mut self, sql_standard: sql::SqlStandard) -> Self {
+        self.sql_standard = sql_standard;
+        self
+    }
+
+    pub fn with_materialize_window_args(mut self, materialize_window_args: bool) -> Self {
+        self.materialize_window_args = materialize_window_args;
+        self
+    }
+
+    pub fn with_wrap_in_parens(mut self, wrap_in_parens: bool) -> Self {
+        self.wrap_in_parens = wrap_in_parens;
+        self
+    }
+
     #[deprecated(note = "`color` now has no effect; see `Options` docs for more details")]
     pub fn with_color(mut self, color: bool) -> Self {
         self.color = color;
@@ -262,6 +412,88 @@ impl Options {
 #[cfg(doctest)]
 pub struct ReadmeDoctests;
 
+/// Compile each relation declared with `let` into a separate SQL query, as
+/// tools such as dbt expect one query per model.
+///
+/// Returns pairs of model names and their SQL, in the order of declaration. The
+/// main pipeline, if any, is compiled as the model `main`. Models that use
+/// other models refer to them by name, rather than including them as CTEs.
+pub fn compile_models(
+    prql: &str,
+    options: &Options,
+) -> Result<Vec<(String, String)>, ErrorMessages> {
+    let mut sources = SourceTree::from(prql);
+    semantic::load_std_lib(&mut sources);
+
+    parser::parse(&sources)
+        .and_then(semantic::resolve_and_lower_models)
+        .and_then(|models| {
+            (models.into_iter())
+                .map(|(name, rq)| Ok((name, sql::compile(rq, options)?)))
+                .collect()
+        })
+        .map_err(error::downcast)
+        .map_err(|e| e.composed(&prql.into()))
+}
+
+/// Compile a PRQL string into a SQL string, returning the result of each
+/// stage along the way: the PL AST, the RQ it resolves to, and the SQL.
+///
+/// This is equivalent to calling [prql_to_pl], [pl_to_rq] and [rq_to_sql] in
+/// turn, which is useful for timing or inspecting the stages separately.
+pub fn compile_stages(
+    prql: &str,
+    options: &Options,
+) -> Result<(Vec<prql_ast::stmt::Stmt>, ir::rq::RelationalQuery, String), ErrorMessages> {
+    let pl = prql_to_pl(prql)?;
+    let rq = pl_to_rq(pl.clone()).map_err(|e| e.composed(&prql.into()))?;
+    let sql = rq_to_sql(rq.clone(), options).map_err(|e| e.composed(&prql.into()))?;
+    Ok((pl, rq, sql))
+}
+
+/// Compile each PRQL code block of a Markdown document, for example to test
+/// the examples of documentation.
+///
+/// A block starts with a line of ```` ``` ```` followed by `fence` (such as
+/// `prql`) and ends with a line of ```` ``` ````. Returns the span of each
+/// block's contents within `text`, along with the result of compiling it.
+pub fn compile_all_blocks(
+    text: &str,
+    fence: &str,
+    options: &Options,
+) -> Vec<(Span, Result<String, ErrorMessages>)> {
+    let mut blocks = Vec::new();
+
+    let mut block_start = None;
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let line_end = line_start + line.len();
+
+        match block_start {
+            None => {
+                let tag = line.trim().strip_prefix("```");
+                let tag = tag.and_then(|t| t.split_whitespace().next());
+                if tag == Some(fence) {
+                    block_start = Some(line_end);
+                }
+            }
+            Some(start) if line.trim() == "```" => {
+                let span = Span {
+                    start,
+                    end: line_start,
+                    source_id: 0,
+                };
+                blocks.push((span, compile(&text[start..line_start], options)));
+                block_start = None;
+            }
+            Some(_) => {}
+        }
+
+        line_start = line_end;
+    }
+    blocks
+}
+
 /// Parse PRQL into a PL AST
 pub fn prql_to_pl(prql: &str) -> Result<Vec<prql_ast::stmt::Stmt>, ErrorMessages> {
     let sources = SourceTree::from(prql);
@@ -272,6 +504,19 @@ pub fn prql_to_pl(prql: &str) -> Result<Vec<prql_ast::stmt::Stmt>, ErrorMessages
         .map_err(|e| e.composed(&prql.into()))
 }
 
+/// Parse PRQL into a PL AST, recovering from syntax errors.
+///
+/// Unlike [prql_to_pl], all syntax errors are reported, along with the AST of
+/// the parts of the query that could be parsed, which is useful for editors.
+pub fn prql_to_pl_recovering(prql: &str) -> (Option<Vec<prql_ast::stmt::Stmt>>, ErrorMessages) {
+    let sources = SourceTree::from(prql);
+
+    let (stmts, errors) = parser::parse_recovering(&sources);
+    let errors = error::downcast(error::Errors(errors).into()).composed(&sources);
+
+    (stmts.sources.into_values().next(), errors)
+}
+
 /// Parse PRQL into a PL AST
 pub fn prql_to_pl_tree(
     prql: &SourceTree,
@@ -281,6 +526,16 @@ pub fn prql_to_pl_tree(
         .map_err(|e| e.composed(prql))
 }
 
+/// Perform semantic analysis of PL, without converting it to RQ.
+///
+/// In the result, names refer to their declarations and expressions have
+/// their types and lineage inferred. This is useful for tools that inspect
+/// queries without compiling them; see [json::from_resolved] to serialize it.
+pub fn resolve(pl: Vec<prql_ast::stmt::Stmt>) -> Result<semantic::RootModule, ErrorMessages> {
+    let source_tree = SourceTree::single(PathBuf::new(), pl);
+    semantic::resolve(source_tree, Default::default()).map_err(error::downcast)
+}
+
 /// Perform semantic analysis and convert PL to RQ.
 pub fn pl_to_rq(pl: Vec<prql_ast::stmt::Stmt>) -> Result<ir::rq::RelationalQuery, ErrorMessages> {
     let source_tree = SourceTree::single(PathBuf::new(), pl);
@@ -295,6 +550,28 @@ pub fn pl_to_rq_tree(
     semantic::resolve_and_lower(pl, main_path).map_err(error::downcast)
 }
 
+/// Resolve PRQL and return the columns of the relation produced by its main
+/// pipeline.
+///
+/// # Example
+///
+/// ```
+/// use prql_compiler::{resolve_frame, semantic::ColumnInfo};
+///
+/// let columns = resolve_frame("from e | select {a, b = c}").unwrap();
+/// assert_eq!(columns[1], ColumnInfo::Single { name: Some("b".to_string()), table: None });
+/// ```
+pub fn resolve_frame(prql: &str) -> Result<Vec<semantic::ColumnInfo>, ErrorMessages> {
+    let mut sources = SourceTree::from(prql);
+    semantic::load_std_lib(&mut sources);
+
+    parser::parse(&sources)
+        .and_then(|ast| semantic::resolve(ast, Default::default()))
+        .and_then(|context| semantic::main_frame(&context, &[]))
+        .map_err(error::downcast)
+        .map_err(|e| e.composed(&prql.into()))
+}
+
 /// Generate SQL from RQ.
 pub fn rq_to_sql(rq: ir::rq::RelationalQuery, options: &Options) -> Result<String, ErrorMessages> {
     sql::compile(rq, options).map_err(error::downcast)
@@ -305,6 +582,16 @@ pub fn pl_to_prql(pl: Vec<prql_ast::stmt::Stmt>) -> Result<String, ErrorMessages
     Ok(codegen::write_stmts(&pl))
 }
 
+/// Format PRQL code, preserving its comments
+///
+/// Fails when the comments cannot be placed into the formatted code, rather
+/// than dropping them.
+pub fn format_prql(prql: &str) -> Result<String, ErrorMessages> {
+    let pl = prql_to_pl(prql)?;
+    let formatted = codegen::write_stmts(&pl);
+    codegen::insert_comments(prql, &formatted).map_err(error::downcast)
+}
+
 /// JSON serialization and deserialization functions
 pub mod json {
     use super::*;
@@ -319,6 +606,28 @@ pub mod json {
         serde_json::from_str(json).map_err(|e| anyhow::anyhow!(e).into())
     }
 
+    /// JSON Schema of the output of [from_pl], generated from the PL types
+    pub fn ast_json_schema() -> String {
+        let mut schema = schemars::schema_for!(Vec<prql_ast::stmt::Stmt>);
+
+        let metadata = schema.schema.metadata();
+        metadata.title = Some("PRQL PL".to_string());
+        metadata.description = Some(
+            "JSON representation of PL AST, as produced by `prql_compiler::json::from_pl`."
+                .to_string(),
+        );
+
+        serde_json::to_string_pretty(&schema).unwrap()
+    }
+
+    /// JSON serialization of the main pipeline of a resolved query
+    pub fn from_resolved(resolved: &semantic::RootModule) -> Result<String, ErrorMessages> {
+        let (main, _) = resolved.find_main_rel(&[]).map_err(|hint| {
+            anyhow::anyhow!(Error::new_simple("Missing main pipeline").with_hints(hint))
+        })?;
+        serde_json::to_string(main).map_err(|e| anyhow::anyhow!(e).into())
+    }
+
     /// JSON serialization
     pub fn from_rq(rq: ir::rq::RelationalQuery) -> Result<String, ErrorMessages> {
         serde_json::to_string(&rq).map_err(|e| anyhow::anyhow!(e).into())
@@ -380,8 +689,8 @@ impl<S: ToString> From<S> for SourceTree {
 
 #[cfg(test)]
 mod tests_lib {
-    use crate::Target;
-    use insta::assert_debug_snapshot;
+    use crate::{Options, Target};
+    use insta::{assert_debug_snapshot, assert_snapshot};
     use std::str::FromStr;
 
     #[test]
@@ -405,7 +714,9 @@ mod tests_lib {
                     name: "\"sql.poostgres\"",
                     namespace: "target",
                 },
-                hints: [],
+                hints: [
+                    "available targets are: sql.any, sql.ansi, sql.athena, sql.bigquery, sql.clickhouse, sql.duckdb, sql.generic, sql.mssql, sql.mysql, sql.oracle, sql.oracle12, sql.postgres, sql.redshift, sql.sqlite, sql.snowflake, sql.trino",
+                ],
                 code: None,
             },
         )
@@ -420,7 +731,9 @@ mod tests_lib {
                     name: "\"postgres\"",
                     namespace: "target",
                 },
-                hints: [],
+                hints: [
+                    "available targets are: sql.any, sql.ansi, sql.athena, sql.bigquery, sql.clickhouse, sql.duckdb, sql.generic, sql.mssql, sql.mysql, sql.oracle, sql.oracle12, sql.postgres, sql.redshift, sql.sqlite, sql.snowflake, sql.trino",
+                ],
                 code: None,
             },
         )
@@ -435,4 +748,102 @@ mod tests_lib {
             .map(|name| Target::from_str(&name))
             .collect();
     }
+
+    #[test]
+    fn test_resolve_frame() {
+        assert_debug_snapshot!(crate::resolve_frame("from e | select {a, b = c}").unwrap(), @r###"
+        [
+            Single {
+                name: Some(
+                    "a",
+                ),
+                table: Some(
+                    "e",
+                ),
+            },
+            Single {
+                name: Some(
+                    "b",
+                ),
+                table: None,
+            },
+        ]
+        "###);
+
+        assert_debug_snapshot!(crate::resolve_frame("from e").unwrap(), @r###"
+        [
+            All {
+                table: "e",
+            },
+        ]
+        "###);
+    }
+
+    /// Formatting an already formatted query keeps it unchanged, including
+    /// its comments.
+    #[test]
+    fn test_format_prql_round_trip() {
+        let prql = r#"# Tracks that are longer than 5 minutes
+from tracks
+# only the long ones
+filter length > 300  # in seconds
+select {title, length}  # that's all we need
+# end of query
+"#;
+
+        let formatted = crate::format_prql(prql).unwrap();
+        assert_eq!(formatted, prql);
+        assert_eq!(crate::format_prql(&formatted).unwrap(), prql);
+    }
+
+    #[test]
+    fn test_compile_all_blocks() {
+        let text = r#"# Examples
+
+```prql
+from employees
+take 10
+```
+
+Some other code:
+
+```sql
+SELECT 1
+```
+
+```prql no-eval
+from e | select x =
+```
+"#;
+
+        let options = Options::default().no_signature();
+        let blocks = crate::compile_all_blocks(text, "prql", &options);
+        assert_eq!(blocks.len(), 2);
+
+        let (span, sql) = &blocks[0];
+        assert_eq!(&text[span.start..span.end], "from employees\ntake 10\n");
+        assert_snapshot!(sql.as_ref().unwrap(), @r###"
+        SELECT
+          *
+        FROM
+          employees
+        LIMIT
+          10
+        "###);
+
+        let (span, sql) = &blocks[1];
+        assert_eq!(&text[span.start..span.end], "from e | select x =\n");
+        assert!(sql.is_err());
+    }
+
+    #[test]
+    fn test_json_from_resolved() {
+        let pl = crate::prql_to_pl("from employees | derive {gross = salary + bonus}").unwrap();
+        let resolved = crate::resolve(pl).unwrap();
+        let json = crate::json::from_resolved(&resolved).unwrap();
+
+        // unlike the PL, the resolved query contains lineage of relations
+        assert!(json.contains(r#""lineage""#));
+        assert!(json.contains(r#""alias":"gross""#));
+    }
 }
@@ -11,7 +11,19 @@ use crate::{codegen, SourceTree};
 use prql_parser::lexer::Token;
 
 pub fn parse(file_tree: &SourceTree<String>) -> Result<SourceTree<Vec<Stmt>>> {
+    let (res, errors) = parse_recovering(file_tree);
+
+    if !errors.is_empty() {
+        return Err(Errors(errors).into());
+    }
+    Ok(res)
+}
+
+/// Like [parse], but also returns the statements that could be parsed when
+/// there are syntax errors.
+pub fn parse_recovering(file_tree: &SourceTree<String>) -> (SourceTree<Vec<Stmt>>, Vec<Error>) {
     let mut res = SourceTree::default();
+    let mut errors = Vec::new();
 
     let ids: HashMap<_, _> = file_tree.source_ids.iter().map(|(a, b)| (b, a)).collect();
     let mut id_gen = IdGenerator::<usize>::new();
@@ -21,28 +33,29 @@ pub fn parse(file_tree: &SourceTree<String>) -> Result<SourceTree<Vec<Stmt>>> {
             .get(path)
             .map(|x| **x)
             .unwrap_or_else(|| id_gen.gen() as u16);
-        let stmts = parse_source(source, id)?;
+        let (stmts, source_errors) = parse_source(source, id);
+        errors.extend(source_errors);
 
-        res.sources.insert(path.clone(), stmts);
+        if let Some(stmts) = stmts {
+            res.sources.insert(path.clone(), stmts);
+        }
         res.source_ids.insert(id, path.clone());
     }
-    Ok(res)
+    (res, errors)
 }
 
-fn parse_source(source: &str, source_id: u16) -> Result<Vec<prql_ast::stmt::Stmt>> {
-    let stmts = prql_parser::parse_source(source, source_id).map_err(|errors| {
-        Errors(
-            errors
-                .into_iter()
-                .map(|err| match err {
-                    prql_parser::Error::Lexer(err) => convert_lexer_error(source, err, source_id),
-                    prql_parser::Error::Parser(err) => convert_parser_error(err),
-                })
-                .collect(),
-        )
-    })?;
+fn parse_source(source: &str, source_id: u16) -> (Option<Vec<Stmt>>, Vec<Error>) {
+    let (stmts, errors) = prql_parser::parse_source_recovering(source, source_id);
 
-    Ok(stmts)
+    let errors = errors
+        .into_iter()
+        .map(|err| match err {
+            prql_parser::Error::Lexer(err) => convert_lexer_error(source, err, source_id),
+            prql_parser::Error::Parser(err) => convert_parser_error(err),
+        })
+        .collect();
+
+    (stmts, errors)
 }
 
 fn convert_lexer_error(source: &str, e: chumsky::error::Cheap<char>, source_id: u16) -> Error {
@@ -178,6 +191,7 @@ impl std::fmt::Display for DisplayToken<'_> {
             Token::Interpolation(c, s) => {
                 write!(f, "{c}\"{}\"", s)
             }
+            Token::Comment(c) => write!(f, "#{c}"),
         }
     }
 }
@@ -186,12 +200,17 @@ impl std::fmt::Display for DisplayToken<'_> {
 mod tests {
     use insta::assert_debug_snapshot;
 
+    use crate::error::Errors;
     use prql_ast::stmt::Stmt;
 
     /// Helper that does not track source_ids
     #[cfg(test)]
     pub fn parse_single(source: &str) -> anyhow::Result<Vec<Stmt>> {
-        super::parse_source(source, 0)
+        let (stmts, errors) = super::parse_source(source, 0);
+        if !errors.is_empty() {
+            return Err(Errors(errors).into());
+        }
+        Ok(stmts.unwrap_or_default())
     }
 
     #[test]
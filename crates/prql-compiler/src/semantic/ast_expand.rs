@@ -2,9 +2,10 @@ use std::collections::HashMap;
 
 use anyhow::{anyhow, Result};
 use itertools::Itertools;
-use prql_ast::expr::{BinOp, BinaryExpr, Expr, ExprKind, Ident};
+use prql_ast::expr::{BinOp, BinaryExpr, Expr, ExprKind, Ident, Literal, ValueAndUnit};
 use prql_ast::stmt::{Annotation, Stmt, StmtKind, VarDefKind};
 
+use crate::error::{Error, WithErrorInfo};
 use crate::ir::pl::{self, new_binop};
 use crate::semantic::{NS_THAT, NS_THIS};
 
@@ -12,6 +13,16 @@ use crate::semantic::{NS_THAT, NS_THIS};
 pub fn expand_expr(expr: Expr) -> Result<pl::Expr> {
     let kind = match expr.kind {
         ExprKind::Ident(v) => pl::ExprKind::Ident(v),
+        ExprKind::Literal(Literal::ValueAndUnit(ValueAndUnit { n, unit })) => {
+            let Some(unit) = normalize_unit(&unit) else {
+                return Err(Error::new_simple(format!("unknown unit `{unit}`"))
+                    .push_hint(format!("expected one of {}", INTERVAL_UNITS.join(", ")))
+                    .with_span(expr.span)
+                    .into());
+            };
+            let unit = unit.to_string();
+            pl::ExprKind::Literal(Literal::ValueAndUnit(ValueAndUnit { n, unit }))
+        }
         ExprKind::Literal(v) => pl::ExprKind::Literal(v),
         ExprKind::Pipeline(v) => {
             let mut e = desugar_pipeline(v)?;
@@ -84,6 +95,31 @@ pub fn expand_expr(expr: Expr) -> Result<pl::Expr> {
     })
 }
 
+const INTERVAL_UNITS: &[&str] = &[
+    "microseconds",
+    "milliseconds",
+    "seconds",
+    "minutes",
+    "hours",
+    "days",
+    "weeks",
+    "months",
+    "years",
+];
+
+/// Validates the unit of a [ValueAndUnit] literal, mapping singular forms such
+/// as `1year` to the plural.
+fn normalize_unit(unit: &str) -> Option<&'static str> {
+    if unit == "%" {
+        return Some("%");
+    }
+    let singular = unit.strip_suffix('s').unwrap_or(unit);
+    INTERVAL_UNITS
+        .iter()
+        .find(|u| u.strip_suffix('s') == Some(singular))
+        .copied()
+}
+
 fn expand_exprs(exprs: Vec<prql_ast::expr::Expr>) -> Result<Vec<pl::Expr>> {
     exprs.into_iter().map(expand_expr).collect()
 }
@@ -146,7 +182,7 @@ fn expand_unary(
                     name: ident.name,
                 })
             };
-            return Ok(new_binop(left, &["std", "eq"], right).kind);
+            return Ok(new_binop(left, &["std", "eq_self"], right).kind);
         }
     };
     Ok(pl::ExprKind::FuncCall(pl::FuncCall::new_simple(
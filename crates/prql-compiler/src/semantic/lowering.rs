@@ -7,7 +7,7 @@ use enum_as_inner::EnumAsInner;
 use itertools::Itertools;
 
 use crate::error::{Error, Reason, Span, WithErrorInfo};
-use crate::generic::{ColumnSort, WindowFrame};
+use crate::generic::{ColumnSort, NullsOrder, SortDirection, WindowFrame};
 use crate::ir::generic::{InterpolateItem, Range, SwitchCase};
 use crate::ir::pl::{self, Ident, Lineage, LineageColumn, PlFold, QueryDef, TupleField};
 use crate::ir::rq::{
@@ -294,6 +294,21 @@ impl Lowerer {
 
                 log::debug!("lowering function table, columns = {columns:?}");
 
+                // the database reads the files when the query is planned, so
+                // their paths cannot be computed
+                let non_literal = (args.iter())
+                    .find(|a| !matches!(a.kind, pl::ExprKind::Literal(pl::Literal::String(_))));
+                if let Some(arg) = non_literal {
+                    let function = name.strip_prefix("std.").unwrap_or(&name);
+                    return Err(Error::new(Reason::Expected {
+                        who: Some(format!("`{function}`")),
+                        expected: "a string literal of the path".to_string(),
+                        found: format!("`{}`", write_pl(arg.clone())),
+                    })
+                    .with_span(arg.span)
+                    .into());
+                }
+
                 // lower the expr
                 let args = args.into_iter().map(|a| self.lower_expr(a)).try_collect()?;
                 let relation = rq::Relation {
@@ -489,6 +504,9 @@ impl Lowerer {
                 let cids = self.declare_as_columns(assigns, false)?;
                 self.pipeline.push(Transform::Select(cids));
             }
+            pl::TransformKind::Keep { .. } => {
+                // kept columns were added to the following selects by the resolver
+            }
             pl::TransformKind::Filter { filter, .. } => {
                 let filter = self.lower_expr(*filter)?;
 
@@ -519,6 +537,119 @@ impl Lowerer {
                     sort: window.sort,
                 }));
             }
+            pl::TransformKind::Tail { rows } => {
+                let window = self.window.take().unwrap_or_default();
+                if window.sort.is_empty() {
+                    return Err(
+                        Error::new_simple("`tail` requires the relation to be sorted")
+                            .push_hint("add a `sort` before `tail`")
+                            .with_span(ast.span)
+                            .into(),
+                    );
+                }
+
+                let range = Range {
+                    start: None,
+                    end: Some(rq::Expr {
+                        kind: rq::ExprKind::Literal(pl::Literal::Integer(rows)),
+                        span: None,
+                    }),
+                };
+                validate_take_range(&range, ast.span)?;
+
+                // the last rows are the first rows of the reversed sort
+                let reversed = window.sort.iter().cloned().map(reverse_sort).collect_vec();
+                let is_grouped = !window.partition.is_empty();
+
+                // sorts within groups don't apply to the result
+                if !is_grouped {
+                    self.pipeline.push(Transform::Sort(reversed.clone()));
+                }
+                self.pipeline.push(Transform::Take(rq::Take {
+                    range,
+                    partition: window.partition,
+                    sort: reversed,
+                }));
+                if !is_grouped {
+                    self.pipeline.push(Transform::Sort(window.sort));
+                }
+            }
+            pl::TransformKind::Sample { percent } => {
+                if !(0..=100).contains(&percent) {
+                    return Err(Error::new(Reason::Expected {
+                        who: Some("take".to_string()),
+                        expected: "a percentage between 0 and 100".to_string(),
+                        found: format!("{percent}%"),
+                    })
+                    .with_span(ast.span)
+                    .into());
+                }
+
+                // sampling is done when reading a table, before any other clause
+                let reads_table = match self.pipeline.as_slice() {
+                    [Transform::From(table_ref), rest @ ..] => {
+                        let table = self.table_buffer.iter().find(|t| t.id == table_ref.source);
+                        let is_extern =
+                            table.map_or(false, |t| t.relation.kind.as_extern_ref().is_some());
+                        is_extern && rest.iter().all(|t| t.as_select().is_some())
+                    }
+                    _ => false,
+                };
+                if !reads_table {
+                    return Err(Error::new_simple(
+                        "`take` with a percentage can only be applied directly to a table",
+                    )
+                    .push_hint("move it right after `from`")
+                    .with_span(ast.span)
+                    .into());
+                }
+
+                self.pipeline.push(Transform::Sample { percent });
+            }
+            pl::TransformKind::SampleRows { rows } => {
+                if rows < 0 {
+                    return Err(Error::new(Reason::Expected {
+                        who: Some("sample".to_string()),
+                        expected: "a non-negative number of rows".to_string(),
+                        found: rows.to_string(),
+                    })
+                    .with_span(ast.span)
+                    .into());
+                }
+
+                self.pipeline.push(Transform::SampleRows { rows });
+            }
+            pl::TransformKind::DistinctOn { by } => {
+                let window = self.window.take().unwrap_or_default();
+                let partition = self.declare_as_columns(by, false)?;
+
+                // the first row of each partition is determined by the sort,
+                // which has to begin with the partition (as Postgres requires)
+                let leading: HashSet<_> = (window.sort.iter())
+                    .take(partition.len())
+                    .map(|s| s.column)
+                    .collect();
+                if leading != partition.iter().cloned().collect() {
+                    let msg = "`distinct_on` requires a preceding `sort` that begins with the same columns";
+                    return Err(Error::new_simple(msg)
+                        .push_hint("add `sort` before `distinct_on`")
+                        .with_span(ast.span)
+                        .into());
+                }
+
+                let first = rq::Expr {
+                    kind: rq::ExprKind::Literal(pl::Literal::Integer(1)),
+                    span: None,
+                };
+                self.pipeline.push(Transform::Take(rq::Take {
+                    range: Range {
+                        start: Some(first.clone()),
+                        end: Some(first),
+                    },
+                    partition,
+                    sort: window.sort,
+                }));
+            }
             pl::TransformKind::Join {
                 side, with, filter, ..
             } => {
@@ -565,10 +696,20 @@ impl Lowerer {
 
     fn lower_sorts(&mut self, by: Vec<ColumnSort<Box<pl::Expr>>>) -> Result<Vec<ColumnSort<CId>>> {
         by.into_iter()
-            .map(|ColumnSort { column, direction }| {
-                let column = self.declare_as_column(*column, false)?;
-                Ok(ColumnSort { direction, column })
-            })
+            .map(
+                |ColumnSort {
+                     column,
+                     direction,
+                     nulls,
+                 }| {
+                    let column = self.declare_as_column(*column, false)?;
+                    Ok(ColumnSort {
+                        direction,
+                        column,
+                        nulls,
+                    })
+                },
+            )
             .try_collect()
     }
 
@@ -821,6 +962,23 @@ impl Lowerer {
             }
             pl::ExprKind::Param(id) => rq::ExprKind::Param(id),
 
+            // a function that did not receive all of its positional arguments
+            pl::ExprKind::Func(func) if func.args.len() < func.params.len() => {
+                let missing = (func.params[func.args.len()..].iter())
+                    .map(|p| format!("`{}`", p.name.split('.').last().unwrap_or(&p.name)))
+                    .join(", ");
+                let name = (func.name_hint.as_ref())
+                    .map(|n| format!("`{}`", n.name))
+                    .unwrap_or_else(|| "function".to_string());
+
+                return Err(Error::new_simple(format!(
+                    "{name} is missing required arguments: {missing}"
+                ))
+                .push_hint("only named arguments with a default value can be omitted")
+                .with_span(expr.span)
+                .into());
+            }
+
             pl::ExprKind::FuncCall(_)
             | pl::ExprKind::Range(_)
             | pl::ExprKind::Tuple(_)
@@ -901,6 +1059,20 @@ fn str_lit(string: String) -> rq::Expr {
     }
 }
 
+fn reverse_sort(sort: ColumnSort<CId>) -> ColumnSort<CId> {
+    ColumnSort {
+        direction: match sort.direction {
+            SortDirection::Asc => SortDirection::Desc,
+            SortDirection::Desc => SortDirection::Asc,
+        },
+        nulls: sort.nulls.map(|nulls| match nulls {
+            NullsOrder::First => NullsOrder::Last,
+            NullsOrder::Last => NullsOrder::First,
+        }),
+        ..sort
+    }
+}
+
 fn validate_take_range(range: &Range<rq::Expr>, span: Option<Span>) -> Result<()> {
     fn bound_as_int(bound: &Option<rq::Expr>) -> Option<Option<&i64>> {
         bound
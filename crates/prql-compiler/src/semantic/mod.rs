@@ -12,6 +12,7 @@ mod static_analysis;
 
 use anyhow::Result;
 use itertools::Itertools;
+use serde::Serialize;
 use std::path::PathBuf;
 
 pub use self::module::Module;
@@ -23,7 +24,7 @@ pub use lowering::lower_to_ir;
 
 use crate::error::WithErrorInfo;
 use crate::ir::pl::{self, Lineage, LineageColumn, ModuleDef, Stmt, StmtKind, TypeDef, VarDef};
-use crate::ir::rq::RelationalQuery;
+use crate::ir::rq::{RelationKind, RelationalQuery};
 use crate::{Error, Reason, SourceTree};
 
 /// Runs semantic analysis on the query and lowers PL to RQ.
@@ -37,6 +38,39 @@ pub fn resolve_and_lower(
     Ok(query)
 }
 
+/// Runs semantic analysis on the query and lowers each relational variable
+/// declared at the top level into its own query, named after the variable.
+///
+/// Relations of other variables are not included, but referenced as tables.
+pub fn resolve_and_lower_models(
+    file_tree: SourceTree<Vec<prql_ast::stmt::Stmt>>,
+) -> Result<Vec<(String, RelationalQuery)>> {
+    let context = resolve(file_tree, Default::default())?;
+
+    let models = (context.root_mod.names.iter())
+        .filter(|(_, decl)| {
+            let table_decl = decl.kind.as_table_decl();
+            table_decl.map_or(false, |t| t.expr.is_relation_var())
+        })
+        .sorted_by_key(|(_, decl)| decl.declared_at)
+        .map(|(name, _)| name.clone())
+        .collect_vec();
+
+    models
+        .iter()
+        .map(|name| {
+            let (mut query, _) = lowering::lower_to_ir(context.clone(), &[name.clone()])?;
+
+            for table in &mut query.tables {
+                if let Some(model) = table.name.as_ref().filter(|n| models.contains(n)) {
+                    table.relation.kind = RelationKind::ExternRef(pl::Ident::from_name(model));
+                }
+            }
+            Ok((name.clone(), query))
+        })
+        .collect()
+}
+
 /// Runs semantic analysis on the query.
 pub fn resolve(
     mut file_tree: SourceTree<Vec<prql_ast::stmt::Stmt>>,
@@ -73,6 +107,56 @@ pub fn resolve(
     Ok(resolver.context)
 }
 
+/// A column of a relation, as inferred by the resolver.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum ColumnInfo {
+    /// A single column. When it is a column of a table (and not computed),
+    /// `table` contains the name of that table.
+    Single {
+        name: Option<String>,
+        table: Option<String>,
+    },
+
+    /// All columns of a table, which are not known to the compiler.
+    All { table: String },
+}
+
+/// Returns the columns of the relation produced by the main pipeline.
+pub fn main_frame(context: &RootModule, main_path: &[String]) -> Result<Vec<ColumnInfo>> {
+    let (table_expr, _) = context.find_main_rel(main_path).map_err(|hint| {
+        Error::new_simple("Missing main pipeline")
+            .with_code("E0001")
+            .with_hints(hint)
+    })?;
+
+    let lineage = match table_expr {
+        decl::TableExpr::RelationVar(expr) => expr.lineage.clone().unwrap_or_default(),
+        _ => Lineage::default(),
+    };
+
+    // name of the table of an input of the relation
+    let table_of = |input_name: &str| {
+        let input = lineage.inputs.iter().find(|i| i.name == input_name);
+        input.map(|i| i.table.name.clone())
+    };
+
+    let columns = (lineage.columns.iter())
+        .map(|col| match col {
+            LineageColumn::Single { name, .. } => ColumnInfo::Single {
+                name: name.as_ref().map(|n| n.name.clone()),
+                table: name
+                    .as_ref()
+                    .and_then(|n| n.path.first())
+                    .and_then(|input_name| table_of(input_name)),
+            },
+            LineageColumn::All { input_name, .. } => ColumnInfo::All {
+                table: table_of(input_name).unwrap_or_else(|| input_name.clone()),
+            },
+        })
+        .collect();
+    Ok(columns)
+}
+
 /// Preferred way of injecting std module.
 pub fn load_std_lib(source_tree: &mut SourceTree) {
     let path = PathBuf::from("std.prql");
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::ops::Range;
 
@@ -160,6 +161,91 @@ impl PlFold for FrameCollector {
     }
 }
 
+/// Formats a table of columns of a relation, with the type that was inferred
+/// for each of them and the table that they come from. Types and names that
+/// are not known are printed as `?`.
+pub fn explain_types(expr: Expr) -> String {
+    let Some(lineage) = expr.lineage.clone() else {
+        return String::new();
+    };
+
+    let mut collector = ExprCollector::default();
+    collector.fold_expr(expr).unwrap();
+    let exprs = collector.exprs;
+
+    let unknown = || "?".to_string();
+    let mut rows = vec![[
+        "column".to_string(),
+        "type".to_string(),
+        "source".to_string(),
+    ]];
+    for col in &lineage.columns {
+        rows.push(match col {
+            LineageColumn::All { input_name, .. } => {
+                let input = lineage.inputs.iter().find(|i| &i.name == input_name);
+                let source = input.map_or_else(unknown, |i| i.table.name.clone());
+                [format!("{input_name}.*"), unknown(), source]
+            }
+            LineageColumn::Single {
+                name, target_id, ..
+            } => {
+                let name = name.as_ref().map_or_else(unknown, |n| n.name.clone());
+
+                // follow the references until reaching an input or the
+                // expression that computes the column
+                let mut id = *target_id;
+                let mut ty = None;
+                let mut source = unknown();
+                for _ in 0..=exprs.len() {
+                    if let Some(input) = lineage.inputs.iter().find(|i| i.id == id) {
+                        source = input.table.name.clone();
+                        break;
+                    }
+                    let Some((expr_ty, target_id)) = exprs.get(&id) else {
+                        break;
+                    };
+                    ty = ty.or(expr_ty.as_ref());
+                    if let Some(target_id) = target_id {
+                        id = *target_id;
+                    } else {
+                        source = "derived".to_string();
+                        break;
+                    }
+                }
+                let ty = ty.map_or_else(unknown, |ty| ty.to_string());
+                [name, ty, source]
+            }
+        });
+    }
+
+    let name_width = rows.iter().map(|r| r[0].len()).max().unwrap_or_default();
+    let ty_width = rows.iter().map(|r| r[1].len()).max().unwrap_or_default();
+    let mut res = String::new();
+    for [name, ty, source] in rows {
+        writeln!(res, "{name:name_width$}  {ty:ty_width$}  {source}").unwrap();
+    }
+    res
+}
+
+/// Traverses AST and collects the type and the target of each node
+#[derive(Default)]
+struct ExprCollector {
+    exprs: HashMap<usize, (Option<Ty>, Option<usize>)>,
+}
+
+impl PlFold for ExprCollector {
+    fn fold_expr(&mut self, expr: Expr) -> Result<Expr> {
+        if let Some(id) = expr.id {
+            self.exprs.insert(id, (expr.ty.clone(), expr.target_id));
+        }
+
+        Ok(Expr {
+            kind: self.fold_expr_kind(expr.kind)?,
+            ..expr
+        })
+    }
+}
+
 pub fn debug_call_tree(expr: Expr) -> (Expr, String) {
     let mut collector = CallTreeDebugger {
         indent: 0,
@@ -107,6 +107,7 @@ impl PlFold for Flattener {
                     TransformKind::Window {
                         kind,
                         range,
+                        partition,
                         pipeline,
                     } => {
                         let tbl = self.fold_expr(*t.input)?;
@@ -118,8 +119,13 @@ impl PlFold for Flattener {
                         self.replace_map.insert(param_id, tbl);
                         self.window = WindowFrame { kind, range };
 
+                        // partitions of the window are added to the keys of the group
+                        let group_partition = self.partition.clone();
+                        self.partition.extend(partition);
+
                         let pipeline = self.fold_expr(*pipeline.body)?;
 
+                        self.partition = group_partition;
                         self.window = WindowFrame::default();
                         self.replace_map.remove(&param_id);
 
@@ -327,6 +327,15 @@ impl PlFold for Resolver {
 
                             if self.in_func_call_name {
                                 expr
+                            } else if fq_ident.to_string() == "std.row_number" {
+                                // row_number does not depend on its argument,
+                                // so it can also be used without one
+                                let this = Expr::new(Ident::from_name(NS_THIS));
+                                let call = FuncCall::new_simple(Expr::new(fq_ident), vec![this]);
+                                self.fold_expr(Expr {
+                                    span,
+                                    ..Expr::new(ExprKind::FuncCall(call))
+                                })?
                             } else {
                                 self.fold_expr(expr)?
                             }
@@ -699,6 +708,7 @@ impl Resolver {
             });
 
         let has_relations = !relations.is_empty();
+        let mut aggregated = false;
 
         // resolve relational args
         if has_relations {
@@ -724,6 +734,7 @@ impl Resolver {
                     let frame = arg.lineage.as_ref().unwrap();
                     if is_last {
                         self.context.root_mod.insert_frame(frame, NS_THIS);
+                        aggregated = transforms::ends_in_aggregate(&arg);
                     } else {
                         self.context.root_mod.insert_frame(frame, NS_THAT);
                     }
@@ -742,11 +753,13 @@ impl Resolver {
 
                     let mut fields_new = Vec::with_capacity(fields.len());
                     for field in fields {
-                        let field = self.fold_within_namespace(field, &param.name)?;
+                        let field = self
+                            .fold_within_namespace(field, &param.name)
+                            .map_err(|e| transforms::hint_aggregated(e, aggregated))?;
 
                         // add aliased columns into scope
-                        if let Some(alias) = field.alias.clone() {
-                            let id = field.id.unwrap();
+                        // (fields of `noresolve` params stay unresolved)
+                        if let (Some(alias), Some(id)) = (field.alias.clone(), field.id) {
                             self.context.root_mod.insert_frame_col(NS_THIS, alias, id);
                         }
                         fields_new.push(field);
@@ -759,7 +772,8 @@ impl Resolver {
                 }
 
                 arg = self
-                    .fold_and_type_check(arg, param, func_name)?
+                    .fold_and_type_check(arg, param, func_name)
+                    .map_err(|e| transforms::hint_aggregated(e, aggregated))?
                     .unwrap_or_else(|a| {
                         partial_application_position = Some(index);
                         a
@@ -6,7 +6,7 @@ use serde::Deserialize;
 use std::iter::zip;
 
 use crate::error::{Error, Reason, WithErrorInfo};
-use crate::generic::{SortDirection, WindowKind};
+use crate::generic::{NullsOrder, SortDirection, WindowKind};
 use crate::ir::pl::PlFold;
 use crate::ir::pl::*;
 use crate::semantic::write_pl;
@@ -15,7 +15,7 @@ use super::super::decl::{Decl, DeclKind};
 use super::super::module::Module;
 use super::Resolver;
 use super::{Lineage, RootModule};
-use super::{NS_PARAM, NS_THIS};
+use super::{NS_PARAM, NS_THAT, NS_THIS};
 
 /// try to convert function call with enough args into transform
 pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
@@ -30,9 +30,49 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
         "select" => {
             let [assigns, tbl] = unpack::<2>(closure);
 
-            let assigns = coerce_into_tuple_and_flatten(assigns)?;
+            let mut assigns = coerce_into_tuple_and_flatten(assigns)?;
+
+            // append the columns of preceding `keep`s that are not selected already
+            for column in kept_columns(&tbl) {
+                if !assigns.iter().any(|a| a.target_id == column.target_id) {
+                    assigns.push(column);
+                }
+            }
             (TransformKind::Select { assigns }, tbl)
         }
+        "keep" => {
+            let [columns, tbl] = unpack::<2>(closure);
+
+            let columns = coerce_into_tuple_and_flatten(columns)?;
+            for column in &columns {
+                if column.alias.is_some() || !column.kind.is_ident() {
+                    return Err(Error::new(Reason::Expected {
+                        who: Some("`keep`".to_string()),
+                        expected: "names of columns".to_string(),
+                        found: write_pl(column.clone()),
+                    })
+                    .push_hint("use `derive` to compute new columns")
+                    .with_span(column.span)
+                    .into());
+                }
+            }
+            (TransformKind::Keep { columns }, tbl)
+        }
+        "drop" => {
+            let [columns, tbl] = unpack::<2>(closure);
+
+            // select !{columns}
+            let columns = Expr::new(ExprKind::Tuple(coerce_into_tuple_and_flatten(columns)?));
+            let exclude = Expr::new(ExprKind::FuncCall(FuncCall::new_simple(
+                Expr::new(Ident::from_path(vec!["std", "not"])),
+                vec![columns],
+            )));
+            let select = FuncCall::new_simple(
+                Expr::new(Ident::from_path(vec!["std", "select"])),
+                vec![exclude, tbl],
+            );
+            return Ok(Expr::new(ExprKind::FuncCall(select)));
+        }
         "filter" => {
             let [filter, tbl] = unpack::<2>(closure);
 
@@ -54,9 +94,31 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
         "sort" => {
             let [by, tbl] = unpack::<2>(closure);
 
-            let by = coerce_into_tuple_and_flatten(by)?
+            // keys are resolved here rather than as arguments, because each of
+            // them can be followed by the position of its nulls
+            let frame = tbl.lineage.clone().unwrap();
+            resolver.context.root_mod.shadow(NS_THIS);
+            resolver.context.root_mod.shadow(NS_THAT);
+            resolver.context.root_mod.insert_frame(&frame, NS_THIS);
+
+            let mut keys = Vec::new();
+            for key in coerce_into_tuple(by)? {
+                let (key, nulls) = split_nulls(key)?;
+                let key = resolver
+                    .fold_within_namespace(key, "by")
+                    .map_err(|e| hint_available_columns(e, &frame))?;
+
+                for key in coerce_into_tuple_and_flatten(key)? {
+                    keys.push((key, nulls));
+                }
+            }
+
+            resolver.context.root_mod.unshadow(NS_THIS);
+            resolver.context.root_mod.unshadow(NS_THAT);
+
+            let by = keys
                 .into_iter()
-                .map(|node| {
+                .map(|(node, nulls)| {
                     let (column, direction) = match node.kind {
                         ExprKind::RqOperator { name, mut args } if name == "std.neg" => {
                             (args.remove(0), SortDirection::Desc)
@@ -65,22 +127,37 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
                     };
                     let column = Box::new(column);
 
-                    ColumnSort { direction, column }
+                    ColumnSort {
+                        direction,
+                        column,
+                        nulls,
+                    }
                 })
                 .collect();
 
             (TransformKind::Sort { by }, tbl)
         }
+        "distinct_on" => {
+            let [by, tbl] = unpack::<2>(closure);
+
+            let by = coerce_into_tuple_and_flatten(by)?;
+            (TransformKind::DistinctOn { by }, tbl)
+        }
         "take" => {
             let [expr, tbl] = unpack::<2>(closure);
 
-            let range = match expr.kind {
-                ExprKind::Literal(Literal::Integer(n)) => range_from_ints(None, Some(n)),
-                ExprKind::Range(range) => range,
+            let kind = match expr.kind {
+                ExprKind::Literal(Literal::Integer(n)) => TransformKind::Take {
+                    range: range_from_ints(None, Some(n)),
+                },
+                ExprKind::Range(range) => TransformKind::Take { range },
+                ExprKind::Literal(Literal::ValueAndUnit(vau)) if vau.unit == "%" => {
+                    TransformKind::Sample { percent: vau.n }
+                }
                 _ => {
                     return Err(Error::new(Reason::Expected {
                         who: Some("`take`".to_string()),
-                        expected: "int or range".to_string(),
+                        expected: "int, range or percentage".to_string(),
                         found: write_pl(expr.clone()),
                     })
                     // Possibly this should refer to the item after the `take` where
@@ -90,8 +167,27 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
                 }
             };
 
+            (kind, tbl)
+        }
+        "sample" => {
+            let [rows, tbl] = unpack::<2>(closure);
+
+            let rows = number_of_rows(&rows, "sample")?;
+            (TransformKind::SampleRows { rows }, tbl)
+        }
+        "head" => {
+            let [rows, tbl] = unpack::<2>(closure);
+
+            let rows = number_of_rows(&rows, "head")?;
+            let range = range_from_ints(None, Some(rows));
             (TransformKind::Take { range }, tbl)
         }
+        "tail" => {
+            let [rows, tbl] = unpack::<2>(closure);
+
+            let rows = number_of_rows(&rows, "tail")?;
+            (TransformKind::Tail { rows }, tbl)
+        }
         "join" => {
             let [side, with, filter, tbl] = unpack::<4>(closure);
 
@@ -128,8 +224,22 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
             let pipeline = Box::new(pipeline);
             (TransformKind::Group { by, pipeline }, tbl)
         }
+        "within" => {
+            let [by, columns, tbl] = unpack::<3>(closure);
+
+            // group {by} (derive {columns})
+            let derive = Expr::new(ExprKind::FuncCall(FuncCall::new_simple(
+                Expr::new(Ident::from_path(vec!["std", "derive"])),
+                vec![columns],
+            )));
+            let group = FuncCall::new_simple(
+                Expr::new(Ident::from_path(vec!["std", "group"])),
+                vec![by, derive, tbl],
+            );
+            return Ok(Expr::new(ExprKind::FuncCall(group)));
+        }
         "window" => {
-            let [rows, range, expanding, rolling, pipeline, tbl] = unpack::<6>(closure);
+            let [rows, range, expanding, rolling, partition, pipeline, tbl] = unpack::<7>(closure);
 
             let expanding = {
                 let as_bool = expanding.kind.as_literal().and_then(|l| l.as_boolean());
@@ -176,12 +286,15 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
                 (WindowKind::Rows, Range::unbounded())
             };
 
+            let partition = coerce_into_tuple_and_flatten(partition)?;
+
             let pipeline =
                 fold_by_simulating_eval(resolver, pipeline, tbl.lineage.clone().unwrap())?;
 
             let transform_kind = TransformKind::Window {
                 kind,
                 range,
+                partition,
                 pipeline: Box::new(pipeline),
             };
             (transform_kind, tbl)
@@ -200,6 +313,93 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
             (TransformKind::Loop(Box::new(pipeline)), tbl)
         }
 
+        "normalize_names" => {
+            let [case, tbl] = unpack::<2>(closure);
+
+            let span = case.span;
+            let case = case
+                .try_cast(ExprKind::into_ident, Some("case"), "ident")?
+                .to_string();
+            if !["snake", "camel", "lower", "upper"].contains(&case.as_str()) {
+                return Err(Error::new(Reason::Expected {
+                    who: Some("`case`".to_string()),
+                    expected: "snake, camel, lower or upper".to_string(),
+                    found: case,
+                })
+                .with_span(span)
+                .into());
+            }
+
+            return select_renamed(
+                tbl,
+                "normalize_names",
+                "select the columns before normalizing their names",
+                |name| convert_case(name, &case),
+            );
+        }
+        "rename_all" => {
+            let [prefix, tbl] = unpack::<2>(closure);
+
+            let prefix = match prefix.kind {
+                ExprKind::Literal(Literal::String(prefix)) => prefix,
+                _ => {
+                    return Err(Error::new(Reason::Expected {
+                        who: Some("`prefix`".to_string()),
+                        expected: "a string literal".to_string(),
+                        found: format!("`{}`", write_pl(prefix.clone())),
+                    })
+                    .with_span(prefix.span)
+                    .into());
+                }
+            };
+
+            return select_renamed(
+                tbl,
+                "rename_all",
+                "select the columns before renaming them",
+                |name| format!("{prefix}{name}"),
+            );
+        }
+
+        "fill" => {
+            let [column, tbl] = unpack::<2>(closure);
+
+            let Some(name) = column.kind.as_ident().map(|i| i.name.clone()) else {
+                return Err(Error::new(Reason::Expected {
+                    who: Some("`fill`".to_string()),
+                    expected: "a column".to_string(),
+                    found: write_pl(column.clone()),
+                })
+                .with_span(column.span)
+                .into());
+            };
+
+            // window expanding:true (derive {name = (last column ignore_nulls:true)})
+            let last = FuncCall {
+                named_args: [("ignore_nulls".to_string(), new_bool(true))].into(),
+                ..FuncCall::new_simple(
+                    Expr::new(Ident::from_path(vec!["std", "last"])),
+                    vec![column],
+                )
+            };
+            let last = Expr {
+                alias: Some(name),
+                ..Expr::new(ExprKind::FuncCall(last))
+            };
+            let derive = Expr::new(ExprKind::FuncCall(FuncCall::new_simple(
+                Expr::new(Ident::from_path(vec!["std", "derive"])),
+                vec![last],
+            )));
+            let window = FuncCall {
+                named_args: [("expanding".to_string(), new_bool(true))].into(),
+                ..FuncCall::new_simple(
+                    Expr::new(Ident::from_path(vec!["std", "window"])),
+                    vec![derive, tbl],
+                )
+            };
+            return Ok(Expr::new(ExprKind::FuncCall(window)));
+        }
+
         "in" => {
             // yes, this is not a transform, but this is the most appropriate place for it
 
@@ -215,6 +415,24 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
                         res.unwrap_or_else(|| Expr::new(ExprKind::Literal(Literal::Boolean(true))));
                     return Ok(res);
                 }
+                ExprKind::Array(items) => {
+                    // an empty list can never contain the value, which is
+                    // expressed as `1 = 0`, because not all dialects have
+                    // boolean literals
+                    if items.is_empty() {
+                        let one = Expr::new(ExprKind::Literal(Literal::Integer(1)));
+                        let zero = Expr::new(ExprKind::Literal(Literal::Integer(0)));
+                        return Ok(new_binop(one, &["std", "eq"], zero));
+                    }
+
+                    // translated into `value IN (...)` by the SQL backend
+                    let mut args = vec![value];
+                    args.extend(items);
+                    return Ok(Expr::new(ExprKind::RqOperator {
+                        name: "std.array_in".to_string(),
+                        args,
+                    }));
+                }
                 ExprKind::Tuple(_) => {
                     // TODO: should translate into `value IN (...)`
                     //   but RQ currently does not support sub queries or
@@ -231,6 +449,28 @@ pub fn cast_transform(resolver: &mut Resolver, closure: Func) -> Result<Expr> {
             .into());
         }
 
+        "assert_type" => {
+            // yes, this is not a transform, but this is the most appropriate place for it
+
+            let [ty, value] = unpack::<2>(closure);
+            let expected = resolver.fold_type_expr(Some(Box::new(ty)))?.unwrap();
+
+            // types are only known for some expressions, so we only reject
+            // values that cannot possibly be of the annotated type
+            if let Some(found) = &value.ty {
+                if !expected.is_super_type_of(found) && !found.is_super_type_of(&expected) {
+                    return Err(Error::new(Reason::Expected {
+                        who: None,
+                        expected: format!("type `{expected}`"),
+                        found: format!("type `{found}`"),
+                    })
+                    .with_span(value.span)
+                    .into());
+                }
+            }
+            return Ok(value);
+        }
+
         "tuple_every" => {
             // yes, this is not a transform, but this is the most appropriate place for it
 
@@ -417,6 +657,93 @@ pub fn coerce_into_tuple_and_flatten(expr: Expr) -> Result<Vec<Expr>> {
     Ok(res2)
 }
 
+/// Selects all columns of `tbl`, renamed by `rename`. Fails when the columns
+/// of `tbl` are not known.
+fn select_renamed(
+    tbl: Expr,
+    transform: &str,
+    hint: &str,
+    rename: impl Fn(&str) -> String,
+) -> Result<Expr> {
+    let lineage = tbl.lineage.as_ref().unwrap();
+    let mut assigns = Vec::with_capacity(lineage.columns.len());
+    for col in &lineage.columns {
+        let LineageColumn::Single { name: Some(name), .. } = col else {
+            return Err(Error::new_simple(format!(
+                "{transform} requires all columns of the relation to be known"
+            ))
+            .push_hint(hint)
+            .with_span(tbl.span)
+            .into());
+        };
+
+        assigns.push(Expr {
+            alias: Some(rename(&name.name)),
+            ..Expr::new(name.clone())
+        });
+    }
+
+    // select is resolved against the frame of `tbl`, like in the source
+    let select = Expr::new(Ident::from_path(vec!["std", "select"]));
+    let args = vec![Expr::new(ExprKind::Tuple(assigns)), tbl];
+    Ok(Expr::new(ExprKind::FuncCall(FuncCall::new_simple(
+        select, args,
+    ))))
+}
+
+/// Converts a column name into `snake`, `camel`, `lower` or `upper` case.
+///
+/// Words are delimited by underscores, dashes, spaces and by changes from lower
+/// to upper case, so `HTTPServer id` has words `HTTP`, `Server` and `id`.
+fn convert_case(name: &str, case: &str) -> String {
+    match case {
+        "lower" => return name.to_lowercase(),
+        "upper" => return name.to_uppercase(),
+        _ => {}
+    }
+
+    let chars = name.chars().collect_vec();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            continue;
+        }
+
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let starts_word = c.is_uppercase()
+            && (prev.map_or(false, |p| p.is_lowercase() || p.is_ascii_digit())
+                || (prev.map_or(false, char::is_uppercase)
+                    && next.map_or(false, |n| n.is_lowercase())));
+        if starts_word && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(*c);
+    }
+    words.extend((!word.is_empty()).then_some(word));
+
+    let words = words.into_iter().map(|w| w.to_lowercase());
+    if case == "snake" {
+        return words.collect_vec().join("_");
+    }
+    words
+        .enumerate()
+        .map(|(i, w)| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) if i > 0 => first.to_uppercase().chain(chars).collect(),
+                _ => w,
+            }
+        })
+        .collect()
+}
+
+fn new_bool(value: bool) -> Expr {
+    Expr::new(ExprKind::Literal(Literal::Boolean(value)))
+}
+
 fn range_is_empty(range: &Range) -> bool {
     fn as_int(bound: &Option<Box<Expr>>) -> Option<i64> {
         bound
@@ -432,6 +759,127 @@ fn range_is_empty(range: &Range) -> bool {
     }
 }
 
+fn number_of_rows(expr: &Expr, transform: &str) -> Result<i64> {
+    let Some(Literal::Integer(rows)) = expr.kind.as_literal() else {
+        return Err(Error::new(Reason::Expected {
+            who: Some(format!("`{transform}`")),
+            expected: "a number of rows".to_string(),
+            found: write_pl(expr.clone()),
+        })
+        .with_span(expr.span)
+        .into());
+    };
+    Ok(*rows)
+}
+
+/// Separates a key of `sort` from the position of its nulls, i.e.
+/// `-amount nulls:last`.
+fn split_nulls(key: Expr) -> Result<(Expr, Option<NullsOrder>)> {
+    match key.kind {
+        ExprKind::FuncCall(FuncCall {
+            name,
+            args,
+            mut named_args,
+        }) if args.is_empty() && named_args.len() == 1 && named_args.contains_key("nulls") => {
+            let nulls = nulls_order(named_args.remove("nulls").unwrap())?;
+            Ok((*name, Some(nulls)))
+        }
+        kind => Ok((Expr { kind, ..key }, None)),
+    }
+}
+
+/// Position of nulls of a sort key, as given by `nulls:first` or `nulls:last`.
+fn nulls_order(nulls: Expr) -> Result<NullsOrder> {
+    let span = nulls.span;
+    let ident = nulls.try_cast(ExprKind::into_ident, Some("nulls"), "ident")?;
+    Ok(match ident.to_string().as_str() {
+        "first" => NullsOrder::First,
+        "last" => NullsOrder::Last,
+
+        found => bail!(Error::new(Reason::Expected {
+            who: Some("`nulls`".to_string()),
+            expected: "first or last".to_string(),
+            found: found.to_string()
+        })
+        .with_span(span)),
+    })
+}
+
+/// When `err` is about an unknown name, lists the columns of `frame` in its hint.
+fn hint_available_columns(err: anyhow::Error, frame: &Lineage) -> anyhow::Error {
+    match err.downcast::<Error>() {
+        Ok(err) if matches!(&err.reason, Reason::Simple(msg) if msg == "Unknown name") => {
+            err.push_hint(format!("available columns: {frame}")).into()
+        }
+        Ok(err) => err.into(),
+        Err(err) => err,
+    }
+}
+
+/// Relations of the input are not available after `aggregate`, which is
+/// easy to miss when expanding one of them with `*`.
+pub(super) fn hint_aggregated(err: anyhow::Error, aggregated: bool) -> anyhow::Error {
+    if !aggregated {
+        return err;
+    }
+    match err.downcast::<Error>() {
+        Ok(err) if matches!(&err.reason, Reason::Simple(msg) if msg.starts_with("Unknown relation")) => {
+            err.push_hint(
+                "after `aggregate`, only the aggregated columns are available as `this.*`",
+            )
+            .into()
+        }
+        Ok(err) => err.into(),
+        Err(err) => err,
+    }
+}
+
+/// Whether the columns of `tbl` are the columns of an `aggregate`, possibly
+/// followed by transforms that keep the columns.
+pub(super) fn ends_in_aggregate(tbl: &Expr) -> bool {
+    let mut tbl = tbl;
+    while let ExprKind::TransformCall(call) = &tbl.kind {
+        match call.kind.as_ref() {
+            TransformKind::Aggregate { .. } => return true,
+            TransformKind::Group { pipeline, .. } => {
+                let Func { body, .. } = pipeline.kind.as_func().unwrap().as_ref();
+                return ends_in_aggregate(body);
+            }
+            TransformKind::Sort { .. }
+            | TransformKind::Filter { .. }
+            | TransformKind::Take { .. }
+            | TransformKind::Keep { .. }
+            | TransformKind::DistinctOn { .. }
+            | TransformKind::Tail { .. }
+            | TransformKind::Sample { .. }
+            | TransformKind::SampleRows { .. } => {}
+            _ => return false,
+        }
+        tbl = &call.input;
+    }
+    false
+}
+
+/// Columns of `keep`s in the pipeline before `tbl`, up to a transform that
+/// replaces the columns of the relation.
+fn kept_columns(tbl: &Expr) -> Vec<Expr> {
+    let mut kept = Vec::new();
+    let mut tbl = tbl;
+    while let ExprKind::TransformCall(call) = &tbl.kind {
+        match call.kind.as_ref() {
+            TransformKind::Keep { columns } => kept.push(columns),
+            TransformKind::Aggregate { .. }
+            | TransformKind::Group { .. }
+            | TransformKind::Window { .. }
+            | TransformKind::Append(_)
+            | TransformKind::Loop(_) => break,
+            _ => {}
+        }
+        tbl = &call.input;
+    }
+    kept.into_iter().rev().flatten().cloned().collect()
+}
+
 fn range_from_ints(start: Option<i64>, end: Option<i64>) -> Range {
     let start = start.map(|x| Box::new(Expr::new(ExprKind::Literal(Literal::Integer(x)))));
     let end = end.map(|x| Box::new(Expr::new(ExprKind::Literal(Literal::Integer(x)))));
@@ -573,7 +1021,14 @@ impl TransformCall {
                 append(top, bottom)?
             }
             Loop(_) => ty_frame_or_default(&self.input)?,
-            Sort { .. } | Filter { .. } | Take { .. } => ty_frame_or_default(&self.input)?,
+            Sort { .. }
+            | Keep { .. }
+            | Filter { .. }
+            | Take { .. }
+            | DistinctOn { .. }
+            | Tail { .. }
+            | Sample { .. }
+            | SampleRows { .. } => ty_frame_or_default(&self.input)?,
         })
     }
 }
@@ -18,6 +18,8 @@ use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use strum::VariantNames;
 
+use super::Explain;
+
 /// SQL dialect.
 ///
 /// This only changes the output for a relatively small subset of features.
@@ -43,6 +45,7 @@ use strum::VariantNames;
 #[strum(serialize_all = "lowercase")]
 pub enum Dialect {
     Ansi,
+    Athena,
     BigQuery,
     ClickHouse,
     DuckDb,
@@ -50,9 +53,14 @@ pub enum Dialect {
     Generic,
     MsSql,
     MySql,
+    Oracle,
+    Oracle12,
     Postgres,
+    Redshift,
     SQLite,
     Snowflake,
+    #[strum(to_string = "trino", serialize = "presto")]
+    Trino,
 }
 
 // Is this the best approach for the Enum / Struct — basically that we have one
@@ -69,7 +77,12 @@ impl Dialect {
             Dialect::ClickHouse => Box::new(ClickHouseDialect),
             Dialect::Snowflake => Box::new(SnowflakeDialect),
             Dialect::DuckDb => Box::new(DuckDbDialect),
+            Dialect::Oracle => Box::new(OracleDialect),
+            Dialect::Oracle12 => Box::new(Oracle12Dialect),
             Dialect::Postgres => Box::new(PostgresDialect),
+            Dialect::Redshift => Box::new(RedshiftDialect),
+            Dialect::Trino => Box::new(TrinoDialect),
+            Dialect::Athena => Box::new(AthenaDialect),
             Dialect::Ansi | Dialect::Generic => Box::new(GenericDialect),
         }
     }
@@ -82,9 +95,25 @@ impl Dialect {
             | Dialect::MySql
             | Dialect::Generic
             | Dialect::ClickHouse => SupportLevel::Supported,
-            Dialect::MsSql | Dialect::Ansi | Dialect::BigQuery | Dialect::Snowflake => {
-                SupportLevel::Unsupported
-            }
+            Dialect::MsSql
+            | Dialect::Ansi
+            | Dialect::BigQuery
+            | Dialect::Snowflake
+            | Dialect::Oracle
+            | Dialect::Oracle12
+            | Dialect::Redshift
+            | Dialect::Trino
+            | Dialect::Athena => SupportLevel::Unsupported,
+        }
+    }
+
+    /// The dialect that this dialect is derived from. Operators that are not
+    /// implemented for this dialect use the implementation of the base.
+    pub(super) fn base(&self) -> Option<Dialect> {
+        match self {
+            Dialect::Athena => Some(Dialect::Trino),
+            Dialect::Oracle12 => Some(Dialect::Oracle),
+            _ => None,
         }
     }
 
@@ -100,6 +129,54 @@ pub enum SupportLevel {
     Nascent,
 }
 
+/// SQL features that are supported by a dialect.
+///
+/// Constructs that are not supported are either emulated or rejected during
+/// compilation, so this can be used to check a query before compiling it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    /// `QUALIFY`, which filters the results of window functions.
+    pub qualify: bool,
+
+    /// `FILTER (WHERE ...)` of aggregate functions.
+    pub filter: bool,
+
+    /// `NULLS FIRST` and `NULLS LAST` in `ORDER BY`.
+    pub nulls_ordering: bool,
+
+    /// `WITH RECURSIVE`, which is needed by `loop`.
+    pub recursive_ctes: bool,
+
+    /// `LATERAL` sub-queries in `FROM`.
+    pub lateral: bool,
+
+    /// `SELECT DISTINCT ON (...)`.
+    pub distinct_on: bool,
+
+    /// `JOIN ... USING (...)`.
+    pub join_using: bool,
+
+    /// `EXCEPT ALL`.
+    pub except_all: bool,
+
+    /// `INTERSECT ALL`.
+    pub intersect_all: bool,
+
+    /// Casts that return NULL instead of failing.
+    pub safe_cast: bool,
+
+    /// `TIMESTAMP WITH TIME ZONE` literals.
+    pub timestamp_tz: bool,
+
+    /// `GROUP BY 1`.
+    pub group_by_ordinals: bool,
+}
+
+/// Returns the features supported by a dialect.
+pub fn dialect_capabilities(dialect: Dialect) -> Capabilities {
+    dialect.handler().capabilities()
+}
+
 #[derive(Debug)]
 pub struct GenericDialect;
 #[derive(Debug)]
@@ -118,60 +195,240 @@ pub struct SnowflakeDialect;
 pub struct DuckDbDialect;
 #[derive(Debug)]
 pub struct PostgresDialect;
+#[derive(Debug)]
+pub struct OracleDialect;
+/// Oracle 12c and later, which limit rows with `FETCH FIRST`.
+#[derive(Debug)]
+pub struct Oracle12Dialect;
+#[derive(Debug)]
+pub struct RedshiftDialect;
+#[derive(Debug)]
+pub struct TrinoDialect;
+/// Athena is based on Trino, so it behaves as [TrinoDialect], apart from its
+/// own overrides.
+#[derive(Debug)]
+pub struct AthenaDialect;
 
 pub(super) enum ColumnExclude {
     Exclude,
     Except,
 }
 
+/// How the database normalizes the case of identifiers that are not quoted.
+#[derive(PartialEq, Eq)]
+pub(super) enum IdentCaseFolding {
+    Lower,
+    Upper,
+}
+
 pub(super) trait DialectHandler: Any + Debug {
+    /// The handler of the dialect that this dialect is derived from, see
+    /// [Dialect::base]. Methods that are not overridden behave as in the base,
+    /// so each default below defers to it.
+    fn base(&self) -> Option<&dyn DialectHandler> {
+        None
+    }
+
     fn use_top(&self) -> bool {
-        false
+        self.base().map_or(false, |base| base.use_top())
+    }
+
+    /// Use `OFFSET n ROWS FETCH FIRST m ROWS ONLY` instead of `LIMIT m OFFSET n`.
+    fn use_fetch(&self) -> bool {
+        self.base().map_or(false, |base| base.use_fetch())
+    }
+
+    /// Support for a clause that limits the number of rows, such as `LIMIT`,
+    /// `TOP` or `FETCH FIRST`. When not supported, rows are limited with
+    /// `ROWNUM` and skipped with `ROW_NUMBER()`.
+    fn supports_row_limiting(&self) -> bool {
+        self.base()
+            .map_or(true, |base| base.supports_row_limiting())
+    }
+
+    /// Support for `LIMIT m OFFSET n`. When not supported, `OFFSET n ROWS
+    /// FETCH FIRST m ROWS ONLY` is used for takes that skip rows.
+    fn limit_offset(&self) -> bool {
+        self.base().map_or(true, |base| base.limit_offset())
     }
 
     fn ident_quote(&self) -> char {
-        '"'
+        self.base().map_or('"', |base| base.ident_quote())
+    }
+
+    /// Whether a dotted name, such as `db.schema.table`, should be split into
+    /// parts that are quoted separately.
+    fn splits_dotted_idents(&self) -> bool {
+        self.base().map_or(true, |base| base.splits_dotted_idents())
     }
 
     fn column_exclude(&self) -> Option<ColumnExclude> {
-        None
+        self.base().and_then(|base| base.column_exclude())
+    }
+
+    fn ident_case_folding(&self) -> IdentCaseFolding {
+        self.base()
+            .map_or(IdentCaseFolding::Lower, |base| base.ident_case_folding())
     }
 
     /// Support for DISTINCT in set ops (UNION DISTINCT, INTERSECT DISTINCT)
     /// When not supported we fallback to implicit DISTINCT.
     fn set_ops_distinct(&self) -> bool {
-        true
+        self.base().map_or(true, |base| base.set_ops_distinct())
+    }
+
+    /// Support for `JOIN ... USING (columns)`.
+    /// When not supported, fallback to `JOIN ... ON`.
+    fn join_using(&self) -> bool {
+        self.base().map_or(true, |base| base.join_using())
+    }
+
+    /// Support for EXCEPT.
+    /// When not supported, fallback to anti join.
+    fn supports_except(&self) -> bool {
+        self.base().map_or(true, |base| base.supports_except())
     }
 
     /// Support or EXCEPT ALL.
     /// When not supported, fallback to anti join.
     fn except_all(&self) -> bool {
-        true
+        self.base().map_or(true, |base| base.except_all())
     }
 
     fn intersect_all(&self) -> bool {
-        self.except_all()
+        self.base()
+            .map_or_else(|| self.except_all(), |base| base.intersect_all())
     }
 
     /// Support for CONCAT function.
     /// When not supported we fallback to use `||` as concat operator.
     fn has_concat_function(&self) -> bool {
-        true
+        self.base().map_or(true, |base| base.has_concat_function())
     }
 
     /// Whether or not intervals such as `INTERVAL 1 HOUR` require quotes like
     /// `INTERVAL '1' HOUR`
     fn requires_quotes_intervals(&self) -> bool {
-        false
+        self.base()
+            .map_or(false, |base| base.requires_quotes_intervals())
+    }
+
+    /// Support for intervals of multiple units, such as
+    /// `INTERVAL '1 year 6 months'`
+    fn compound_intervals(&self) -> bool {
+        self.base().map_or_else(
+            || self.requires_quotes_intervals(),
+            |base| base.compound_intervals(),
+        )
+    }
+
+    /// Support for `*` next to other columns, as in `SELECT *, a + 1`. When not
+    /// supported, the star is qualified with the name of its relation.
+    fn unqualified_star_with_columns(&self) -> bool {
+        self.base()
+            .map_or(true, |base| base.unqualified_star_with_columns())
     }
 
     /// Support for GROUP BY *
     fn stars_in_group(&self) -> bool {
-        true
+        self.base().map_or(true, |base| base.stars_in_group())
     }
 
     fn supports_distinct_on(&self) -> bool {
-        false
+        self.base()
+            .map_or(false, |base| base.supports_distinct_on())
+    }
+
+    /// Support for `NULLS FIRST` and `NULLS LAST` in ORDER BY
+    fn supports_nulls_order(&self) -> bool {
+        self.base().map_or(true, |base| base.supports_nulls_order())
+    }
+
+    /// Support for referencing projection in GROUP BY by position, i.e. `GROUP BY 1`
+    fn group_by_ordinals(&self) -> bool {
+        self.base().map_or(false, |base| base.group_by_ordinals())
+    }
+
+    /// Support for referencing projection in GROUP BY by alias
+    fn group_by_aliases(&self) -> bool {
+        self.base()
+            .map_or_else(|| self.group_by_ordinals(), |base| base.group_by_aliases())
+    }
+
+    /// Prefix of the statement that displays the query plan, if supported.
+    fn explain(&self, explain: Explain) -> Option<&'static str> {
+        if let Some(base) = self.base() {
+            return base.explain(explain);
+        }
+        Some(match explain {
+            Explain::Plan => "EXPLAIN",
+            Explain::Analyze => "EXPLAIN ANALYZE",
+        })
+    }
+
+    /// Clause appended to a table in FROM, that samples approximately
+    /// `percent` percent of its rows, if supported.
+    fn table_sample(&self, percent: i64) -> Option<String> {
+        self.base().and_then(|base| base.table_sample(percent))
+    }
+
+    /// Clause appended to a table in FROM, that samples the given number of
+    /// its rows, if supported.
+    fn table_sample_rows(&self, rows: i64) -> Option<String> {
+        self.base().and_then(|base| base.table_sample_rows(rows))
+    }
+
+    /// Function without arguments that returns a random value for each row,
+    /// which is used to sort rows randomly.
+    fn random(&self) -> &'static str {
+        self.base().map_or("RANDOM", |base| base.random())
+    }
+
+    /// Support for casts that return NULL instead of failing, which are
+    /// implemented by the `safe_as` operator.
+    fn supports_safe_cast(&self) -> bool {
+        self.base().map_or(false, |base| base.supports_safe_cast())
+    }
+
+    /// Support for `TIMESTAMP WITH TIME ZONE` literals. When not supported,
+    /// timestamps with a time zone offset are emitted as plain timestamps.
+    fn supports_timestamp_tz(&self) -> bool {
+        self.base()
+            .map_or(false, |base| base.supports_timestamp_tz())
+    }
+
+    /// Support for `QUALIFY`.
+    fn supports_qualify(&self) -> bool {
+        self.base().map_or(false, |base| base.supports_qualify())
+    }
+
+    /// Support for `FILTER (WHERE ...)` of aggregate functions.
+    fn supports_filter(&self) -> bool {
+        self.base().map_or(false, |base| base.supports_filter())
+    }
+
+    /// Support for `WITH RECURSIVE`. When not supported, `loop` cannot be
+    /// compiled.
+    fn supports_recursive_ctes(&self) -> bool {
+        self.base()
+            .map_or(true, |base| base.supports_recursive_ctes())
+    }
+
+    /// Support for `LATERAL` sub-queries.
+    fn supports_lateral(&self) -> bool {
+        self.base().map_or(false, |base| base.supports_lateral())
+    }
+
+    /// Support for `AS` between a table and its alias. When not supported, the
+    /// alias directly follows the table.
+    fn table_alias_keyword(&self) -> bool {
+        self.base().map_or(true, |base| base.table_alias_keyword())
+    }
+
+    /// Support for `LIKE ... ESCAPE`. When not supported, wildcards are
+    /// escaped with a backslash, which is the default escape character.
+    fn supports_like_escape(&self) -> bool {
+        self.base().map_or(true, |base| base.supports_like_escape())
     }
 }
 
@@ -180,11 +437,33 @@ impl dyn DialectHandler {
     pub fn is<T: DialectHandler + 'static>(&self) -> bool {
         TypeId::of::<T>() == self.type_id()
     }
+
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            qualify: self.supports_qualify(),
+            filter: self.supports_filter(),
+            nulls_ordering: self.supports_nulls_order(),
+            recursive_ctes: self.supports_recursive_ctes(),
+            lateral: self.supports_lateral(),
+            distinct_on: self.supports_distinct_on(),
+            join_using: self.join_using(),
+            except_all: self.except_all(),
+            intersect_all: self.intersect_all(),
+            safe_cast: self.supports_safe_cast(),
+            timestamp_tz: self.supports_timestamp_tz(),
+            group_by_ordinals: self.group_by_ordinals(),
+        }
+    }
 }
 
 impl DialectHandler for GenericDialect {}
 
 impl DialectHandler for PostgresDialect {
+    fn group_by_ordinals(&self) -> bool {
+        // https://www.postgresql.org/docs/current/sql-select.html#SQL-GROUPBY
+        true
+    }
+
     fn requires_quotes_intervals(&self) -> bool {
         true
     }
@@ -193,9 +472,34 @@ impl DialectHandler for PostgresDialect {
         // https://www.postgresql.org/docs/current/sql-select.html
         true
     }
+
+    fn table_sample(&self, percent: i64) -> Option<String> {
+        // https://www.postgresql.org/docs/current/sql-select.html#SQL-FROM
+        Some(format!("TABLESAMPLE SYSTEM ({percent})"))
+    }
+
+    fn supports_timestamp_tz(&self) -> bool {
+        // https://www.postgresql.org/docs/current/datatype-datetime.html
+        true
+    }
+
+    fn supports_filter(&self) -> bool {
+        // https://www.postgresql.org/docs/current/sql-expressions.html#SYNTAX-AGGREGATES
+        true
+    }
+
+    fn supports_lateral(&self) -> bool {
+        // https://www.postgresql.org/docs/current/queries-table-expressions.html#QUERIES-LATERAL
+        true
+    }
 }
 
 impl DialectHandler for SQLiteDialect {
+    fn group_by_ordinals(&self) -> bool {
+        // https://www.sqlite.org/lang_select.html#resultset
+        true
+    }
+
     fn set_ops_distinct(&self) -> bool {
         false
     }
@@ -211,13 +515,39 @@ impl DialectHandler for SQLiteDialect {
     fn stars_in_group(&self) -> bool {
         false
     }
+
+    fn explain(&self, explain: Explain) -> Option<&'static str> {
+        // https://www.sqlite.org/eqp.html
+        match explain {
+            Explain::Plan => Some("EXPLAIN QUERY PLAN"),
+            Explain::Analyze => None,
+        }
+    }
+
+    fn supports_filter(&self) -> bool {
+        // https://www.sqlite.org/lang_aggfunc.html
+        true
+    }
 }
 
 impl DialectHandler for MsSqlDialect {
+    fn supports_nulls_order(&self) -> bool {
+        false
+    }
+
     fn use_top(&self) -> bool {
         true
     }
 
+    fn limit_offset(&self) -> bool {
+        false
+    }
+
+    fn join_using(&self) -> bool {
+        // https://learn.microsoft.com/en-us/sql/t-sql/queries/from-transact-sql
+        false
+    }
+
     // https://learn.microsoft.com/en-us/sql/t-sql/language-elements/set-operators-except-and-intersect-transact-sql?view=sql-server-ver16
     fn except_all(&self) -> bool {
         false
@@ -226,9 +556,33 @@ impl DialectHandler for MsSqlDialect {
     fn set_ops_distinct(&self) -> bool {
         false
     }
+
+    fn explain(&self, _: Explain) -> Option<&'static str> {
+        // plans are requested with `SET SHOWPLAN_ALL ON`, instead of a prefix
+        None
+    }
+
+    fn table_sample(&self, percent: i64) -> Option<String> {
+        // https://learn.microsoft.com/en-us/sql/t-sql/queries/from-transact-sql#tablesample-clause
+        Some(format!("TABLESAMPLE SYSTEM ({percent} PERCENT)"))
+    }
+
+    fn random(&self) -> &'static str {
+        // https://learn.microsoft.com/en-us/sql/t-sql/functions/newid-transact-sql
+        "NEWID"
+    }
 }
 
 impl DialectHandler for MySqlDialect {
+    fn supports_nulls_order(&self) -> bool {
+        false
+    }
+
+    fn group_by_ordinals(&self) -> bool {
+        // https://dev.mysql.com/doc/refman/8.0/en/select.html
+        true
+    }
+
     fn ident_quote(&self) -> char {
         '`'
     }
@@ -237,6 +591,16 @@ impl DialectHandler for MySqlDialect {
         // https://dev.mysql.com/doc/refman/8.0/en/set-operations.html
         true
     }
+
+    fn supports_lateral(&self) -> bool {
+        // https://dev.mysql.com/doc/refman/8.0/en/lateral-derived-tables.html
+        true
+    }
+
+    fn random(&self) -> &'static str {
+        // https://dev.mysql.com/doc/refman/8.0/en/mathematical-functions.html#function_rand
+        "RAND"
+    }
 }
 
 impl DialectHandler for ClickHouseDialect {
@@ -248,12 +612,47 @@ impl DialectHandler for ClickHouseDialect {
         // https://clickhouse.com/docs/en/sql-reference/statements/select/distinct
         true
     }
+
+    fn explain(&self, explain: Explain) -> Option<&'static str> {
+        // https://clickhouse.com/docs/en/sql-reference/statements/explain
+        match explain {
+            Explain::Plan => Some("EXPLAIN"),
+            Explain::Analyze => None,
+        }
+    }
+
+    fn supports_recursive_ctes(&self) -> bool {
+        // https://clickhouse.com/docs/en/sql-reference/statements/select/with
+        false
+    }
+
+    fn random(&self) -> &'static str {
+        // https://clickhouse.com/docs/en/sql-reference/functions/random-functions
+        "rand"
+    }
+
+    fn supports_like_escape(&self) -> bool {
+        // https://clickhouse.com/docs/en/sql-reference/functions/string-search-functions#like
+        false
+    }
 }
 
 impl DialectHandler for BigQueryDialect {
+    fn group_by_ordinals(&self) -> bool {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#group_by_clause
+        true
+    }
+
     fn ident_quote(&self) -> char {
         '`'
     }
+
+    fn splits_dotted_idents(&self) -> bool {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#quoted_identifiers
+        // a quoted path can contain dots, i.e. `project.dataset.table`
+        false
+    }
+
     fn column_exclude(&self) -> Option<ColumnExclude> {
         // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#select_except
         Some(ColumnExclude::Except)
@@ -263,21 +662,99 @@ impl DialectHandler for BigQueryDialect {
         // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#set_operators
         true
     }
+
+    fn explain(&self, _: Explain) -> Option<&'static str> {
+        // query plans are only available in the console, after the execution
+        None
+    }
+
+    fn table_sample(&self, percent: i64) -> Option<String> {
+        // https://cloud.google.com/bigquery/docs/table-sampling
+        Some(format!("TABLESAMPLE SYSTEM ({percent} PERCENT)"))
+    }
+
+    fn random(&self) -> &'static str {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/mathematical_functions#rand
+        "RAND"
+    }
+
+    fn supports_safe_cast(&self) -> bool {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/conversion_functions#safe_casting
+        true
+    }
+
+    fn supports_qualify(&self) -> bool {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/query-syntax#qualify_clause
+        true
+    }
+
+    fn supports_like_escape(&self) -> bool {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/operators#like_operator
+        false
+    }
 }
 
 impl DialectHandler for SnowflakeDialect {
+    fn group_by_ordinals(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/constructs/group-by
+        true
+    }
+
     fn column_exclude(&self) -> Option<ColumnExclude> {
         // https://docs.snowflake.com/en/sql-reference/sql/select.html
         Some(ColumnExclude::Exclude)
     }
 
+    fn ident_case_folding(&self) -> IdentCaseFolding {
+        // https://docs.snowflake.com/en/sql-reference/identifiers-syntax
+        IdentCaseFolding::Upper
+    }
+
     fn set_ops_distinct(&self) -> bool {
         // https://docs.snowflake.com/en/sql-reference/operators-query.html
         false
     }
+
+    fn explain(&self, explain: Explain) -> Option<&'static str> {
+        // https://docs.snowflake.com/en/sql-reference/sql/explain
+        match explain {
+            Explain::Plan => Some("EXPLAIN"),
+            Explain::Analyze => None,
+        }
+    }
+
+    fn table_sample(&self, percent: i64) -> Option<String> {
+        // https://docs.snowflake.com/en/sql-reference/constructs/sample
+        Some(format!("TABLESAMPLE SYSTEM ({percent})"))
+    }
+
+    fn table_sample_rows(&self, rows: i64) -> Option<String> {
+        // https://docs.snowflake.com/en/sql-reference/constructs/sample
+        Some(format!("SAMPLE ({rows} ROWS)"))
+    }
+
+    fn supports_timestamp_tz(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/data-types-datetime
+        true
+    }
+
+    fn supports_qualify(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/constructs/qualify
+        true
+    }
+
+    fn supports_lateral(&self) -> bool {
+        // https://docs.snowflake.com/en/sql-reference/constructs/join-lateral
+        true
+    }
 }
 
 impl DialectHandler for DuckDbDialect {
+    fn group_by_ordinals(&self) -> bool {
+        // https://duckdb.org/docs/sql/query_syntax/groupby
+        true
+    }
+
     fn column_exclude(&self) -> Option<ColumnExclude> {
         // https://duckdb.org/2022/05/04/friendlier-sql.html#select--exclude
         Some(ColumnExclude::Exclude)
@@ -292,11 +769,188 @@ impl DialectHandler for DuckDbDialect {
         // https://duckdb.org/docs/sql/query_syntax/select.html#distinct-on-clause
         true
     }
+
+    fn table_sample(&self, percent: i64) -> Option<String> {
+        // https://duckdb.org/docs/sql/samples
+        Some(format!("TABLESAMPLE {percent}%"))
+    }
+
+    fn table_sample_rows(&self, rows: i64) -> Option<String> {
+        // https://duckdb.org/docs/sql/samples
+        Some(format!("TABLESAMPLE RESERVOIR({rows} ROWS)"))
+    }
+
+    fn supports_timestamp_tz(&self) -> bool {
+        // https://duckdb.org/docs/sql/data_types/timestamp
+        true
+    }
+
+    fn supports_qualify(&self) -> bool {
+        // https://duckdb.org/docs/sql/query_syntax/qualify
+        true
+    }
+
+    fn supports_filter(&self) -> bool {
+        // https://duckdb.org/docs/sql/query_syntax/filter
+        true
+    }
+
+    fn supports_lateral(&self) -> bool {
+        // https://duckdb.org/docs/sql/query_syntax/from#lateral-joins
+        true
+    }
+}
+
+impl DialectHandler for OracleDialect {
+    fn supports_row_limiting(&self) -> bool {
+        // `FETCH FIRST` was added in 12c, see [Oracle12Dialect]
+        // https://docs.oracle.com/cd/E11882_01/server.112/e41084/pseudocolumns009.htm
+        false
+    }
+
+    fn ident_case_folding(&self) -> IdentCaseFolding {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/Database-Object-Names-and-Qualifiers.html
+        IdentCaseFolding::Upper
+    }
+
+    fn set_ops_distinct(&self) -> bool {
+        // UNION is distinct by default, `UNION DISTINCT` is not valid
+        false
+    }
+
+    fn join_using(&self) -> bool {
+        // columns of USING cannot be qualified, but the compiler qualifies
+        // column references of joined relations
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/SELECT.html
+        false
+    }
+
+    fn supports_except(&self) -> bool {
+        // the set operator is called MINUS
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/The-UNION-ALL-INTERSECT-MINUS-Operators.html
+        false
+    }
+
+    fn except_all(&self) -> bool {
+        false
+    }
+
+    fn has_concat_function(&self) -> bool {
+        // CONCAT only accepts two arguments
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/CONCAT.html
+        false
+    }
+
+    fn requires_quotes_intervals(&self) -> bool {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/Literals.html
+        true
+    }
+
+    fn compound_intervals(&self) -> bool {
+        false
+    }
+
+    fn unqualified_star_with_columns(&self) -> bool {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/SELECT.html
+        false
+    }
+
+    fn table_alias_keyword(&self) -> bool {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/SELECT.html
+        false
+    }
+
+    fn explain(&self, _: Explain) -> Option<&'static str> {
+        // plans are written into a table with `EXPLAIN PLAN FOR`
+        None
+    }
+
+    fn random(&self) -> &'static str {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/arpls/DBMS_RANDOM.html
+        "DBMS_RANDOM.VALUE"
+    }
+
+    fn supports_timestamp_tz(&self) -> bool {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/Literals.html
+        true
+    }
+
+    fn supports_recursive_ctes(&self) -> bool {
+        // recursive sub-queries have no RECURSIVE keyword, but require a list
+        // of columns
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/SELECT.html
+        false
+    }
+}
+
+impl DialectHandler for Oracle12Dialect {
+    fn base(&self) -> Option<&dyn DialectHandler> {
+        Some(&OracleDialect)
+    }
+
+    fn supports_row_limiting(&self) -> bool {
+        true
+    }
+
+    fn use_fetch(&self) -> bool {
+        // https://docs.oracle.com/en/database/oracle/oracle-database/19/sqlrf/SELECT.html
+        true
+    }
+}
+
+impl DialectHandler for RedshiftDialect {
+    fn requires_quotes_intervals(&self) -> bool {
+        true
+    }
+
+    fn explain(&self, explain: Explain) -> Option<&'static str> {
+        // https://docs.aws.amazon.com/redshift/latest/dg/r_EXPLAIN.html
+        match explain {
+            Explain::Plan => Some("EXPLAIN"),
+            Explain::Analyze => None,
+        }
+    }
+
+    fn supports_timestamp_tz(&self) -> bool {
+        // https://docs.aws.amazon.com/redshift/latest/dg/r_Datetime_types.html
+        true
+    }
+}
+
+impl DialectHandler for TrinoDialect {
+    fn limit_offset(&self) -> bool {
+        // https://trino.io/docs/current/sql/select.html#offset-clause
+        false
+    }
+
+    fn requires_quotes_intervals(&self) -> bool {
+        true
+    }
+
+    fn compound_intervals(&self) -> bool {
+        // https://trino.io/docs/current/functions/datetime.html#interval-functions
+        false
+    }
+
+    fn group_by_ordinals(&self) -> bool {
+        true
+    }
+}
+
+impl DialectHandler for AthenaDialect {
+    fn base(&self) -> Option<&dyn DialectHandler> {
+        Some(&TrinoDialect)
+    }
+
+    fn supports_recursive_ctes(&self) -> bool {
+        // https://docs.aws.amazon.com/athena/latest/ug/other-notable-limitations.html
+        false
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Dialect;
+    use super::{dialect_capabilities, Capabilities, Dialect};
     use insta::assert_debug_snapshot;
     use std::str::FromStr;
 
@@ -313,6 +967,38 @@ mod tests {
             VariantNotFound,
         )
         "###);
+
+        assert_eq!(Dialect::from_str("presto"), Ok(Dialect::Trino));
+        assert_eq!(Dialect::Trino.to_string(), "trino");
+    }
+
+    #[test]
+    fn test_dialect_capabilities() {
+        assert!(!dialect_capabilities(Dialect::Postgres).qualify);
+        assert!(dialect_capabilities(Dialect::BigQuery).qualify);
+
+        assert!(dialect_capabilities(Dialect::Postgres).filter);
+        assert!(!dialect_capabilities(Dialect::BigQuery).filter);
+
+        assert!(dialect_capabilities(Dialect::Postgres).lateral);
+        assert!(!dialect_capabilities(Dialect::SQLite).lateral);
+
+        assert!(dialect_capabilities(Dialect::Postgres).distinct_on);
+        assert!(!dialect_capabilities(Dialect::BigQuery).distinct_on);
+
+        assert!(!dialect_capabilities(Dialect::MsSql).join_using);
+        assert!(!dialect_capabilities(Dialect::ClickHouse).recursive_ctes);
+        assert!(!dialect_capabilities(Dialect::Athena).recursive_ctes);
+
+        // Athena behaves as Trino, apart from its own overrides
+        let trino = dialect_capabilities(Dialect::Trino);
+        assert_eq!(
+            dialect_capabilities(Dialect::Athena),
+            Capabilities {
+                recursive_ctes: false,
+                ..trino
+            }
+        );
     }
 }
 
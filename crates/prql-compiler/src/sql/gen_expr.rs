@@ -4,20 +4,21 @@ use anyhow::{anyhow, bail, Result};
 use itertools::Itertools;
 use regex::Regex;
 use sqlparser::ast::{
-    self as sql_ast, BinaryOperator, DateTimeField, Function, FunctionArg, FunctionArgExpr,
+    self as sql_ast, BinaryOperator, DateTimeField, Fetch, Function, FunctionArg, FunctionArgExpr,
     ObjectName, OrderByExpr, SelectItem, Top, UnaryOperator, Value, WindowFrameBound, WindowSpec,
 };
 
 use crate::error::{Error, Span, WithErrorInfo};
-use crate::generic::{ColumnSort, SortDirection, WindowFrame, WindowKind};
+use crate::generic::{ColumnSort, NullsOrder, SortDirection, WindowFrame, WindowKind};
 use crate::ir::generic::{InterpolateItem, Range};
 use crate::ir::pl::{self, Ident, Literal};
 use crate::ir::rq::*;
 use crate::sql::srq::context::ColumnDecl;
-use crate::utils::{OrMap, VALID_IDENT};
+use crate::utils::{OrMap, VALID_IDENT, VALID_IDENT_ANY_CASE};
 
+use super::dialect::{IdentCaseFolding, SQLiteDialect};
 use super::gen_projection::try_into_exprs;
-use super::{keywords, Context};
+use super::{keywords, Context, QuotingStyle, SqlStandard};
 
 pub(super) fn translate_expr(expr: Expr, ctx: &mut Context) -> Result<ExprOrSource> {
     Ok(match expr.kind {
@@ -81,6 +82,10 @@ pub(super) fn translate_expr(expr: Expr, ctx: &mut Context) -> Result<ExprOrSour
             .into()
         }
         ExprKind::Operator { ref name, ref args } => {
+            if let Some(interval) = try_into_compound_interval(&expr, ctx) {
+                return Ok(interval.into());
+            }
+
             // A few special cases and then fall-through to the standard approach.
             match name.as_str() {
                 // See notes in `std.rs` re whether we use names vs.
@@ -102,6 +107,10 @@ pub(super) fn translate_expr(expr: Expr, ctx: &mut Context) -> Result<ExprOrSour
                     }
                 }
                 "std.concat" => return Ok(process_concat(&expr, ctx)?.into()),
+                "std.array_in" => return Ok(process_array_in(args, ctx)?.into()),
+                "std.starts_with" | "std.contains" => {
+                    return Ok(process_like(name, args, ctx)?.into())
+                }
                 _ => match try_into_between(expr.clone(), ctx)? {
                     Some(between_expr) => return Ok(between_expr.into()),
                     None => {
@@ -185,6 +194,96 @@ fn process_concat(expr: &Expr, ctx: &mut Context) -> Result<sql_ast::Expr> {
     }
 }
 
+/// Translates `value | in [a, b, c]` into `value IN (a, b, c)`
+fn process_array_in(args: &[Expr], ctx: &mut Context) -> Result<sql_ast::Expr> {
+    let (value, list) = args.split_first().unwrap();
+
+    let strength = sql_ast::Expr::InList {
+        expr: Box::new(sql_ast::Expr::Value(Value::Null)),
+        list: vec![],
+        negated: false,
+    }
+    .binding_strength();
+    let expr = translate_operand(value.clone(), strength, false, ctx)?;
+
+    let list = list
+        .iter()
+        .map(|item| translate_expr(item.clone(), ctx).map(|x| x.into_ast()))
+        .try_collect()?;
+
+    Ok(sql_ast::Expr::InList {
+        expr: Box::new(expr.into_ast()),
+        list,
+        negated: false,
+    })
+}
+
+/// Translates `starts_with` and `contains` into `LIKE`. Wildcards of a
+/// literal pattern are escaped, so they match only themselves.
+fn process_like(name: &str, args: &[Expr], ctx: &mut Context) -> Result<sql_ast::Expr> {
+    let (value, needle) = (&args[0], &args[1]);
+
+    let strength = sql_ast::Expr::Like {
+        negated: false,
+        expr: Box::new(sql_ast::Expr::Value(Value::Null)),
+        pattern: Box::new(sql_ast::Expr::Value(Value::Null)),
+        escape_char: None,
+    }
+    .binding_strength();
+    let expr = translate_operand(value.clone(), strength, false, ctx)?;
+
+    let prefix = if name == "std.contains" { "%" } else { "" };
+
+    let (pattern, escape_char) = if let ExprKind::Literal(Literal::String(needle)) = &needle.kind {
+        let supports_escape = ctx.dialect.supports_like_escape();
+        let escape = if supports_escape { '!' } else { '\\' };
+
+        let mut escaped = String::with_capacity(needle.len());
+        for c in needle.chars() {
+            if matches!(c, '%' | '_') || c == escape {
+                escaped.push(escape);
+            }
+            escaped.push(c);
+        }
+        let escape_char = (supports_escape && escaped.len() != needle.len()).then_some(escape);
+        if !supports_escape {
+            // string literals of these dialects also use backslash escapes
+            escaped = escaped.replace('\\', "\\\\");
+        }
+
+        let pattern = Value::SingleQuotedString(format!("{prefix}{escaped}%"));
+        (sql_ast::Expr::Value(pattern), escape_char)
+    } else {
+        // wildcards in the values of the pattern are not escaped
+        let wildcard = |text: &str| Expr {
+            kind: ExprKind::Literal(Literal::String(text.to_string())),
+            span: None,
+        };
+        let mut parts = vec![needle.clone(), wildcard("%")];
+        if !prefix.is_empty() {
+            parts.insert(0, wildcard(prefix));
+        }
+        let concat = parts
+            .into_iter()
+            .reduce(|left, right| Expr {
+                kind: ExprKind::Operator {
+                    name: "std.concat".to_string(),
+                    args: vec![left, right],
+                },
+                span: None,
+            })
+            .unwrap();
+        (process_concat(&concat, ctx)?, None)
+    };
+
+    Ok(sql_ast::Expr::Like {
+        negated: false,
+        expr: Box::new(expr.into_ast()),
+        pattern: Box::new(pattern),
+        escape_char,
+    })
+}
+
 fn translate_binary_operator(
     left: &Expr,
     right: &Expr,
@@ -210,6 +309,51 @@ fn collect_concat_args(expr: &Expr) -> Vec<&Expr> {
     }
 }
 
+/// Translate a sum of interval literals, such as `1years + 6months`, into a
+/// single `INTERVAL '1 year 6 months'`, for dialects that accept intervals as
+/// strings. Other dialects add each of the intervals separately.
+fn try_into_compound_interval(expr: &Expr, ctx: &Context) -> Option<sql_ast::Expr> {
+    fn collect_parts(expr: &Expr, negate: bool, parts: &mut Vec<(i64, String)>) -> bool {
+        match &expr.kind {
+            ExprKind::Literal(Literal::ValueAndUnit(vau)) if vau.unit != "%" => {
+                parts.push((if negate { -vau.n } else { vau.n }, vau.unit.clone()));
+                true
+            }
+            ExprKind::Operator { name, args } if name == "std.add" || name == "std.sub" => {
+                let [left, right] = args.as_slice() else {
+                    return false;
+                };
+                collect_parts(left, negate, parts)
+                    && collect_parts(right, negate ^ (name == "std.sub"), parts)
+            }
+            _ => false,
+        }
+    }
+
+    if !ctx.dialect.compound_intervals() {
+        return None;
+    }
+    let mut parts = Vec::new();
+    if !collect_parts(expr, false, &mut parts) || parts.len() < 2 {
+        return None;
+    }
+
+    let value = parts
+        .into_iter()
+        .map(|(n, unit)| match n {
+            1 | -1 => format!("{n} {}", unit.trim_end_matches('s')),
+            _ => format!("{n} {unit}"),
+        })
+        .join(" ");
+    Some(sql_ast::Expr::Interval(sqlparser::ast::Interval {
+        value: Box::new(sql_ast::Expr::Value(Value::SingleQuotedString(value))),
+        leading_field: None,
+        leading_precision: None,
+        last_field: None,
+        fractional_seconds_precision: None,
+    }))
+}
+
 /// Translate expr into a BETWEEN statement if possible, otherwise returns the expr unchanged.
 fn try_into_between(expr: Expr, ctx: &mut Context) -> Result<Option<sql_ast::Expr>, anyhow::Error> {
     match expr.kind {
@@ -255,7 +399,7 @@ fn operator_from_name(name: &str) -> Option<BinaryOperator> {
         "std.mul" => Some(Multiply),
         "std.add" => Some(Plus),
         "std.sub" => Some(Minus),
-        "std.eq" => Some(Eq),
+        "std.eq" | "std.eq_self" => Some(Eq),
         "std.ne" => Some(NotEq),
         "std.gt" => Some(Gt),
         "std.lt" => Some(Lt),
@@ -281,11 +425,20 @@ pub(super) fn translate_literal(l: Literal, ctx: &Context) -> Result<sql_ast::Ex
             value,
             ctx,
         ),
-        Literal::Timestamp(value) => translate_datetime_literal(
-            sql_ast::DataType::Timestamp(None, sql_ast::TimezoneInfo::None),
-            value,
-            ctx,
-        ),
+        Literal::Timestamp(value) => {
+            let has_offset = Regex::new(r"[+-]\d{2}:?\d{2}$").unwrap().is_match(&value);
+            let timezone = if !has_offset || ctx.dialect.is::<SQLiteDialect>() {
+                sql_ast::TimezoneInfo::None
+            } else if ctx.dialect.supports_timestamp_tz() {
+                sql_ast::TimezoneInfo::WithTimeZone
+            } else {
+                log::warn!(
+                    "timestamp `{value}` has a time zone offset, which this dialect may not respect"
+                );
+                sql_ast::TimezoneInfo::None
+            };
+            translate_datetime_literal(sql_ast::DataType::Timestamp(None, timezone), value, ctx)
+        }
         Literal::ValueAndUnit(vau) => {
             let sql_parser_datetime = match vau.unit.as_str() {
                 "years" => DateTimeField::Year,
@@ -322,7 +475,7 @@ fn translate_datetime_literal(
     value: String,
     ctx: &Context,
 ) -> sql_ast::Expr {
-    if ctx.dialect.is::<crate::sql::dialect::SQLiteDialect>() {
+    if ctx.dialect.is::<SQLiteDialect>() {
         translate_datetime_literal_with_sqlite_function(data_type, value)
     } else {
         translate_datetime_literal_with_typed_string(data_type, value)
@@ -525,6 +678,14 @@ pub(super) fn top_of_i64(take: i64, ctx: &mut Context) -> Top {
     }
 }
 
+pub(super) fn fetch_of_i64(take: i64) -> Fetch {
+    Fetch {
+        quantity: Some(expr_of_i64(take)),
+        with_ties: false,
+        percent: false,
+    }
+}
+
 pub(super) fn translate_select_item(cid: CId, ctx: &mut Context) -> Result<SelectItem> {
     let expr = translate_cid(cid, ctx)?.into_ast();
 
@@ -560,6 +721,16 @@ fn translate_windowed(
     ctx: &mut Context,
     span: Option<Span>,
 ) -> Result<ExprOrSource> {
+    if ctx.sql_standard < SqlStandard::Sql2003 {
+        return Err(Error::new_simple(format!(
+            "window functions require {}, but the target is {}",
+            SqlStandard::Sql2003,
+            ctx.sql_standard
+        ))
+        .with_span(span)
+        .into());
+    }
+
     let default_frame = {
         let (kind, range) = if window.sort.is_empty() {
             (WindowKind::Rows, Range::unbounded())
@@ -578,6 +749,9 @@ fn translate_windowed(
         WindowFrame { kind, range }
     };
 
+    // Only aggregate functions are computed over a frame. Ranking functions and
+    // `LAG` / `LEAD` depend only on the partition and the order, so a frame
+    // would be ignored by some databases and rejected by others.
     let supports_frame = matches!(
         expr,
         ExprOrSource::Source(SourceExpr {
@@ -591,6 +765,7 @@ fn translate_windowed(
         order_by: (window.sort)
             .into_iter()
             .map(|sort| translate_column_sort(&sort, ctx))
+            .flatten_ok()
             .try_collect()?,
         window_frame: if supports_frame && window.frame != default_frame {
             Some(try_into_window_frame(window.frame)?)
@@ -643,19 +818,64 @@ fn try_into_window_frame(frame: WindowFrame<Expr>) -> Result<sql_ast::WindowFram
     })
 }
 
+/// Translates a sort into ORDER BY expressions.
+///
+/// Usually that's a single expression, but if position of nulls is specified
+/// and the dialect does not support `NULLS FIRST`, a sort by `IS NULL` is
+/// prepended.
 pub(super) fn translate_column_sort(
     sort: &ColumnSort<CId>,
     ctx: &mut Context,
-) -> Result<OrderByExpr> {
-    Ok(OrderByExpr {
-        expr: translate_cid(sort.column, ctx)?.into_ast(),
-        asc: if matches!(sort.direction, SortDirection::Asc) {
-            None // default order is ASC, so there is no need to emit it
-        } else {
-            Some(false)
+) -> Result<Vec<OrderByExpr>> {
+    let expr = translate_cid(sort.column, ctx)?.into_ast();
+    let asc = if matches!(sort.direction, SortDirection::Asc) {
+        None // default order is ASC, so there is no need to emit it
+    } else {
+        Some(false)
+    };
+    let nulls_first = sort.nulls.map(|n| matches!(n, NullsOrder::First));
+
+    if nulls_first.is_none() || ctx.dialect.supports_nulls_order() {
+        return Ok(vec![OrderByExpr {
+            expr,
+            asc,
+            nulls_first,
+        }]);
+    }
+
+    if ctx.strict {
+        return Err(Error::new_simple(format!(
+            "The dialect {} does not support NULLS FIRST or NULLS LAST",
+            ctx.dialect_enum
+        ))
+        .push_hint("remove `nulls` from the sort, or remove `strict:true` to emulate it")
+        .into());
+    }
+
+    // CASE WHEN expr IS NULL THEN 0 ELSE 1 END
+    let (null, not_null) = if nulls_first == Some(true) {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    let is_null = sql_ast::Expr::Case {
+        operand: None,
+        conditions: vec![sql_ast::Expr::IsNull(Box::new(expr.clone()))],
+        results: vec![expr_of_i64(null)],
+        else_result: Some(Box::new(expr_of_i64(not_null))),
+    };
+    Ok(vec![
+        OrderByExpr {
+            expr: is_null,
+            asc: None,
+            nulls_first: None,
         },
-        nulls_first: None,
-    })
+        OrderByExpr {
+            expr,
+            asc,
+            nulls_first: None,
+        },
+    ])
 }
 
 /// Translate a PRQL Ident to a Vec of SQL Idents.
@@ -671,7 +891,11 @@ pub(super) fn translate_ident(
     let mut parts = Vec::with_capacity(4);
     if !ctx.query.omit_ident_prefix || column.is_none() {
         if let Some(table) = table_ident {
-            parts.extend(table.into_iter());
+            if ctx.dialect.splits_dotted_idents() {
+                parts.extend(table.into_iter().flat_map(split_dotted_ident));
+            } else {
+                parts.extend(table.into_iter());
+            }
         }
     }
 
@@ -683,8 +907,50 @@ pub(super) fn translate_ident(
         .collect()
 }
 
+/// Splits a name such as `db.schema.table` into parts, so each of them can
+/// be quoted separately. Dots escaped with a backslash don't split the name.
+///
+/// When some part would not be a valid identifier (e.g. `db.schema.t-able`),
+/// the name is probably not a path, so it is kept as a single name.
+fn split_dotted_ident(ident: String) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = ident.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'.') => {
+                chars.next();
+                parts.last_mut().unwrap().push('.');
+            }
+            '.' => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    // dots that remain in parts were escaped
+    let is_path = parts
+        .iter()
+        .all(|part| part != "*" && VALID_IDENT_ANY_CASE.is_match(&part.replace('.', "_")));
+    if parts.len() == 1 || is_path {
+        parts
+    } else {
+        vec![ident]
+    }
+}
+
 pub(super) fn translate_ident_part(ident: String, ctx: &Context) -> sql_ast::Ident {
-    let is_bare = VALID_IDENT.is_match(&ident);
+    let is_bare = match ctx.quoting_style {
+        QuotingStyle::CasePreserving => match ctx.dialect.ident_case_folding() {
+            IdentCaseFolding::Lower => VALID_IDENT.is_match(&ident),
+
+            // uppercase idents are not changed by folding, so they can be bare
+            IdentCaseFolding::Upper => {
+                VALID_IDENT.is_match(&ident)
+                    || (ident == ident.to_ascii_uppercase()
+                        && VALID_IDENT_ANY_CASE.is_match(&ident))
+            }
+        },
+        QuotingStyle::Minimal => VALID_IDENT_ANY_CASE.is_match(&ident),
+    };
 
     if is_bare && !keywords::is_keyword(&ident) {
         sql_ast::Ident::new(ident)
@@ -763,6 +1029,8 @@ impl SQLExpression for sql_ast::Expr {
 
             sql_ast::Expr::Like { .. } | sql_ast::Expr::ILike { .. } => 7,
 
+            sql_ast::Expr::InList { .. } => 6,
+
             sql_ast::Expr::IsNull(_) | sql_ast::Expr::IsNotNull(_) => 5,
 
             // all other items types bind stronger (function calls, literals, ...)
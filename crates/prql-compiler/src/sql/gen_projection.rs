@@ -125,6 +125,8 @@ pub(super) fn translate_select_items(
     mut excluded: Excluded,
     ctx: &mut Context,
 ) -> Result<Vec<SelectItem>> {
+    let qualify_stars = cols.len() > 1 && !ctx.dialect.unqualified_star_with_columns();
+
     let mut res: Vec<_> = cols
         .into_iter()
         .map(|cid| {
@@ -139,11 +141,16 @@ pub(super) fn translate_select_items(
             let t = &ctx.anchor.relation_instances[riid];
             let table_name = t.table_ref.name.clone().map(Ident::from_name);
 
+            let omit_ident_prefix = ctx.query.omit_ident_prefix;
+            ctx.query.omit_ident_prefix &= !qualify_stars;
             let ident = translate_ident(table_name, Some("*".to_string()), ctx);
+            ctx.query.omit_ident_prefix = omit_ident_prefix;
 
             // excluded columns
             let opts = (excluded.remove(&cid))
-                .and_then(|excluded| translate_exclude(ctx, excluded))
+                .map(|excluded| translate_exclude(ctx, excluded))
+                .transpose()?
+                .flatten()
                 .unwrap_or_default();
 
             Ok(if ident.len() > 1 {
@@ -169,27 +176,41 @@ pub(super) fn translate_select_items(
 fn translate_exclude(
     ctx: &mut Context,
     excluded: HashSet<CId>,
-) -> Option<WildcardAdditionalOptions> {
-    let excluded = as_col_names(&excluded, &ctx.anchor);
-
+) -> Result<Option<WildcardAdditionalOptions>> {
     let Some(supported) = ctx.dialect.column_exclude() else {
-        // TODO: eventually this should throw an error
-        //   I don't want to do this now, because we have no way around it.
-        //   We could also ask the user to add table definitions.
+        // Columns introduced by the compiler (i.e. row numbers of
+        // `group (take 1)`) are included with the star. Columns that the query
+        // excluded from a relation whose columns we don't know cannot be
+        // expressed at all.
+        let requested: HashSet<_> = (excluded.iter())
+            .filter(|cid| !ctx.anchor.generated_cids.contains(*cid))
+            .cloned()
+            .collect();
+        if !requested.is_empty() {
+            return Err(Error::new_simple(format!(
+                "Excluding columns {} is not supported by dialect {}",
+                as_col_names(&requested, &ctx.anchor).join(", "),
+                ctx.dialect_enum
+            ))
+            .push_hint("specify columns of the relation, for example with `select`")
+            .into());
+        }
+
         if log::log_enabled!(log::Level::Warn) {
-            let excluded = excluded.join(", ");
+            let excluded = as_col_names(&excluded, &ctx.anchor).join(", ");
 
             log::warn!("Columns {excluded} will be included with *, but were not requested.")
         }
-        return None;
+        return Ok(None);
     };
 
+    let excluded = as_col_names(&excluded, &ctx.anchor);
     let mut excluded = excluded
         .into_iter()
         .map(|name| translate_ident_part(name.to_string(), ctx))
         .collect_vec();
 
-    Some(match supported {
+    Ok(Some(match supported {
         ColumnExclude::Exclude => WildcardAdditionalOptions {
             opt_exclude: Some(ExcludeSelectItem::Multiple(excluded)),
             ..Default::default()
@@ -201,7 +222,7 @@ fn translate_exclude(
             }),
             ..Default::default()
         },
-    })
+    }))
 }
 
 fn as_col_names<'a>(cids: &'a HashSet<CId>, ctx: &'a AnchorContext) -> Vec<&'a str> {
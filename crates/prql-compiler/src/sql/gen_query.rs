@@ -2,6 +2,8 @@
 //! then to a String. We use sqlparser because it's trivial to create the string
 //! once it's in their AST (it's just `.to_string()`). It also lets us support a
 //! few dialects of SQL immediately.
+use std::collections::HashMap;
+
 use anyhow::{anyhow, Result};
 use itertools::Itertools;
 use regex::Regex;
@@ -13,26 +15,57 @@ use sqlparser::ast::{
 use crate::error::WithErrorInfo;
 use crate::ir::generic::InterpolateItem;
 use crate::ir::pl::{JoinSide, Literal};
-use crate::ir::rq::{CId, Expr, ExprKind, RelationLiteral, RelationalQuery};
+use crate::ir::rq::{
+    CId, Expr, ExprKind, RelationColumn, RelationLiteral, RelationalQuery, TableRef,
+};
 use crate::utils::{BreakUp, Pluck};
-use crate::Error;
+use crate::{Error, Options};
 
 use super::gen_expr::*;
 use super::gen_projection::*;
 use super::srq::ast::{Cte, CteKind, RelationExpr, RelationExprKind, SqlRelation, SqlTransform};
 
 use super::operators::translate_operator;
-use super::{Context, Dialect};
+use super::srq::context::{ColumnDecl, RIId};
+use super::srq::CidCollector;
+use super::{Context, GroupByStyle, JoinStyle, SqlStandard};
 
 type Transform = SqlTransform<RelationExpr, ()>;
 
-pub fn translate_query(query: RelationalQuery, dialect: Option<Dialect>) -> Result<sql_ast::Query> {
+pub fn translate_query(
+    query: RelationalQuery,
+    options: &Options,
+) -> Result<(sql_ast::Query, Vec<String>)> {
+    let crate::Target::Sql(dialect) = options.target;
+
     // compile from RQ to SRQ
-    let (srq_query, mut ctx) = super::srq::compile_query(query, dialect)?;
+    let (srq_query, mut ctx) = super::srq::compile_query(
+        query,
+        dialect,
+        options.inline_single_use_ctes,
+        options.materialize_window_args,
+    )?;
+    ctx.group_by_style = options.group_by_style;
+    ctx.join_style = options.join_style;
+    ctx.explain_splits = options.explain_splits;
+    ctx.quoting_style = options.quoting_style;
+    ctx.safe_cast = options.safe_cast;
+    ctx.allow_approximations = options.allow_approximations;
+    ctx.sql_standard = options.sql_standard;
 
     let mut query = translate_relation(srq_query.main_relation, &mut ctx)?;
 
     if !srq_query.ctes.is_empty() {
+        if ctx.sql_standard < SqlStandard::Sql99 {
+            return Err(Error::new_simple(format!(
+                "CTEs require {}, but the target is {}",
+                SqlStandard::Sql99,
+                ctx.sql_standard
+            ))
+            .push_hint("relations that are used once can be inlined with `inline_single_use_ctes`")
+            .into());
+        }
+
         // attach CTEs
         let mut cte_tables = Vec::new();
         let mut recursive = false;
@@ -47,7 +80,7 @@ pub fn translate_query(query: RelationalQuery, dialect: Option<Dialect>) -> Resu
         });
     }
 
-    Ok(query)
+    Ok((query, ctx.placeholders))
 }
 
 fn translate_relation(relation: SqlRelation, ctx: &mut Context) -> Result<sql_ast::Query> {
@@ -80,8 +113,12 @@ fn translate_select_pipeline(
     ctx.query.omit_ident_prefix = table_count == 1;
     ctx.query.pre_projection = true;
 
-    let mut from: Vec<_> = pipeline
-        .pluck(|t| t.into_from())
+    let mut from: Vec<_> = pipeline.pluck(|t| t.into_from());
+
+    // relations on the left side of each join
+    let mut left_riids = from.iter().map(|source| source.riid).collect_vec();
+
+    let mut from: Vec<_> = from
         .into_iter()
         .map(|source| -> Result<TableWithJoins> {
             Ok(TableWithJoins {
@@ -91,11 +128,31 @@ fn translate_select_pipeline(
         })
         .try_collect()?;
 
-    let joins = pipeline
-        .pluck(|t| t.into_join())
-        .into_iter()
-        .map(|j| translate_join(j, ctx))
-        .collect::<Result<Vec<_>>>()?;
+    if let Some(percent) = pipeline.pluck(|t| t.into_sample()).pop() {
+        if let Some(from) = from.first_mut() {
+            translate_sample(&mut from.relation, percent, ctx)?;
+        }
+    }
+
+    // rows read directly from a table are sampled by the table clause, if the
+    // dialect has one, otherwise they are sorted randomly and the first taken
+    let mut sample_rows = pipeline.pluck(|t| t.into_sample_rows()).pop();
+    if let (Some(rows), [from]) = (sample_rows, from.as_mut_slice()) {
+        let reads_table = matches!(from.relation, TableFactor::Table { .. })
+            && (pipeline.iter()).all(|t| matches!(t, Transform::Select(_) | Transform::Sort(_)));
+
+        if let (true, Some(clause)) = (reads_table, ctx.dialect.table_sample_rows(rows)) {
+            append_to_table(&mut from.relation, clause, ctx);
+            sample_rows = None;
+        }
+    }
+
+    let mut joins = Vec::new();
+    for join in pipeline.pluck(|t| t.into_join()) {
+        let riid = join.1.riid;
+        joins.push(translate_join(join, &left_riids, ctx)?);
+        left_riids.push(riid);
+    }
     if !joins.is_empty() {
         if let Some(from) = from.last_mut() {
             from.joins = joins;
@@ -109,8 +166,21 @@ fn translate_select_pipeline(
         .into_iter()
         .exactly_one()
         .unwrap();
+
+    let unnests = translate_unnests(&projection, ctx)?;
+    if !unnests.is_empty() {
+        if let Some(from) = from.last_mut() {
+            from.joins.extend(unnests);
+        } else {
+            return Err(anyhow!("Cannot use `unnest` without `from`"));
+        }
+    }
+
+    let is_windowed = projection.iter().any(|cid| is_windowed(cid, ctx));
+
     let projection = translate_wildcards(&ctx.anchor, projection);
     let projection = translate_select_items(projection.0, projection.1, ctx)?;
+    let projection = collapse_using_wildcards(projection, &from);
 
     let order_by = pipeline.pluck(|t| t.into_sort());
     let takes = pipeline.pluck(|t| t.into_take());
@@ -147,14 +217,33 @@ fn translate_select_pipeline(
         pipeline.break_up(|t| matches!(t, Transform::Aggregate { .. } | Transform::Union { .. }));
 
     // WHERE and HAVING
-    let where_ = filter_of_conditions(before_agg.pluck(|t| t.into_filter()), ctx)?;
-    let having = filter_of_conditions(after_agg.pluck(|t| t.into_filter()), ctx)?;
+    let mut where_conditions = before_agg.pluck(|t| t.into_filter());
+    let mut having_conditions = after_agg.pluck(|t| t.into_filter());
+
+    // Conditions on the grouping keys only remove whole groups, so they can be
+    // applied before aggregation. Conditions without columns must stay in
+    // HAVING, because an aggregation without keys always produces a row.
+    if let Some(Transform::Aggregate { partition, .. }) = after_agg.first() {
+        let (on_keys, on_aggregates): (Vec<_>, Vec<_>) = having_conditions
+            .into_iter()
+            .flat_map(conjunctions)
+            .partition(|cond| {
+                let cids = CidCollector::collect(cond.clone());
+                !cids.is_empty() && cids.iter().all(|cid| partition.contains(cid))
+            });
+        where_conditions.extend(on_keys);
+        having_conditions = on_aggregates;
+    }
+
+    let where_ = filter_of_conditions(where_conditions, ctx)?;
+    let having = filter_of_conditions(having_conditions, ctx)?;
 
     // GROUP BY
     let aggregate = after_agg.pluck(|t| t.into_aggregate()).into_iter().next();
     let group_by: Vec<CId> = aggregate.map(|(part, _)| part).unwrap_or_default();
     ctx.query.allow_stars = ctx.dialect.stars_in_group();
     let group_by = try_into_exprs(group_by, ctx, None)?;
+    let group_by = reference_projection(group_by, &projection, ctx);
     ctx.query.allow_stars = true;
 
     ctx.query.pre_projection = false;
@@ -162,7 +251,11 @@ fn translate_select_pipeline(
     let ranges = takes.into_iter().map(|x| x.range).collect();
     let take = range_of_ranges(ranges)?;
     let offset = take.start.map(|s| s - 1).unwrap_or(0);
-    let limit = take.end.map(|e| e - offset);
+    let mut limit = take.end.map(|e| e - offset);
+
+    // Without `LIMIT m OFFSET n`, the offset is expressed with `OFFSET n ROWS`,
+    // which is followed by `FETCH` instead of `TOP` or `LIMIT`.
+    let use_fetch = ctx.dialect.use_fetch() || (!ctx.dialect.limit_offset() && offset != 0);
 
     let offset = if offset == 0 {
         None
@@ -171,7 +264,11 @@ fn translate_select_pipeline(
         let expr = Expr { kind, span: None };
         Some(sqlparser::ast::Offset {
             value: translate_expr(expr, ctx)?.into_ast(),
-            rows: sqlparser::ast::OffsetRows::None,
+            rows: if use_fetch {
+                sqlparser::ast::OffsetRows::Rows
+            } else {
+                sqlparser::ast::OffsetRows::None
+            },
         })
     };
 
@@ -182,23 +279,94 @@ fn translate_select_pipeline(
             sorts
                 .iter()
                 .map(|s| translate_column_sort(s, ctx))
+                .flatten_ok()
                 .try_collect()
         })
         .transpose()?
         .unwrap_or_default();
 
-    let (top, limit) = if ctx.dialect.use_top() {
-        (limit.map(|l| top_of_i64(l, ctx)), None)
+    let order_by = if let Some(rows) = sample_rows {
+        if ctx.strict {
+            return Err(Error::new_simple(format!(
+                "The dialect {} does not support sampling rows of a table",
+                ctx.dialect_enum
+            ))
+            .push_hint("remove `strict:true` to take rows in random order instead")
+            .into());
+        }
+        limit = Some(rows);
+        vec![sql_ast::OrderByExpr {
+            expr: sql_ast::Expr::Function(sql_ast::Function {
+                name: sql_ast::ObjectName(
+                    (ctx.dialect.random().split('.'))
+                        .map(sql_ast::Ident::new)
+                        .collect(),
+                ),
+                args: vec![],
+                over: None,
+                distinct: false,
+                special: false,
+                order_by: vec![],
+            }),
+            asc: None,
+            nulls_first: None,
+        }]
+    } else {
+        order_by
+    };
+
+    if use_fetch && ctx.dialect.use_top() && order_by.is_empty() {
+        return Err(Error::new_simple(format!(
+            "The dialect {} can only skip rows of a sorted relation",
+            ctx.dialect_enum
+        ))
+        .push_hint("add a `sort` before the `take`")
+        .into());
+    }
+
+    // without a clause that limits rows, they are numbered by ROWNUM as they
+    // are filtered, so that has to happen after sorting, grouping and windows
+    let rownum = if ctx.dialect.supports_row_limiting() {
+        None
     } else {
-        (None, limit.map(expr_of_i64))
+        limit.take().map(|limit| sql_ast::Expr::BinaryOp {
+            left: Box::new(sql_ast::Expr::Identifier(sql_ast::Ident::new("ROWNUM"))),
+            op: sql_ast::BinaryOperator::LtEq,
+            right: Box::new(expr_of_i64(limit)),
+        })
+    };
+    let is_simple = order_by.is_empty()
+        && group_by.is_empty()
+        && having.is_none()
+        && distinct.is_none()
+        && !is_windowed;
+    let (where_, rownum) = match (rownum, where_) {
+        (Some(rownum), where_) if is_simple => (Some(and(where_, rownum)), None),
+        (rownum, where_) => (where_, rownum),
     };
 
+    let (top, fetch, limit) = if use_fetch {
+        (None, limit.map(fetch_of_i64), None)
+    } else if ctx.dialect.use_top() {
+        (limit.map(|l| top_of_i64(l, ctx)), None, None)
+    } else {
+        (None, None, limit.map(expr_of_i64))
+    };
+
+    for from in &mut from {
+        from.relation = translate_alias_keyword(from.relation.clone(), ctx);
+        for join in &mut from.joins {
+            join.relation = translate_alias_keyword(join.relation.clone(), ctx);
+        }
+    }
+
     ctx.pop_query();
 
-    Ok(sql_ast::Query {
+    let query = sql_ast::Query {
         order_by,
         limit,
         offset,
+        fetch,
         ..default_query(SetExpr::Select(Box::new(Select {
             distinct,
             top,
@@ -209,9 +377,114 @@ fn translate_select_pipeline(
             having,
             ..default_select()
         })))
+    };
+
+    // `SELECT * FROM (query) WHERE ROWNUM <= n`
+    Ok(match rownum {
+        Some(rownum) => default_query(SetExpr::Select(Box::new(Select {
+            projection: vec![SelectItem::Wildcard(
+                sql_ast::WildcardAdditionalOptions::default(),
+            )],
+            from: vec![TableWithJoins {
+                relation: TableFactor::Derived {
+                    lateral: false,
+                    subquery: Box::new(query),
+                    alias: None,
+                },
+                joins: vec![],
+            }],
+            selection: Some(rownum),
+            ..default_select()
+        }))),
+        None => query,
     })
 }
 
+/// Whether the column is computed with a window function, either directly or
+/// through the columns it refers to.
+fn is_windowed(cid: &CId, ctx: &Context) -> bool {
+    let Some(ColumnDecl::Compute(compute)) = ctx.anchor.column_decls.get(cid) else {
+        return false;
+    };
+    compute.window.is_some()
+        || CidCollector::collect(compute.expr.clone())
+            .iter()
+            .any(|cid| is_windowed(cid, ctx))
+}
+
+/// Joins two conditions with AND.
+fn and(left: Option<sql_ast::Expr>, right: sql_ast::Expr) -> sql_ast::Expr {
+    let Some(left) = left else {
+        return right;
+    };
+    // AND binds stronger than OR
+    let left = match left {
+        sql_ast::Expr::BinaryOp {
+            op: sql_ast::BinaryOperator::Or,
+            ..
+        } => sql_ast::Expr::Nested(Box::new(left)),
+        left => left,
+    };
+    sql_ast::Expr::BinaryOp {
+        left: Box::new(left),
+        op: sql_ast::BinaryOperator::And,
+        right: Box::new(right),
+    }
+}
+
+/// Replaces GROUP BY keys that are also in the projection with their ordinals
+/// or aliases, when requested by [GroupByStyle] and supported by the dialect.
+fn reference_projection(
+    group_by: Vec<sql_ast::Expr>,
+    projection: &[SelectItem],
+    ctx: &Context,
+) -> Vec<sql_ast::Expr> {
+    let supported = match ctx.group_by_style {
+        GroupByStyle::Expressions => false,
+        GroupByStyle::Ordinals => ctx.dialect.group_by_ordinals(),
+        GroupByStyle::Aliases => ctx.dialect.group_by_aliases(),
+    };
+    if !supported {
+        return group_by;
+    }
+
+    group_by
+        .into_iter()
+        .map(|key| {
+            let position = projection.iter().position(|item| match item {
+                SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                    expr == &key
+                }
+                _ => false,
+            });
+            let Some(position) = position else {
+                return key;
+            };
+
+            match (ctx.group_by_style, &projection[position]) {
+                (GroupByStyle::Ordinals, _) => {
+                    // stars expand into an unknown number of columns
+                    let after_star = projection[..position].iter().any(|item| {
+                        matches!(
+                            item,
+                            SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+                        )
+                    });
+                    if after_star {
+                        key
+                    } else {
+                        expr_of_i64(position as i64 + 1)
+                    }
+                }
+                (GroupByStyle::Aliases, SelectItem::ExprWithAlias { alias, .. }) => {
+                    sql_ast::Expr::Identifier(alias.clone())
+                }
+                _ => key,
+            }
+        })
+        .collect()
+}
+
 fn translate_set_ops_pipeline(
     mut top: sql_ast::Query,
     mut pipeline: Vec<Transform>,
@@ -308,6 +581,72 @@ fn translate_relation_expr(relation_expr: RelationExpr, ctx: &mut Context) -> Re
     })
 }
 
+/// Appends the sampling clause of the dialect to a table.
+fn translate_sample(relation: &mut TableFactor, percent: i64, ctx: &mut Context) -> Result<()> {
+    let Some(clause) = ctx.dialect.table_sample(percent) else {
+        return Err(Error::new_simple(format!(
+            "The dialect {} does not support sampling a percentage of rows",
+            ctx.dialect_enum
+        ))
+        .push_hint("use `take` with a number of rows instead")
+        .into());
+    };
+
+    append_to_table(relation, clause, ctx);
+    Ok(())
+}
+
+/// Appends a clause to a table in FROM.
+fn append_to_table(relation: &mut TableFactor, clause: String, ctx: &mut Context) {
+    // sqlparser cannot express TABLESAMPLE, so the last identifier, which is
+    // the alias if there is one, is followed by the clause once rendered
+    let last = match relation {
+        TableFactor::Table {
+            alias: Some(alias), ..
+        } => &mut alias.name,
+        TableFactor::Table { name, .. } => name.0.last_mut().unwrap(),
+        _ => unreachable!(),
+    };
+    *last = ctx.placeholder(format!("{last} {clause}"));
+}
+
+/// Renders the alias of a table without `AS`, if the dialect rejects it.
+fn translate_alias_keyword(relation: TableFactor, ctx: &mut Context) -> TableFactor {
+    if ctx.dialect.table_alias_keyword() {
+        return relation;
+    }
+
+    // sqlparser always renders `AS`, so the alias is rendered with the table
+    match relation {
+        TableFactor::Table {
+            name,
+            alias: Some(alias),
+            args,
+            with_hints,
+        } => {
+            let mut relation = TableFactor::Table {
+                name,
+                alias: None,
+                args,
+                with_hints,
+            };
+            append_to_table(&mut relation, alias.to_string(), ctx);
+            relation
+        }
+        TableFactor::Derived {
+            lateral: false,
+            subquery,
+            alias: Some(alias),
+        } => TableFactor::Table {
+            name: sql_ast::ObjectName(vec![ctx.placeholder(format!("({subquery}) {alias}"))]),
+            alias: None,
+            args: None,
+            with_hints: vec![],
+        },
+        relation => relation,
+    }
+}
+
 fn translate_table_alias(alias: Option<String>, ctx: &mut Context) -> Option<TableAlias> {
     alias
         .map(|ident| translate_ident_part(ident, ctx))
@@ -316,11 +655,27 @@ fn translate_table_alias(alias: Option<String>, ctx: &mut Context) -> Option<Tab
 
 fn translate_join(
     (side, with, filter): (JoinSide, RelationExpr, Expr),
+    left_riids: &[RIId],
     ctx: &mut Context,
 ) -> Result<Join> {
+    let using = match ctx.join_style {
+        _ if !ctx.dialect.join_using() => None,
+        JoinStyle::UsingForShorthand => using_columns(&filter, &with.riid, left_riids, true, ctx),
+        JoinStyle::Using => using_columns(&filter, &with.riid, left_riids, false, ctx),
+        JoinStyle::On => None,
+    };
+
     let relation = translate_relation_expr(with, ctx)?;
 
-    let constraint = JoinConstraint::On(translate_expr(filter, ctx)?.into_ast());
+    let constraint = if let Some(columns) = using {
+        let columns = columns
+            .into_iter()
+            .map(|name| translate_ident_part(name, ctx))
+            .collect();
+        JoinConstraint::Using(columns)
+    } else {
+        JoinConstraint::On(translate_expr(filter, ctx)?.into_ast())
+    };
 
     Ok(Join {
         relation,
@@ -333,11 +688,174 @@ fn translate_join(
     })
 }
 
+/// Replaces `a.*, b.*` with `*` when the relations are joined with `USING`,
+/// so the join columns appear only once.
+fn collapse_using_wildcards(
+    projection: Vec<SelectItem>,
+    from: &[TableWithJoins],
+) -> Vec<SelectItem> {
+    let joins = from.iter().flat_map(|t| &t.joins).collect_vec();
+    let has_using = joins.iter().any(|join| {
+        matches!(
+            join.join_operator,
+            JoinOperator::Inner(JoinConstraint::Using(_))
+                | JoinOperator::LeftOuter(JoinConstraint::Using(_))
+                | JoinOperator::RightOuter(JoinConstraint::Using(_))
+                | JoinOperator::FullOuter(JoinConstraint::Using(_))
+        )
+    });
+    let all_wildcards = projection.len() == from.len() + joins.len()
+        && projection.iter().all(|item| {
+            matches!(item, SelectItem::QualifiedWildcard(_, opts) if opts == &Default::default())
+        });
+
+    if has_using && all_wildcards {
+        vec![SelectItem::Wildcard(Default::default())]
+    } else {
+        projection
+    }
+}
+
+/// Columns of the projection that unnest an array are moved into a
+/// `CROSS JOIN UNNEST(...)`, because set-returning functions are not allowed
+/// in all positions of a SELECT.
+fn translate_unnests(projection: &[CId], ctx: &mut Context) -> Result<Vec<Join>> {
+    let unnests = projection
+        .iter()
+        .filter_map(|cid| {
+            let ColumnDecl::Compute(compute) = &ctx.anchor.column_decls[cid] else {
+                return None;
+            };
+            let ExprKind::Operator { name, args } = &compute.expr.kind else {
+                return None;
+            };
+            if name != "std.unnest" || compute.window.is_some() {
+                return None;
+            }
+            Some((*cid, name.clone(), args.clone(), compute.expr.span))
+        })
+        .collect_vec();
+
+    if !unnests.is_empty() {
+        // the unnested relation is another table in the query
+        ctx.query.omit_ident_prefix = false;
+    }
+
+    let mut joins = Vec::with_capacity(unnests.len());
+    for (cid, name, args, span) in unnests {
+        let source = translate_operator(name, args, ctx).with_span(span)?;
+
+        let alias = match ctx.anchor.column_names.get(&cid) {
+            Some(name) => name.clone(),
+            None => ctx.anchor.col_name.gen(),
+        };
+        ctx.anchor.column_names.insert(cid, alias.clone());
+
+        // from now on, the column is a column of the unnested relation
+        let source_tid = ctx.anchor.tid.gen();
+        ctx.anchor.create_relation_instance(
+            TableRef {
+                source: source_tid,
+                name: Some(alias.clone()),
+                columns: vec![(RelationColumn::Single(Some(alias.clone())), cid)],
+            },
+            HashMap::new(),
+        );
+
+        joins.push(Join {
+            relation: TableFactor::Table {
+                name: sql_ast::ObjectName(vec![sql_ast::Ident::new(source.text)]),
+                alias: Some(simple_table_alias(translate_ident_part(alias, ctx))),
+                args: None,
+                with_hints: vec![],
+            },
+            join_operator: JoinOperator::CrossJoin,
+        });
+    }
+    Ok(joins)
+}
+
+/// Names of columns for `USING`, if the join condition only compares columns
+/// of the left side to the columns of `with` that have the same name.
+///
+/// With `shorthand_only`, the comparisons must come from the `(==id)`
+/// shorthand, which is lowered to `std.eq_self`.
+///
+/// A name that may be exposed by more than one of the `left_riids` relations
+/// would make `USING` ambiguous, so such conditions are expressed with `ON`.
+fn using_columns(
+    filter: &Expr,
+    with: &RIId,
+    left_riids: &[RIId],
+    shorthand_only: bool,
+    ctx: &Context,
+) -> Option<Vec<String>> {
+    // returns the relation and the name of a column reference
+    let relation_column = |expr: &Expr| {
+        let ExprKind::ColumnRef(cid) = &expr.kind else {
+            return None;
+        };
+        match &ctx.anchor.column_decls[cid] {
+            ColumnDecl::RelationColumn(riid, _, RelationColumn::Single(Some(name))) => {
+                Some((*riid, name.clone()))
+            }
+            _ => None,
+        }
+    };
+
+    // whether a relation has (or may have, through a wildcard) a column
+    let exposes = |riid: &RIId, name: &String| {
+        let relation = &ctx.anchor.relation_instances[riid];
+        relation.table_ref.columns.iter().any(|(col, _)| match col {
+            RelationColumn::Single(Some(n)) => n == name,
+            RelationColumn::Single(None) => false,
+            RelationColumn::Wildcard => true,
+        })
+    };
+
+    let mut conditions = vec![filter];
+    let mut names = Vec::new();
+    while let Some(condition) = conditions.pop() {
+        let ExprKind::Operator { name, args } = &condition.kind else {
+            return None;
+        };
+        match (name.as_str(), args.as_slice()) {
+            ("std.and", [left, right]) => {
+                conditions.push(right);
+                conditions.push(left);
+            }
+            ("std.eq", _) if shorthand_only => return None,
+            ("std.eq" | "std.eq_self", [left, right]) => {
+                let (left_riid, left_name) = relation_column(left)?;
+                let (right_riid, right_name) = relation_column(right)?;
+
+                if left_name != right_name || (left_riid == *with) == (right_riid == *with) {
+                    return None;
+                }
+                let exposed_by = left_riids.iter().filter(|r| exposes(r, &left_name));
+                if exposed_by.count() > 1 {
+                    return None;
+                }
+                names.push(left_name);
+            }
+            _ => return None,
+        }
+    }
+    Some(names)
+}
+
 fn translate_cte(cte: Cte, ctx: &mut Context) -> Result<(sql_ast::Cte, bool)> {
     let decl = ctx.anchor.table_decls.get_mut(&cte.tid).unwrap();
     let cte_name = decl.name.clone().unwrap();
+    let split_reason = decl.split_reason.clone();
+
+    let mut cte_name = translate_ident(Some(cte_name), None, ctx).pop().unwrap();
 
-    let cte_name = translate_ident(Some(cte_name), None, ctx).pop().unwrap();
+    // sqlparser cannot express comments, so the explanation precedes the name
+    // of the CTE once rendered
+    if let Some(reason) = split_reason.filter(|_| ctx.explain_splits) {
+        cte_name = ctx.placeholder(format!("-- boundary: {reason}\n{cte_name}"));
+    }
 
     let (query, recursive) = match cte.kind {
         // base case
@@ -345,6 +863,14 @@ fn translate_cte(cte: Cte, ctx: &mut Context) -> Result<(sql_ast::Cte, bool)> {
 
         // special: WITH RECURSIVE
         CteKind::Loop { initial, step } => {
+            if !ctx.dialect.supports_recursive_ctes() {
+                return Err(Error::new_simple(format!(
+                    "`loop` is not supported for dialect {}, because it does not support recursive CTEs",
+                    ctx.dialect_enum
+                ))
+                .into());
+            }
+
             // compile initial
             let initial = query_to_set_expr(translate_relation(initial, ctx)?, ctx);
 
@@ -498,6 +1024,16 @@ fn filter_of_conditions(exprs: Vec<Expr>, context: &mut Context) -> Result<Optio
     })
 }
 
+/// Splits a condition into the operands of its top-level `and`s.
+fn conjunctions(expr: Expr) -> Vec<Expr> {
+    match expr.kind {
+        ExprKind::Operator { name, args } if name == "std.and" => {
+            args.into_iter().flat_map(conjunctions).collect()
+        }
+        kind => vec![Expr { kind, ..expr }],
+    }
+}
+
 fn all(mut exprs: Vec<Expr>) -> Option<Expr> {
     let mut condition = exprs.pop()?;
     while let Some(expr) = exprs.pop() {
@@ -564,18 +1100,19 @@ fn query_to_set_expr(query: sql_ast::Query, context: &mut Context) -> Box<SetExp
 
     // query is not simple, so we need to wrap it into
     // `SELECT * FROM (query)`
+    let relation = TableFactor::Derived {
+        lateral: false,
+        subquery: Box::new(query),
+        alias: Some(simple_table_alias(sql_ast::Ident::new(
+            context.anchor.table_name.gen(),
+        ))),
+    };
     Box::new(SetExpr::Select(Box::new(Select {
         projection: vec![SelectItem::Wildcard(
             sql_ast::WildcardAdditionalOptions::default(),
         )],
         from: vec![TableWithJoins {
-            relation: TableFactor::Derived {
-                lateral: false,
-                subquery: Box::new(query),
-                alias: Some(simple_table_alias(sql_ast::Ident::new(
-                    context.anchor.table_name.gen(),
-                ))),
-            },
+            relation: translate_alias_keyword(relation, context),
             joins: vec![],
         }],
         ..default_select()
@@ -8,10 +8,12 @@ mod keywords;
 mod operators;
 mod srq;
 
-pub use dialect::{Dialect, SupportLevel};
+pub use dialect::{dialect_capabilities, Capabilities, Dialect, SupportLevel};
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
 use crate::{ir::rq::RelationalQuery, Options, COMPILER_VERSION};
 
 use self::dialect::DialectHandler;
@@ -21,9 +23,9 @@ use self::srq::context::AnchorContext;
 /// Translate a PRQL AST into a SQL string.
 pub fn compile(query: RelationalQuery, options: &Options) -> Result<String> {
     let crate::Target::Sql(dialect) = options.target;
-    let sql_ast = gen_query::translate_query(query, dialect)?;
+    let (sql_ast, placeholders) = gen_query::translate_query(query, options)?;
 
-    let sql = sql_ast.to_string();
+    let sql = fill_placeholders(sql_ast.to_string(), &placeholders);
 
     // formatting
     let sql = if options.format {
@@ -38,6 +40,26 @@ pub fn compile(query: RelationalQuery, options: &Options) -> Result<String> {
         sql
     };
 
+    // explain
+    let sql = if let Some(explain) = options.explain {
+        let dialect = dialect.unwrap_or_default();
+        let Some(prefix) = dialect.handler().explain(explain) else {
+            let statement = match explain {
+                Explain::Plan => "EXPLAIN",
+                Explain::Analyze => "EXPLAIN ANALYZE",
+            };
+            return Err(Error::new_simple(format!(
+                "The dialect {dialect} does not support {statement}"
+            ))
+            .into());
+        };
+
+        let separator = if options.format { "\n" } else { " " };
+        format!("{prefix}{separator}{sql}")
+    } else {
+        sql
+    };
+
     // signature
     let sql = if options.signature_comment {
         let pre = if options.format { "\n" } else { " " };
@@ -54,9 +76,125 @@ pub fn compile(query: RelationalQuery, options: &Options) -> Result<String> {
         sql
     };
 
+    // parentheses
+    let sql = if options.wrap_in_parens {
+        let sql = sql.trim_end();
+        // the closing parenthesis must not end up in the signature comment
+        let separator = if options.signature_comment { "\n" } else { "" };
+        format!("({sql}{separator})")
+    } else {
+        sql
+    };
+
     Ok(sql)
 }
 
+/// Replaces identifiers of [Context::placeholder] with the SQL they stand for.
+fn fill_placeholders(mut sql: String, placeholders: &[String]) -> String {
+    // in reverse, so `_prql_placeholder_1` does not match `_prql_placeholder_10`
+    for (index, replacement) in placeholders.iter().enumerate().rev() {
+        sql = sql.replace(&placeholder_name(index), replacement);
+    }
+    sql
+}
+
+fn placeholder_name(index: usize) -> String {
+    format!("_prql_placeholder_{index}")
+}
+
+/// Wraps the query into a statement that describes how the database would
+/// execute it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Explain {
+    /// Display the plan of the query, without executing it.
+    Plan,
+
+    /// Execute the query and display the plan, along with run-time statistics.
+    Analyze,
+}
+
+/// How GROUP BY refers to keys that are also in the projection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GroupByStyle {
+    /// Repeat the expression of the key, i.e. `GROUP BY UPPER(name)`.
+    #[default]
+    Expressions,
+
+    /// Position of the key in the projection, i.e. `GROUP BY 1`.
+    Ordinals,
+
+    /// Alias of the key in the projection, i.e. `GROUP BY upper_name`.
+    ///
+    /// Some databases (i.e. Postgres) resolve GROUP BY names to input columns
+    /// first, so an alias that shadows an input column would group by that
+    /// column instead.
+    Aliases,
+}
+
+/// How the condition of a join is expressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum JoinStyle {
+    /// Always use `ON`, i.e. `JOIN b ON a.id = b.id`, so both columns are kept.
+    #[default]
+    On,
+
+    /// Use `USING` for the `join b (==id)` shorthand, i.e. `JOIN b USING (id)`,
+    /// and `ON` for all other conditions.
+    UsingForShorthand,
+
+    /// Use `USING` when the condition only compares columns with the same name,
+    /// i.e. `JOIN b USING (id)`, so each pair of columns is merged into one.
+    ///
+    /// Other conditions are still expressed with `ON`.
+    Using,
+}
+
+/// Version of the SQL standard that the generated SQL conforms to. Constructs
+/// that were introduced in later versions are rejected with an error.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    Serialize,
+    Deserialize,
+    strum::Display,
+)]
+pub enum SqlStandard {
+    /// No CTEs or window functions, which helps with legacy engines.
+    #[strum(to_string = "SQL-92")]
+    Sql92,
+
+    /// Adds CTEs, including the recursive ones that are needed by `loop`.
+    #[strum(to_string = "SQL:1999")]
+    Sql99,
+
+    /// Adds window functions.
+    #[strum(to_string = "SQL:2003")]
+    Sql2003,
+
+    #[default]
+    #[strum(to_string = "SQL:2016")]
+    Sql2016,
+}
+
+/// Which identifiers are quoted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum QuotingStyle {
+    /// Quote identifiers that are not all lowercase, so their case is
+    /// preserved, i.e. `"FirstName"`. Keywords are always quoted.
+    #[default]
+    CasePreserving,
+
+    /// Quote only identifiers that could not be used bare, i.e. `FirstName`
+    /// but `"last name"`. Keywords are always quoted.
+    Minimal,
+}
+
 /// This module gives access to internal machinery that gives no stability guarantees.
 pub mod internal {
     use super::*;
@@ -82,7 +220,8 @@ pub mod internal {
 
     /// Applies preprocessing and anchoring to the main relation in RQ. Meant for debugging purposes.
     pub fn anchor(query: RelationalQuery) -> Result<srq::ast::SqlQuery> {
-        let (query, _ctx) = srq::compile_query(query, Some(dialect::Dialect::Generic))?;
+        let (query, _ctx) =
+            srq::compile_query(query, Some(dialect::Dialect::Generic), false, false)?;
         Ok(query)
     }
 }
@@ -101,6 +240,41 @@ struct Context {
     query_stack: Vec<QueryOpts>,
 
     pub ctes: Vec<Cte>,
+
+    pub group_by_style: GroupByStyle,
+
+    pub join_style: JoinStyle,
+
+    /// When true, relations that are referenced only once are emitted as
+    /// sub-queries instead of CTEs.
+    pub inline_single_use_ctes: bool,
+
+    /// When true, arguments of window functions are computed in a preceding
+    /// SELECT and referenced by name.
+    pub materialize_window_args: bool,
+
+    /// When true, CTEs that were introduced by splitting a pipeline are
+    /// preceded by a comment with the reason for the split.
+    pub explain_splits: bool,
+
+    pub quoting_style: QuotingStyle,
+
+    /// When true, casts are emitted as safe casts, if the dialect supports them.
+    pub safe_cast: bool,
+
+    /// When true, operators that the dialect cannot compute exactly are
+    /// approximated, if the dialect supports that.
+    pub allow_approximations: bool,
+
+    pub sql_standard: SqlStandard,
+
+    /// When true, features that the dialect does not support are rejected
+    /// with an error instead of being emulated. Set by `strict:true` in the
+    /// query header.
+    pub strict: bool,
+
+    /// SQL that sqlparser cannot express. See [Context::placeholder].
+    pub placeholders: Vec<String>,
 }
 
 #[derive(Clone, Debug)]
@@ -145,9 +319,28 @@ impl Context {
             query: QueryOpts::default(),
             query_stack: Vec::new(),
             ctes: Vec::new(),
+            group_by_style: GroupByStyle::default(),
+            join_style: JoinStyle::default(),
+            inline_single_use_ctes: false,
+            materialize_window_args: false,
+            explain_splits: false,
+            quoting_style: QuotingStyle::default(),
+            safe_cast: false,
+            allow_approximations: false,
+            sql_standard: SqlStandard::default(),
+            strict: false,
+            placeholders: Vec::new(),
         }
     }
 
+    /// Returns an identifier that is replaced by `sql` once the query is
+    /// rendered, for clauses that sqlparser cannot express.
+    fn placeholder(&mut self, sql: String) -> sqlparser::ast::Ident {
+        let ident = sqlparser::ast::Ident::new(placeholder_name(self.placeholders.len()));
+        self.placeholders.push(sql);
+        ident
+    }
+
     fn push_query(&mut self) {
         self.query_stack.push(self.query.clone());
     }
@@ -17,6 +17,14 @@ use crate::Error;
 
 static STD: Lazy<semantic::Module> = Lazy::new(load_std_sql);
 
+/// Operators that are implemented exactly for dialects that cannot
+/// approximate them.
+const APPROXIMATE_OPERATORS: &[&str] = &["std.approx_count_distinct"];
+
+/// Operators whose generic implementation emulates them, so they are rejected
+/// with `strict:true` for dialects without an implementation of their own.
+const EMULATED_OPERATORS: &[&str] = &["std.every", "std.any"];
+
 fn load_std_sql() -> semantic::Module {
     let std_lib = crate::SourceTree::new([(
         PathBuf::from("std.prql"),
@@ -45,9 +53,35 @@ pub(super) fn translate_operator(
     args: Vec<rq::Expr>,
     ctx: &mut Context,
 ) -> Result<SourceExpr> {
-    let (func_def, binding_strength, window_frame, coalesce) =
+    let name = match name.as_str() {
+        "std.as" if ctx.safe_cast && ctx.dialect.supports_safe_cast() => "std.safe_as".to_string(),
+        "std.div_f" if args.iter().any(is_float_literal) => "std.div_f_of_float".to_string(),
+        _ => name,
+    };
+
+    if APPROXIMATE_OPERATORS.contains(&name.as_str()) && !has_dialect_impl(&name, ctx.dialect_enum)
+    {
+        log::warn!(
+            "dialect {} cannot approximate `{}`, so it is computed exactly",
+            ctx.dialect_enum,
+            name.strip_prefix("std.").unwrap_or(&name)
+        );
+    }
+
+    if ctx.strict
+        && EMULATED_OPERATORS.contains(&name.as_str())
+        && !has_dialect_impl(&name, ctx.dialect_enum)
+    {
+        return Err(Error::new_simple(format!(
+            "operator {} is not supported for dialect {}",
+            name, ctx.dialect_enum
+        ))
+        .push_hint("remove `strict:true` to emulate it")
+        .into());
+    }
+
+    let (mut func_def, mut binding_strength, mut window_frame, mut coalesce) =
         find_operator_impl(&name, ctx.dialect_enum).unwrap();
-    let parent_binding_strength = binding_strength.unwrap_or(100);
 
     let params = func_def
         .named_params
@@ -55,7 +89,49 @@ pub(super) fn translate_operator(
         .chain(func_def.params.iter())
         .map(|x| x.name.split('.').last().unwrap_or(x.name.as_str()));
 
-    let args: HashMap<&str, _> = zip(params, args.into_iter()).collect();
+    let mut args: HashMap<&str, _> = zip(params, args.into_iter()).collect();
+
+    // skipping nulls is implemented by a separate operator of the dialect
+    if let Some(ignore_nulls) = args.remove("ignore_nulls") {
+        let ignore_nulls = match ignore_nulls.kind {
+            rq::ExprKind::Literal(pl::Literal::Boolean(value)) => value,
+            _ => return Err(Error::new_simple("`ignore_nulls` must be true or false").into()),
+        };
+
+        if ignore_nulls {
+            let variant = format!("{name}_ignore_nulls");
+            let Some(variant) = find_operator_impl(&variant, ctx.dialect_enum) else {
+                return Err(Error::new_simple(format!(
+                    "`ignore_nulls` of {} is not supported for dialect {}",
+                    name, ctx.dialect_enum
+                ))
+                .into());
+            };
+            (func_def, binding_strength, window_frame, coalesce) = variant;
+        }
+    }
+
+    // dialects that cannot compute the exact result may approximate it
+    if matches!(func_def.body.kind, pl::ExprKind::Literal(pl::Literal::Null)) {
+        let variant = format!("{name}_approx");
+        if let Some(variant) = find_operator_impl(&variant, ctx.dialect_enum) {
+            if ctx.strict || !ctx.allow_approximations {
+                let hint = if ctx.strict {
+                    "remove `strict:true` to compute an approximation instead"
+                } else {
+                    "set `allow_approximations` to compute an approximation instead"
+                };
+                return Err(Error::new_simple(format!(
+                    "operator {} is not supported for dialect {}",
+                    name, ctx.dialect_enum
+                ))
+                .push_hint(hint)
+                .into());
+            }
+            (func_def, binding_strength, window_frame, coalesce) = variant;
+        }
+    }
+    let parent_binding_strength = binding_strength.unwrap_or(100);
 
     // body can only be an s-string
     let body = match &func_def.body.kind {
@@ -115,22 +191,36 @@ pub(super) fn translate_operator(
     })
 }
 
+fn is_float_literal(expr: &rq::Expr) -> bool {
+    matches!(expr.kind, rq::ExprKind::Literal(pl::Literal::Float(_)))
+}
+
+/// Whether the dialect has its own implementation of the operator, rather than
+/// using the generic one.
+fn has_dialect_impl(operator_name: &str, dialect: Dialect) -> bool {
+    let operator_name = pl::Ident::from_name(operator_name.strip_prefix("std.").unwrap());
+
+    dialect_modules(dialect).any(|module| module.get(&operator_name).is_some())
+}
+
+/// Modules of std.sql that contain implementations for the dialect, starting
+/// with the dialect itself and followed by the dialects it is based on.
+fn dialect_modules(dialect: Dialect) -> impl Iterator<Item = &'static semantic::Module> {
+    std::iter::successors(Some(dialect), Dialect::base).filter_map(|dialect| {
+        let decl = STD.get(&pl::Ident::from_name(dialect.to_string()))?;
+        Some(decl.kind.as_module().unwrap())
+    })
+}
+
 fn find_operator_impl(
     operator_name: &str,
     dialect: Dialect,
-) -> Option<(&pl::Func, Option<i32>, bool, Option<String>)> {
+) -> Option<(&'static pl::Func, Option<i32>, bool, Option<String>)> {
     let operator_name = operator_name.strip_prefix("std.").unwrap();
 
     let operator_name = pl::Ident::from_name(operator_name);
 
-    let dialect_module = STD.get(&pl::Ident::from_name(dialect.to_string()));
-
-    let mut func_def = None;
-
-    if let Some(dialect_module) = dialect_module {
-        let module = dialect_module.kind.as_module().unwrap();
-        func_def = module.get(&operator_name);
-    }
+    let mut func_def = dialect_modules(dialect).find_map(|module| module.get(&operator_name));
 
     if func_def.is_none() {
         func_def = STD.get(&operator_name);
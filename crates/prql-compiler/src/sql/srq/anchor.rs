@@ -14,20 +14,25 @@ use super::context::{AnchorContext, ColumnDecl, RIId, RelationStatus, SqlTableDe
 
 /// Extract last part of pipeline that is able to "fit" into a single SELECT statement.
 /// Remaining proceeding pipeline is declared as a table and stored in AnchorContext.
+///
+/// When `materialize_window_args` is set, arguments of window functions that
+/// are computed expressions are left in the preceding pipeline.
 pub(super) fn extract_atomic(
     pipeline: Vec<SqlTransform>,
+    materialize_window_args: bool,
     ctx: &mut AnchorContext,
 ) -> Vec<SqlTransform> {
     let output = ctx.determine_select_columns(&pipeline);
 
-    let (preceding, atomic) = split_off_back(pipeline, output.clone(), ctx);
+    let (preceding, atomic) =
+        split_off_back(pipeline, output.clone(), materialize_window_args, ctx);
 
-    let atomic = if let Some(preceding) = preceding {
+    let atomic = if let Some((preceding, reason)) = preceding {
         log::debug!(
             "pipeline split after {}",
             preceding.last().unwrap().as_str()
         );
-        anchor_split(ctx, preceding, atomic)
+        anchor_split(ctx, preceding, atomic, Some(reason))
     } else {
         atomic
     };
@@ -52,7 +57,8 @@ pub(super) fn extract_atomic(
         // construct the new SELECT
         let limited_view = vec![SqlTransform::Super(Transform::Select(output))];
 
-        return anchor_split(ctx, atomic, limited_view);
+        let reason = "projection of columns used only by other clauses".to_string();
+        return anchor_split(ctx, atomic, limited_view, Some(reason));
     }
 
     atomic
@@ -61,12 +67,14 @@ pub(super) fn extract_atomic(
 /// Splits pipeline into two parts, such that the second part contains
 /// maximum number of transforms while "fitting" into a SELECT query.
 ///
-/// Returns optional remaining preceding pipeline and the atomic pipeline.
+/// Returns optional remaining preceding pipeline (along with the reason for
+/// the split) and the atomic pipeline.
 pub(super) fn split_off_back(
     mut pipeline: Vec<SqlTransform>,
     output: Vec<CId>,
+    materialize_window_args: bool,
     ctx: &mut AnchorContext,
-) -> (Option<Vec<SqlTransform>>, Vec<SqlTransform>) {
+) -> (Option<(Vec<SqlTransform>, String)>, Vec<SqlTransform>) {
     if pipeline.is_empty() {
         return (None, Vec::new());
     }
@@ -77,21 +85,44 @@ pub(super) fn split_off_back(
 
     let mut inputs_required = into_requirements(output.clone(), Complexity::highest(), true);
     let mut inputs_avail = HashSet::new();
+    let mut window_args = HashSet::new();
 
     // iterate backwards
     let mut curr_pipeline_rev = Vec::new();
+    let mut split_reason = String::new();
     'pipeline: while let Some(transform) = pipeline.pop() {
         // stop if split is needed
         let split = is_split_required(&transform, &mut following_transforms);
-        if split {
+        if let Some(following) = split {
             log::debug!("split required after {}", transform.as_str());
             log::debug!(".. following={:?}", following_transforms);
+            split_reason = format!(
+                "{} after {}",
+                following.to_lowercase(),
+                transform.as_str().to_lowercase()
+            );
             pipeline.push(transform);
             break;
         }
 
         // anchor and record all requirements
-        let required = get_requirements(&transform, &following_transforms);
+        let mut required = get_requirements(&transform, &following_transforms);
+        if materialize_window_args {
+            if let SqlTransform::Super(Transform::Compute(compute)) = &transform {
+                // an argument that only renames a column is followed to the
+                // expression of that column
+                let renames_arg = window_args.contains(&compute.id)
+                    && matches!(compute.expr.kind, rq::ExprKind::ColumnRef(_));
+                if compute.window.is_some() || renames_arg {
+                    window_args.extend(CidCollector::collect(compute.expr.clone()));
+                }
+            }
+            for r in required.iter_mut().filter(|r| window_args.contains(&r.col)) {
+                if !is_column_ref(&ctx.column_decls[&r.col]) {
+                    r.max_complexity = Complexity::Column;
+                }
+            }
+        }
         log::debug!("transform {} requires {:?}", transform.as_str(), required);
         inputs_required.extend(required);
 
@@ -101,6 +132,7 @@ pub(super) fn split_off_back(
                     log::debug!("materializing {:?}", compute.id);
                     inputs_avail.insert(compute.id);
                 } else {
+                    split_reason = "compute that cannot be inlined".to_string();
                     pipeline.push(transform);
                     break;
                 }
@@ -110,6 +142,7 @@ pub(super) fn split_off_back(
                     let decl = &ctx.column_decls[cid];
                     if let ColumnDecl::Compute(compute) = decl {
                         if !can_materialize(compute, &inputs_required) {
+                            split_reason = "aggregate that cannot be inlined".to_string();
                             pipeline.push(transform);
                             break 'pipeline;
                         }
@@ -172,7 +205,7 @@ pub(super) fn split_off_back(
         // drop inputs that were satisfied in current pipeline
         pipeline.push(SqlTransform::Super(Transform::Select(missing)));
 
-        Some(pipeline)
+        Some((pipeline, split_reason))
     };
 
     curr_pipeline_rev.reverse();
@@ -208,6 +241,7 @@ pub(super) fn anchor_split(
     ctx: &mut AnchorContext,
     preceding: Vec<SqlTransform>,
     atomic: Vec<SqlTransform>,
+    split_reason: Option<String>,
 ) -> Vec<SqlTransform> {
     let new_tid = ctx.tid.gen();
 
@@ -223,6 +257,13 @@ pub(super) fn anchor_split(
     for old_cid in cols_at_split {
         let new_cid = ctx.cid.gen();
 
+        // columns that are not named by the query are only carried over the
+        // split because the following transforms need them (i.e. keys of a
+        // `sort` or columns of a `drop`), so the query did not request them
+        if ctx.generated_cids.contains(old_cid) || !ctx.is_named(*old_cid) {
+            ctx.generated_cids.insert(new_cid);
+        }
+
         let old_name = ctx.ensure_column_name(*old_cid).cloned();
 
         let mut new_name = old_name;
@@ -260,6 +301,7 @@ pub(super) fn anchor_split(
             relation: RelationStatus::NotYetDefined(RelationAdapter::Preprocessed(
                 preceding, columns,
             )),
+            split_reason,
         },
     );
 
@@ -284,7 +326,9 @@ pub(super) fn anchor_split(
 /// fit into one SELECT statement.
 ///
 /// `following` contain names of following transforms in the pipeline.
-fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>) -> bool {
+///
+/// Returns the name of the following transform that requires the split.
+fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>) -> Option<String> {
     // Pipeline must be split when there is a transform that is out of order:
     // - from (max 1x),
     // - join (no limit),
@@ -294,6 +338,7 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
     // - compute (no limit)
     // - sort (no limit)
     // - take (no limit)
+    // - sample (max 1x)
     // - distinct
     // - append/except/intersect (no limit)
     // - loop (max 1x)
@@ -306,17 +351,20 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
     // because it's done within the aggregation
     if let Super(Compute(decl)) = transform {
         if decl.is_aggregation {
-            return false;
+            return None;
         }
     }
 
-    fn contains_any<const C: usize>(set: &HashSet<String>, elements: [&'static str; C]) -> bool {
+    fn contains_any<const C: usize>(
+        set: &HashSet<String>,
+        elements: [&'static str; C],
+    ) -> Option<String> {
         for t in elements {
             if set.contains(t) {
-                return true;
+                return Some(t.to_string());
             }
         }
-        false
+        None
     }
 
     let split = match transform {
@@ -332,7 +380,28 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
         // Super(Sort(_)) => contains_any(following, ["From", "Join", "Compute", "Aggregate"]),
         Super(Take(_)) => contains_any(
             following,
-            ["From", "Join", "Compute", "Filter", "Aggregate", "Sort"],
+            [
+                "From",
+                "Join",
+                "Compute",
+                "Filter",
+                "Aggregate",
+                "Sort",
+                "SampleRows",
+            ],
+        ),
+        Super(SampleRows { .. }) => contains_any(
+            following,
+            [
+                "From",
+                "Join",
+                "Compute",
+                "Filter",
+                "Aggregate",
+                "Sort",
+                "Take",
+                "SampleRows",
+            ],
         ),
         SqlTransform::Distinct | SqlTransform::DistinctOn(_) => contains_any(
             following,
@@ -344,6 +413,7 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
                 "Aggregate",
                 "Sort",
                 "Take",
+                "SampleRows",
             ],
         ),
         SqlTransform::Union { .. }
@@ -358,14 +428,15 @@ fn is_split_required(transform: &SqlTransform, following: &mut HashSet<String>)
                 "Aggregate",
                 "Sort",
                 "Take",
+                "SampleRows",
                 "Distinct",
             ],
         ),
-        Super(Loop(_)) => !following.is_empty(),
-        _ => false,
+        Super(Loop(_)) => following.iter().min().cloned(),
+        _ => None,
     };
 
-    if !split {
+    if split.is_none() {
         following.insert(transform.as_str().to_string());
     }
     split
@@ -500,9 +571,18 @@ pub(super) fn get_requirements(
     into_requirements(cids, max_complexity, selected)
 }
 
+fn is_column_ref(decl: &ColumnDecl) -> bool {
+    match decl {
+        ColumnDecl::Compute(compute) => matches!(compute.expr.kind, rq::ExprKind::ColumnRef(_)),
+        ColumnDecl::RelationColumn(..) => true,
+    }
+}
+
 /// Complexity of a column expressions.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Complexity {
+    /// Columns that are referenced by name, so no expression is inlined
+    Column,
     /// Simple non-aggregated and non-windowed expressions
     Plain,
     /// Expressions that cannot be used in GROUP BY (CASE)
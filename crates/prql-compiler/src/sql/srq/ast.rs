@@ -93,6 +93,10 @@ pub enum SqlTransform<Rel = RIId, Super = rq::Transform> {
     },
     Sort(Vec<ColumnSort<rq::CId>>),
     Take(rq::Take),
+    /// Percentage of rows of the table in FROM to sample.
+    Sample(i64),
+    /// Number of random rows to take.
+    SampleRows(i64),
     Join {
         side: JoinSide,
         with: Rel,
@@ -175,6 +179,8 @@ pub fn fold_sql_transform<
             filter: fold.fold_expr(filter)?,
         },
 
+        SqlTransform::Sample(percent) => SqlTransform::Sample(percent),
+        SqlTransform::SampleRows(rows) => SqlTransform::SampleRows(rows),
         SqlTransform::Distinct => SqlTransform::Distinct,
         SqlTransform::DistinctOn(ids) => SqlTransform::DistinctOn(fold.fold_cids(ids)?),
         SqlTransform::Union { bottom, distinct } => SqlTransform::Union {
@@ -1,7 +1,7 @@
 //! Transform the parsed AST into a "materialized" AST, by executing functions and
 //! replacing variables. The materialized AST is "flat", in the sense that it
 //! contains no query-specific logic.
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::iter::zip;
 
 use anyhow::Result;
@@ -29,6 +29,12 @@ pub struct AnchorContext {
 
     pub relation_instances: HashMap<RIId, RelationInstance>,
 
+    /// Columns introduced by the compiler rather than by the query, i.e. row
+    /// numbers of `group (take 1)` or columns that a split carries over only
+    /// for the following transforms. When they are not selected, they are
+    /// excluded from a star without the query requesting that.
+    pub generated_cids: HashSet<CId>,
+
     pub col_name: NameGenerator,
     pub table_name: NameGenerator,
 
@@ -50,6 +56,10 @@ pub struct SqlTableDecl {
     /// None means that it has already been defined, or was not needed to be defined in the
     /// first place.
     pub relation: RelationStatus,
+
+    /// Why this relation was split off from the pipeline that follows it.
+    /// None for relations that were not introduced by the anchoring.
+    pub split_reason: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -198,6 +208,16 @@ impl AnchorContext {
         Some(entry.or_insert_with(|| self.col_name.gen()))
     }
 
+    /// Whether the column has a name that comes from the query rather than
+    /// from the compiler.
+    pub(super) fn is_named(&self, cid: CId) -> bool {
+        match &self.column_decls[&cid] {
+            ColumnDecl::RelationColumn(_, _, RelationColumn::Single(name)) => name.is_some(),
+            ColumnDecl::RelationColumn(_, _, RelationColumn::Wildcard) => true,
+            ColumnDecl::Compute(_) => self.column_names.contains_key(&cid),
+        }
+    }
+
     pub(super) fn load_names(
         &mut self,
         pipeline: &[SqlTransform],
@@ -307,6 +327,7 @@ impl QueryLoader {
                 // this relation should be defined when needed
                 RelationStatus::NotYetDefined(decl.relation.into())
             },
+            split_reason: None,
         };
 
         self.context.table_decls.insert(decl.id, sql_decl);
@@ -24,6 +24,8 @@ use super::{postprocess, preprocess};
 pub(in super::super) fn compile_query(
     query: RelationalQuery,
     dialect: Option<Dialect>,
+    inline_single_use_ctes: bool,
+    materialize_window_args: bool,
 ) -> Result<(SqlQuery, Context)> {
     let dialect = if let Some(dialect) = dialect {
         dialect
@@ -35,10 +37,14 @@ pub(in super::super) fn compile_query(
             .unwrap_or_default();
         maybe_dialect.unwrap_or_default()
     };
+    let strict = query.def.other.get("strict").map_or(false, |s| s == "true");
 
     let (anchor, main_relation) = AnchorContext::of(query);
 
     let mut ctx = Context::new(dialect, anchor);
+    ctx.inline_single_use_ctes = inline_single_use_ctes;
+    ctx.materialize_window_args = materialize_window_args;
+    ctx.strict = strict;
 
     // compile main relation that will recursively compile CTEs
     let main_relation = compile_relation(main_relation.into(), &mut ctx)?;
@@ -107,7 +113,7 @@ fn compile_pipeline(mut pipeline: Vec<SqlTransform>, ctx: &mut Context) -> Resul
     }
 
     // extract an atomic pipeline from back of the pipeline and stash preceding part into context
-    let pipeline = anchor::extract_atomic(pipeline, &mut ctx.anchor);
+    let pipeline = anchor::extract_atomic(pipeline, ctx.materialize_window_args, &mut ctx.anchor);
 
     // ensure names for all columns that need it
     ensure_names(&pipeline, &mut ctx.anchor);
@@ -159,6 +165,8 @@ impl<'a> SrqMapper<RIId, RelationExpr, Transform, ()> for TransformCompiler<'a>
                             }
                             Transform::Sort(v) => SqlTransform::Sort(v),
                             Transform::Take(v) => SqlTransform::Take(v),
+                            Transform::Sample { percent } => SqlTransform::Sample(percent),
+                            Transform::SampleRows { rows } => SqlTransform::SampleRows(rows),
                             Transform::Compute(_) | Transform::Append(_) | Transform::Loop(_) => {
                                 // these are not used from here on
                                 return Ok(None);
@@ -177,12 +185,19 @@ impl<'a> SrqMapper<RIId, RelationExpr, Transform, ()> for TransformCompiler<'a>
 pub(super) fn compile_relation_instance(riid: RIId, ctx: &mut Context) -> Result<RelationExpr> {
     let table_ref = &ctx.anchor.relation_instances.get(&riid).unwrap().table_ref;
     let source = table_ref.source;
-    let decl = ctx.anchor.table_decls.get_mut(&table_ref.source).unwrap();
+
+    let references = (ctx.anchor.relation_instances.values())
+        .filter(|ri| ri.table_ref.source == source)
+        .count();
+    let single_use = ctx.inline_single_use_ctes && references == 1;
+
+    let decl = ctx.anchor.table_decls.get_mut(&source).unwrap();
 
     // ensure that the table is declared
     if let RelationStatus::NotYetDefined(sql_relation) = decl.relation.take_to_define() {
         // if we cannot use CTEs (probably because we are within RECURSIVE)
-        if !ctx.query.allow_ctes {
+        // or the relation is not referenced anywhere else
+        if !ctx.query.allow_ctes || single_use {
             // restore relation for other references
             decl.relation = RelationStatus::NotYetDefined(sql_relation.clone());
 
@@ -222,7 +237,7 @@ fn compile_loop(pipeline: Vec<SqlTransform>, ctx: &mut Context) -> Result<Vec<Sq
     // (defining new columns, redirecting cids)
     let recursive_columns = SqlTransform::Super(Transform::Select(recursive_columns));
     initial.push(recursive_columns.clone());
-    let step = anchor_split(&mut ctx.anchor, initial, step);
+    let step = anchor_split(&mut ctx.anchor, initial, step, None);
     let from = step.first().unwrap().as_from().unwrap();
     let from = ctx.anchor.relation_instances.get(from).unwrap();
     let from = &from.table_ref.source;
@@ -247,7 +262,7 @@ fn compile_loop(pipeline: Vec<SqlTransform>, ctx: &mut Context) -> Result<Vec<Sq
     ctx.pop_query();
 
     // create a split between the loop SELECT statement and the following pipeline
-    let mut following = anchor_split(&mut ctx.anchor, vec![recursive_columns], following);
+    let mut following = anchor_split(&mut ctx.anchor, vec![recursive_columns], following, None);
 
     let from = following.first_mut().unwrap();
     let from = from.as_from().unwrap();
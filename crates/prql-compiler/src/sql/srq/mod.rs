@@ -15,6 +15,7 @@ mod gen_query;
 mod postprocess;
 pub mod preprocess;
 
+pub(super) use anchor::CidCollector;
 pub(super) use gen_query::compile_query;
 
 #[cfg(test)]
@@ -29,7 +30,7 @@ mod test {
     fn parse_and_resolve(source: &str) -> Result<SqlQuery> {
         let query = crate::semantic::test::parse_resolve_and_lower(source)?;
 
-        let (sql, _) = compile_query(query, Some(Dialect::Generic))?;
+        let (sql, _) = compile_query(query, Some(Dialect::Generic), false, false)?;
         Ok(sql)
     }
 
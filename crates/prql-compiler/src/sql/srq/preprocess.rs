@@ -147,8 +147,34 @@ pub(in crate::sql) fn distinct(
     let mut res = Vec::new();
     for transform in pipeline.clone() {
         match transform {
-            Super(Take(rq::Take { ref partition, .. })) if partition.is_empty() => {
-                res.push(transform);
+            Super(Take(rq::Take {
+                ref partition,
+                ref range,
+                ref sort,
+            })) if partition.is_empty() => {
+                let skips_rows = (range.start.clone())
+                    .map_or(Ok(false), |start| as_int(start).map(|start| start > 1))
+                    .map_err(|_| anyhow::anyhow!("Invalid take arguments"))?;
+
+                if skips_rows && !ctx.dialect.supports_row_limiting() {
+                    // rows can only be skipped by numbering them
+                    if sort.is_empty() {
+                        return Err(Error::new_simple(format!(
+                            "The dialect {} can only skip rows of a sorted relation",
+                            ctx.dialect_enum
+                        ))
+                        .push_hint("add a `sort` before the `take`")
+                        .into());
+                    }
+                    res.extend(create_filter_by_row_number(
+                        range.clone(),
+                        sort.clone(),
+                        Vec::new(),
+                        ctx,
+                    ));
+                } else {
+                    res.push(transform);
+                }
             }
 
             Super(Take(rq::Take {
@@ -176,8 +202,8 @@ pub(in crate::sql) fn distinct(
                 } else if ctx.dialect.supports_distinct_on() {
                     // DISTINCT ON
 
-                    let sort = if sort.is_empty() {
-                        vec![]
+                    let sort = if sort.is_empty() || sort_begins_with(&sort, &partition) {
+                        sort
                     } else {
                         [into_column_sort(&partition), sort].concat()
                     };
@@ -199,12 +225,21 @@ pub(in crate::sql) fn distinct(
     Ok(res)
 }
 
+/// Checks whether the sort begins with the given columns, in any order
+fn sort_begins_with(sort: &[ColumnSort<CId>], columns: &[CId]) -> bool {
+    sort.len() >= columns.len()
+        && sort[..columns.len()]
+            .iter()
+            .all(|s| columns.contains(&s.column))
+}
+
 fn into_column_sort(partition: &[CId]) -> Vec<ColumnSort<CId>> {
     partition
         .iter()
         .map(|cid| ColumnSort {
             direction: SortDirection::Asc,
             column: *cid,
+            nulls: None,
         })
         .collect_vec()
 }
@@ -249,6 +284,7 @@ fn create_filter_by_row_number(
     };
 
     ctx.anchor.register_compute(compute.clone());
+    ctx.anchor.generated_cids.insert(compute.id);
 
     let col_ref = Expr {
         kind: ExprKind::ColumnRef(compute.id),
@@ -383,6 +419,12 @@ pub(in crate::sql) fn except(
             }
         }
 
+        if distinct && !ctx.dialect.supports_except() {
+            // EXCEPT is not supported, so the anti-join stays, which is
+            // preceded by the DISTINCT of top
+            continue;
+        }
+
         res.pop(); // filter
         let join = res.pop(); // join
         let (_, with, _) = join.unwrap().into_join().unwrap();
@@ -506,7 +548,9 @@ fn collect_equals(expr: &Expr) -> Result<(Vec<&Expr>, Vec<&Expr>)> {
     let mut rights = Vec::new();
 
     match &expr.kind {
-        ExprKind::Operator { name, args } if name == "std.eq" && args.len() == 2 => {
+        ExprKind::Operator { name, args }
+            if (name == "std.eq" || name == "std.eq_self") && args.len() == 2 =>
+        {
             lefts.push(&args[0]);
             rights.push(&args[1]);
         }
@@ -563,6 +607,16 @@ pub(in crate::sql) fn reorder(mut pipeline: Vec<SqlTransform>) -> Vec<SqlTransfo
         (Super(Compute(decl)), Super(Take(_))) if infer_complexity(decl) == Complexity::Plain => {
             Ordering::Less
         }
+        (Super(SampleRows { .. }), Super(Compute(decl)))
+            if infer_complexity(decl) == Complexity::Plain =>
+        {
+            Ordering::Greater
+        }
+        (Super(Compute(decl)), Super(SampleRows { .. }))
+            if infer_complexity(decl) == Complexity::Plain =>
+        {
+            Ordering::Less
+        }
 
         // don't reorder by default
         _ => Ordering::Equal,
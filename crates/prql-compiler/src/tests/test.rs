@@ -44,9 +44,321 @@ fn json_of_test() {
     let json = crate::prql_to_pl("from employees | take 10")
         .and_then(crate::json::from_pl)
         .unwrap();
-    // Since the AST is so in flux right now just test that the brackets are present
-    assert_eq!(json.chars().next().unwrap(), '[');
-    assert_eq!(json.chars().nth(json.len() - 1).unwrap(), ']');
+    assert_snapshot!(json, @r###"[{"Main":{"Pipeline":{"exprs":[{"FuncCall":{"name":{"Ident":["from"]},"args":[{"Ident":["employees"]}]}},{"FuncCall":{"name":{"Ident":["take"]},"args":[{"Literal":{"Integer":10}}]}}]}},"annotations":[]}]"###);
+
+    // The JSON of PL is consumed by other tools, so its schema should only
+    // change deliberately. Its names are those of the Rust types, so this
+    // snapshot is what catches a rename. It covers joins, grouping, windows
+    // and s-strings.
+    let pl = crate::prql_to_pl(
+        r#"
+    from e=employees
+    join d=departments (==dept_id)
+    group {d.name} (window rows:-1..0 (derive {total = s"SUM({e.salary})"}))
+    "#,
+    )
+    .unwrap();
+    assert_snapshot!(serde_json::to_string_pretty(&pl).unwrap(), @r###"
+    [
+      {
+        "Main": {
+          "Pipeline": {
+            "exprs": [
+              {
+                "FuncCall": {
+                  "name": {
+                    "Ident": [
+                      "from"
+                    ]
+                  },
+                  "args": [
+                    {
+                      "Ident": [
+                        "employees"
+                      ],
+                      "alias": "e"
+                    }
+                  ]
+                }
+              },
+              {
+                "FuncCall": {
+                  "name": {
+                    "Ident": [
+                      "join"
+                    ]
+                  },
+                  "args": [
+                    {
+                      "Ident": [
+                        "departments"
+                      ],
+                      "alias": "d"
+                    },
+                    {
+                      "Unary": {
+                        "op": "EqSelf",
+                        "expr": {
+                          "Ident": [
+                            "dept_id"
+                          ]
+                        }
+                      }
+                    }
+                  ]
+                }
+              },
+              {
+                "FuncCall": {
+                  "name": {
+                    "Ident": [
+                      "group"
+                    ]
+                  },
+                  "args": [
+                    {
+                      "Tuple": [
+                        {
+                          "Ident": [
+                            "d",
+                            "name"
+                          ]
+                        }
+                      ]
+                    },
+                    {
+                      "FuncCall": {
+                        "name": {
+                          "Ident": [
+                            "window"
+                          ]
+                        },
+                        "args": [
+                          {
+                            "FuncCall": {
+                              "name": {
+                                "Ident": [
+                                  "derive"
+                                ]
+                              },
+                              "args": [
+                                {
+                                  "Tuple": [
+                                    {
+                                      "SString": [
+                                        {
+                                          "String": "SUM("
+                                        },
+                                        {
+                                          "Expr": {
+                                            "expr": {
+                                              "Ident": [
+                                                "e",
+                                                "salary"
+                                              ]
+                                            },
+                                            "format": null
+                                          }
+                                        },
+                                        {
+                                          "String": ")"
+                                        }
+                                      ],
+                                      "alias": "total"
+                                    }
+                                  ]
+                                }
+                              ]
+                            }
+                          }
+                        ],
+                        "named_args": {
+                          "rows": {
+                            "Range": {
+                              "start": {
+                                "Unary": {
+                                  "op": "Neg",
+                                  "expr": {
+                                    "Literal": {
+                                      "Integer": 1
+                                    }
+                                  }
+                                }
+                              },
+                              "end": {
+                                "Literal": {
+                                  "Integer": 0
+                                }
+                              }
+                            }
+                          }
+                        }
+                      }
+                    }
+                  ]
+                }
+              }
+            ]
+          }
+        },
+        "annotations": []
+      }
+    ]
+    "###);
+}
+
+/// Validates `value` against the subset of JSON Schema that is used by
+/// [crate::json::ast_json_schema], returning a message for each violation.
+fn validate_json_schema(
+    root: &serde_json::Value,
+    schema: &serde_json::Value,
+    value: &serde_json::Value,
+    path: &str,
+) -> Vec<String> {
+    use serde_json::Value;
+
+    let mut schema = schema;
+    while let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.strip_prefix("#/definitions/").unwrap();
+        schema = &root["definitions"][name];
+        assert!(!schema.is_null(), "unknown definition {name}");
+    }
+
+    let mut errors = Vec::new();
+    if let Some(ty) = schema.get("type") {
+        let types: Vec<&str> = match ty {
+            Value::String(ty) => vec![ty.as_str()],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => panic!("bad type {ty}"),
+        };
+        let matches = |ty: &str| match ty {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => panic!("unknown type {ty}"),
+        };
+        if !types.into_iter().any(matches) {
+            return vec![format!("{path}: expected {ty}, found {value}")];
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            errors.push(format!("{path}: expected {expected}, found {value}"));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            errors.push(format!("{path}: {value} is not one of {options:?}"));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("oneOf") {
+        let valid = (options.iter())
+            .filter(|option| validate_json_schema(root, option, value, path).is_empty())
+            .count();
+        if valid != 1 {
+            errors.push(format!("{path}: {valid} variants of oneOf match {value}"));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("anyOf") {
+        let is_valid = (options.iter())
+            .any(|option| validate_json_schema(root, option, value, path).is_empty());
+        if !is_valid {
+            errors.push(format!("{path}: no variant of anyOf matches {value}"));
+        }
+    }
+    if let Some(Value::Array(schemas)) = schema.get("allOf") {
+        for schema in schemas {
+            errors.extend(validate_json_schema(root, schema, value, path));
+        }
+    }
+    if let Value::Array(items) = value {
+        if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+            if (items.len() as u64) < min {
+                errors.push(format!("{path}: expected at least {min} items"));
+            }
+        }
+        if let Some(item_schema) = schema.get("items") {
+            for (index, item) in items.iter().enumerate() {
+                let item_path = format!("{path}/{index}");
+                errors.extend(validate_json_schema(root, item_schema, item, &item_path));
+            }
+        }
+    }
+    if let Value::Object(fields) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !fields.contains_key(name) {
+                    errors.push(format!("{path}: missing field {name}"));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, field) in fields {
+            let field_path = format!("{path}/{name}");
+            match properties.and_then(|p| p.get(name)) {
+                Some(field_schema) => {
+                    errors.extend(validate_json_schema(root, field_schema, field, &field_path))
+                }
+                None => match schema.get("additionalProperties") {
+                    Some(Value::Bool(false)) => {
+                        errors.push(format!("{path}: unexpected field {name}"))
+                    }
+                    Some(Value::Bool(true)) | None => {}
+                    Some(field_schema) => {
+                        errors.extend(validate_json_schema(root, field_schema, field, &field_path))
+                    }
+                },
+            }
+        }
+    }
+    errors
+}
+
+#[test]
+fn test_ast_json_schema() {
+    let schema: serde_json::Value = serde_json::from_str(&crate::json::ast_json_schema()).unwrap();
+
+    let pl = crate::prql_to_pl(
+        r#"
+    prql target:sql.postgres
+
+    @{binding_strength=1}
+    let add_one = x -> x + 1
+    let top = (from tracks | take 10)
+
+    from e=employees
+    join side:left d=departments (==dept_id)
+    derive {
+      gross = (salary ?? 0) + benefits,
+      label = f"{first_name} {last_name}",
+      hired = @2021-01-01,
+      tenure = 3years,
+      is_senior = case {level > 3 => true, true => null},
+    }
+    filter (age | in 18..40) && !(name ~= "^A")
+    group {d.name} (window rows:-1..0 (derive {total = s"SUM({e.salary})"}))
+    sort {-gross}
+    take 1..10
+    "#,
+    )
+    .unwrap();
+    let json = crate::json::from_pl(pl.clone()).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    let errors = validate_json_schema(&schema, &schema, &value, "");
+    assert!(errors.is_empty(), "{}", errors.join("\n"));
+
+    // the JSON that conforms to the schema is read back into the same PL
+    assert_eq!(crate::json::to_pl(&json).unwrap(), pl);
+
+    // a violation is reported
+    let mut invalid = value;
+    invalid[0]["unknown"] = serde_json::Value::Bool(true);
+    assert!(!validate_json_schema(&schema, &schema, &invalid, "").is_empty());
 }
 
 #[test]
@@ -126,6 +438,31 @@ fn test_precedence() {
       numbers
     "###);
 
+    // `&&` binds stronger than `||` and `!` binds stronger than both
+    assert_display_snapshot!((compile(r###"
+    from numbers
+    select {
+      x = a || b && c,
+      y = (a || b) && c,
+      z = !(a && b) || c,
+      w = !a || !b && c,
+    }
+    "###).unwrap()), @r###"
+    SELECT
+      a
+      OR b
+      AND c AS x,
+      (a OR b)
+      AND c AS y,
+      NOT (a AND b)
+      OR c AS z,
+      NOT a
+      OR NOT b
+      AND c AS w
+    FROM
+      numbers
+    "###);
+
     assert_display_snapshot!(compile(
     r###"
     from numbers
@@ -159,6 +496,37 @@ fn test_precedence() {
     );
 }
 
+#[test]
+fn test_derive_intra_references() {
+    // later expressions of a derive can reference earlier ones, which are inlined
+    assert_display_snapshot!((compile(r###"
+    from x
+    derive {n = a + b, r = a / n}
+    select {r}
+    "###).unwrap()), @r###"
+    SELECT
+      a / (a + b) AS r
+    FROM
+      x
+    "###);
+
+    // names are resolved in definition order, so a name cannot be used before
+    // it is defined
+    assert_display_snapshot!(compile(r###"
+    from x
+    select {a, b}
+    derive {r = a / n, n = a + b}
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:4:21]
+       │
+     4 │     derive {r = a / n, n = a + b}
+       │                     ┬
+       │                     ╰── Unknown name
+    ───╯
+    "###);
+}
+
 #[test]
 fn test_append() {
     assert_display_snapshot!(compile(r###"
@@ -262,6 +630,48 @@ fn test_append() {
     "###);
 }
 
+#[test]
+fn test_append_positional() {
+    // columns are matched by position, so the names of the bottom relation do
+    // not need to match
+    assert_display_snapshot!(compile(r###"
+    from invoices_2023
+    select {customer_id, total}
+    append (
+        from invoices_2022
+        select {customer_id, amount}
+    )
+    "###).unwrap(), @r###"
+    SELECT
+      customer_id,
+      total
+    FROM
+      invoices_2023
+    UNION
+    ALL
+    SELECT
+      customer_id,
+      amount
+    FROM
+      invoices_2022
+    "###);
+
+    let err = compile(
+        r###"
+    from invoices_2023
+    select {customer_id, total}
+    append (
+        from invoices_2022
+        select {customer_id}
+    )
+    "###,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("cannot append two relations with non-matching number of columns."));
+    assert!(err.contains("top has 2 columns, but bottom has 1"));
+}
+
 #[test]
 fn test_remove() {
     assert_display_snapshot!(compile(r#"
@@ -619,11 +1029,11 @@ fn test_quoting() {
     )
     SELECT
       "UPPER".*,
-      "some_schema.tablename".*,
+      some_schema.tablename.*,
       5 AS "from"
     FROM
       "UPPER"
-      JOIN "some_schema.tablename" ON "UPPER".id = "some_schema.tablename".id
+      JOIN some_schema.tablename ON "UPPER".id = some_schema.tablename.id
     "###);
 
     // GH-1493
@@ -655,6 +1065,22 @@ fn test_quoting() {
       JOIN `db.schema.t-able` AS c ON `db.schema.table`.id = c.id
     "###);
 
+    // dotted names are split into parts, unless some part is not an identifier
+    assert_display_snapshot!((compile(r###"
+    from `db.Schema.table`
+    join `db.schema.t-able` (==id)
+    join m = `db.my\.table` (`db.schema.t-able`.id == m.id)
+    "###).unwrap()), @r###"
+    SELECT
+      db."Schema"."table".*,
+      "db.schema.t-able".*,
+      m.*
+    FROM
+      db."Schema"."table"
+      JOIN "db.schema.t-able" ON db."Schema"."table".id = "db.schema.t-able".id
+      JOIN db."my.table" AS m ON "db.schema.t-able".id = m.id
+    "###);
+
     assert_display_snapshot!((compile(r###"
     default_db.table
     select `first name`
@@ -715,6 +1141,118 @@ fn test_sorts() {
     "###);
 }
 
+#[test]
+fn test_sort_by_aggregate() {
+    // the aggregate is referenced by its alias, in the same SELECT
+    assert_display_snapshot!((compile(r###"
+    from employees
+    group {department} (
+      aggregate {total_salary = sum salary}
+    )
+    sort {-total_salary}
+    take 3
+    "###
+    ).unwrap()), @r###"
+    SELECT
+      department,
+      COALESCE(SUM(salary), 0) AS total_salary
+    FROM
+      employees
+    GROUP BY
+      department
+    ORDER BY
+      total_salary DESC
+    LIMIT
+      3
+    "###);
+}
+
+#[test]
+fn test_sort_nulls() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
+    from invoices
+    sort {-amount nulls:last, issued_at nulls:first, id}
+    "###
+    ).unwrap()), @r###"
+    SELECT
+      *
+    FROM
+      invoices
+    ORDER BY
+      amount DESC NULLS LAST,
+      issued_at NULLS FIRST,
+      id
+    "###);
+
+    // MySQL has no NULLS FIRST, so nulls are sorted separately
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.mysql
+    from invoices
+    sort {-amount nulls:first}
+    "###
+    ).unwrap()), @r###"
+    SELECT
+      *
+    FROM
+      invoices
+    ORDER BY
+      CASE
+        WHEN amount IS NULL THEN 0
+        ELSE 1
+      END,
+      amount DESC
+    "###);
+
+    // ... unless the query asks for the SQL to be used as written
+    let err = compile(
+        r###"
+    prql target:sql.mysql strict:true
+    from invoices
+    sort {-amount nulls:first}
+    "###,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("does not support NULLS FIRST or NULLS LAST"));
+
+    // strict is accepted, but has no effect, when the dialect supports the feature
+    assert!(compile(
+        r###"
+    prql target:sql.postgres strict:true
+    from invoices
+    sort {-amount nulls:first}
+    "###
+    )
+    .unwrap()
+    .contains("amount DESC NULLS FIRST"));
+
+    assert_display_snapshot!((compile(r###"
+    from invoices
+    sort {amount nulls:middle}
+    "###
+    ).unwrap_err()), @r###"
+    Error:
+       ╭─[:3:24]
+       │
+     3 │     sort {amount nulls:middle}
+       │                        ───┬──
+       │                           ╰──── `nulls` expected first or last, but found middle
+    ───╯
+    "###);
+
+    // outside of `sort`, the key is called as if it was a function
+    let err = compile(
+        r###"
+    from invoices
+    derive {amount nulls:last}
+    "###,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("expected a function"));
+}
+
 #[test]
 fn test_numbers() {
     let query = r###"
@@ -738,6 +1276,45 @@ fn test_numbers() {
     FROM
       numbers
     "###);
+
+    // leading zeros of decimals are kept, so every dialect accepts them
+    assert_display_snapshot!((compile(r###"
+    from numbers
+    derive {x = 0.5, y = 0.05, z = -0.5}
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      0.5 AS x,
+      0.05 AS y,
+      -0.5 AS z
+    FROM
+      numbers
+    "###);
+
+    // a number must have digits on both sides of the period
+    compile("from numbers | derive x = .5").unwrap_err();
+    compile("from numbers | derive y = 1.").unwrap_err();
+}
+
+#[test]
+fn test_float_division() {
+    let query = r###"
+    prql target:sql.postgres
+
+    from numbers
+    select {a = 5 / 2, b = 5.0 / 2, c = x / 2.0, d = x / y}
+    "###;
+
+    // integer division needs a cast to float, which a float literal provides
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      (5 * 1.0 / 2) AS a,
+      (5.0 / 2) AS b,
+      (x / 2.0) AS c,
+      (x * 1.0 / y) AS d
+    FROM
+      numbers
+    "###);
 }
 
 #[test]
@@ -760,6 +1337,50 @@ fn test_ranges() {
     "###);
 }
 
+#[test]
+fn test_in_values() {
+    assert_display_snapshot!((compile(r###"
+    from employees
+    filter (country | in ["USA", "UK", "DE"])
+    derive is_senior = (level | in [3, 4 + 1])
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      level IN (3, 4 + 1) AS is_senior
+    FROM
+      employees
+    WHERE
+      country IN ('USA', 'UK', 'DE')
+    "###);
+
+    // an empty list never matches
+    assert_display_snapshot!((compile(r###"
+    from employees
+    filter (country | in [])
+    "###).unwrap()), @r###"
+    SELECT
+      *
+    FROM
+      employees
+    WHERE
+      1 = 0
+    "###);
+
+    // MSSQL has no boolean literals
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.mssql
+    from employees
+    filter (country | in [])
+    "###).unwrap()), @r###"
+    SELECT
+      *
+    FROM
+      employees
+    WHERE
+      1 = 0
+    "###);
+}
+
 #[test]
 fn test_interval() {
     let query = r###"
@@ -788,27 +1409,260 @@ fn test_interval() {
     FROM
       projects
     "###);
-}
 
-#[test]
-fn test_dates() {
-    assert_display_snapshot!((compile(r###"
-    from to_do_empty_table
+    let query = r###"
+    from projects
     derive {
-        date = @2011-02-01,
-        timestamp = @2011-02-01T10:00,
-        time = @14:00,
-        # datetime = @2011-02-01T10:00<datetime>,
+        reviewed_on = finish - 3months,
+        renewed_on = start + (1year + 6months),
     }
-    "###).unwrap()), @r###"
+    "###;
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
     SELECT
       *,
-      DATE '2011-02-01' AS date,
-      TIMESTAMP '2011-02-01T10:00' AS timestamp,
-      TIME '14:00' AS time
+      finish - INTERVAL 3 MONTH AS reviewed_on,
+      start + INTERVAL 1 YEAR + INTERVAL 6 MONTH AS renewed_on
     FROM
-      to_do_empty_table
+      projects
+    "###);
+
+    // dialects which take intervals as strings get a single interval
+    assert_display_snapshot!((compile(&format!("prql target:sql.postgres\n{query}")).unwrap()), @r###"
+    SELECT
+      *,
+      finish - INTERVAL '3' MONTH AS reviewed_on,
+      start + INTERVAL '1 year 6 months' AS renewed_on
+    FROM
+      projects
+    "###);
+}
+
+#[test]
+fn test_unnest() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
+
+    from posts
+    select {id, tag = unnest tags}
+    "###).unwrap()), @r###"
+    SELECT
+      posts.id,
+      tag.tag
+    FROM
+      posts
+      CROSS JOIN UNNEST(posts.tags) AS tag
+    "###);
+
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
+
+    from posts
+    derive tag = (unnest tags)
+    "###).unwrap()), @r###"
+    SELECT
+      posts.*,
+      tag.tag
+    FROM
+      posts
+      CROSS JOIN UNNEST(posts.tags) AS tag
+    "###);
+
+    let err = compile(
+        r###"
+    prql target:sql.sqlite
+
+    from posts
+    select {id, tag = unnest tags}
+    "###,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("operator std.unnest is not supported for dialect sqlite"));
+}
+
+#[test]
+fn test_dates() {
+    assert_display_snapshot!((compile(r###"
+    from to_do_empty_table
+    derive {
+        date = @2011-02-01,
+        timestamp = @2011-02-01T10:00,
+        time = @14:00,
+        # datetime = @2011-02-01T10:00<datetime>,
+    }
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      DATE '2011-02-01' AS date,
+      TIMESTAMP '2011-02-01T10:00' AS timestamp,
+      TIME '14:00' AS time
+    FROM
+      to_do_empty_table
+    "###);
+}
+
+#[test]
+fn test_timestamps_with_time_zone() {
+    let query = r###"
+    from events
+    derive {starts_at = @2011-02-01T10:00+02:00}
+    select {starts_at}
+    "###;
+
+    assert_display_snapshot!((compile(&format!("prql target:sql.postgres\n{query}")).unwrap()), @r###"
+    SELECT
+      TIMESTAMP WITH TIME ZONE '2011-02-01T10:00+02:00' AS starts_at
+    FROM
+      events
+    "###);
+
+    assert_display_snapshot!((compile(&format!("prql target:sql.sqlite\n{query}")).unwrap()), @r###"
+    SELECT
+      DATETIME('2011-02-01T10:00+02:00') AS starts_at
+    FROM
+      events
+    "###);
+
+    // the offset is kept, but the dialect may ignore it
+    assert_display_snapshot!((compile(&format!("prql target:sql.mysql\n{query}")).unwrap()), @r###"
+    SELECT
+      TIMESTAMP '2011-02-01T10:00+02:00' AS starts_at
+    FROM
+      events
+    "###);
+}
+
+#[test]
+fn test_approx_count_distinct() {
+    let query = |dialect: &str| {
+        format!(
+            r###"
+    prql target:sql.{dialect}
+    from page_views
+    aggregate {{visitors = approx_count_distinct user_id}}
+    "###
+        )
+    };
+
+    assert_display_snapshot!(compile(&query("bigquery")).unwrap(), @r###"
+    SELECT
+      APPROX_COUNT_DISTINCT(user_id) AS visitors
+    FROM
+      page_views
+    "###);
+
+    assert_display_snapshot!(compile(&query("trino")).unwrap(), @r###"
+    SELECT
+      APPROX_DISTINCT(user_id) AS visitors
+    FROM
+      page_views
+    "###);
+
+    assert_display_snapshot!(compile(&query("clickhouse")).unwrap(), @r###"
+    SELECT
+      uniq(user_id) AS visitors
+    FROM
+      page_views
+    "###);
+
+    assert_display_snapshot!(compile(&query("redshift")).unwrap(), @r###"
+    SELECT
+      APPROXIMATE COUNT(DISTINCT user_id) AS visitors
+    FROM
+      page_views
+    "###);
+
+    // without an approximation, distinct values are counted exactly
+    assert_display_snapshot!(compile(&query("postgres")).unwrap(), @r###"
+    SELECT
+      COUNT(DISTINCT user_id) AS visitors
+    FROM
+      page_views
+    "###);
+}
+
+#[test]
+fn test_median_approximation() {
+    let query = |dialect: &str| {
+        format!(
+            r###"
+    prql target:sql.{dialect}
+    from orders
+    aggregate {{typical_total = median total}}
+    "###
+        )
+    };
+    let approximate = Options::default()
+        .no_signature()
+        .with_allow_approximations(true);
+
+    assert_display_snapshot!(compile(&query("duckdb")).unwrap(), @r###"
+    SELECT
+      MEDIAN(total) AS typical_total
+    FROM
+      orders
+    "###);
+
+    // Trino can only approximate the median
+    let err = compile(&query("trino")).unwrap_err().to_string();
+    assert!(err.contains("operator std.median is not supported for dialect trino"));
+    assert!(err.contains("set `allow_approximations` to compute an approximation instead"));
+
+    assert_display_snapshot!(crate::compile(&query("trino"), &approximate).unwrap(), @r###"
+    SELECT
+      APPROX_PERCENTILE(total, 0.5) AS typical_total
+    FROM
+      orders
+    "###);
+
+    // SQLite cannot even approximate it
+    let err = crate::compile(&query("sqlite"), &approximate)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("operator std.median is not supported for dialect sqlite"));
+    assert!(!err.contains("allow_approximations"));
+
+    // strict queries are never approximated
+    let err = crate::compile(&query("trino strict:true"), &approximate)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("operator std.median is not supported for dialect trino"));
+    assert!(err.contains("remove `strict:true` to compute an approximation instead"));
+}
+
+#[test]
+fn test_sql_standard() {
+    let query = r###"
+    from employees
+    window expanding:true (derive {running_total = sum salary})
+    "###;
+
+    let options = |standard| {
+        Options::default()
+            .no_signature()
+            .with_sql_standard(standard)
+    };
+
+    let err = crate::compile(query, &options(sql::SqlStandard::Sql92))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("window functions require SQL:2003, but the target is SQL-92"));
+
+    assert_display_snapshot!(crate::compile(query, &options(sql::SqlStandard::Sql2003)).unwrap(), @r###"
+    SELECT
+      *,
+      SUM(salary) OVER (ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) AS running_total
+    FROM
+      employees
     "###);
+
+    let err = crate::compile(
+        "from employees | take 10 | filter salary > 0",
+        &options(sql::SqlStandard::Sql92),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("CTEs require SQL:1999, but the target is SQL-92"));
 }
 
 #[test]
@@ -827,6 +1681,63 @@ fn test_window_functions_00() {
     "###);
 }
 
+#[test]
+fn test_group_by_style() {
+    let query = r#"
+    from cust_order
+    derive {order_month = s"TO_CHAR({order_date}, '%Y-%m')"}
+    group {order_month, status} (aggregate {num_orders = count order_id})
+    "#;
+    let options = Options::default()
+        .no_signature()
+        .with_target(Target::Sql(Some(sql::Dialect::MySql)));
+
+    let ordinals = options
+        .clone()
+        .with_group_by_style(sql::GroupByStyle::Ordinals);
+    assert_snapshot!(crate::compile(query, &ordinals).unwrap(), @r###"
+    SELECT
+      TO_CHAR(order_date, '%Y-%m') AS order_month,
+      status,
+      COUNT(*) AS num_orders
+    FROM
+      cust_order
+    GROUP BY
+      1,
+      2
+    "###);
+
+    let aliases = options.with_group_by_style(sql::GroupByStyle::Aliases);
+    assert_snapshot!(crate::compile(query, &aliases).unwrap(), @r###"
+    SELECT
+      TO_CHAR(order_date, '%Y-%m') AS order_month,
+      status,
+      COUNT(*) AS num_orders
+    FROM
+      cust_order
+    GROUP BY
+      order_month,
+      status
+    "###);
+
+    // not supported by MS SQL, so expressions are repeated
+    let mssql = Options::default()
+        .no_signature()
+        .with_target(Target::Sql(Some(sql::Dialect::MsSql)))
+        .with_group_by_style(sql::GroupByStyle::Ordinals);
+    assert_snapshot!(crate::compile(query, &mssql).unwrap(), @r###"
+    SELECT
+      TO_CHAR(order_date, '%Y-%m') AS order_month,
+      status,
+      COUNT(*) AS num_orders
+    FROM
+      cust_order
+    GROUP BY
+      TO_CHAR(order_date, '%Y-%m'),
+      status
+    "###);
+}
+
 #[test]
 fn test_window_functions_02() {
     let query = r###"
@@ -890,6 +1801,110 @@ fn test_window_functions_02() {
     "###);
 }
 
+#[test]
+fn test_window_functions_over_aggregate() {
+    // aggregates cannot be nested within window functions, so the aggregation
+    // is moved into a CTE
+    assert_display_snapshot!((compile(r###"
+    from orders
+    group customer_id (aggregate {total = sum amount})
+    derive {share = total / (sum total)}
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
+      SELECT
+        customer_id,
+        COALESCE(SUM(amount), 0) AS total
+      FROM
+        orders
+      GROUP BY
+        customer_id
+    )
+    SELECT
+      customer_id,
+      total,
+      (total / SUM(total) OVER ()) AS share
+    FROM
+      table_0
+    "###);
+}
+
+#[test]
+fn test_materialize_window_args() {
+    let query = r###"
+    from orders
+    derive {gross = price * quantity}
+    group customer_id (derive {customer_gross = sum gross})
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      price * quantity AS gross,
+      SUM(price * quantity) OVER (PARTITION BY customer_id) AS customer_gross
+    FROM
+      orders
+    "###);
+
+    let options = Options::default()
+        .no_signature()
+        .with_materialize_window_args(true);
+    assert_display_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    WITH table_0 AS (
+      SELECT
+        *,
+        price * quantity AS gross
+      FROM
+        orders
+    )
+    SELECT
+      *,
+      SUM(gross) OVER (PARTITION BY customer_id) AS customer_gross
+    FROM
+      table_0
+    "###);
+
+    // columns that are only renamed are already referenced by name
+    let query = r###"
+    from orders
+    derive {amount = price}
+    group customer_id (derive {customer_amount = sum amount})
+    "###;
+    assert_display_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    SELECT
+      *,
+      price AS amount,
+      SUM(price) OVER (PARTITION BY customer_id) AS customer_amount
+    FROM
+      orders
+    "###);
+
+    // aggregates are moved into a CTE regardless of the option
+    let query = r###"
+    from orders
+    group customer_id (aggregate {total = sum amount})
+    derive {share = total / (sum total)}
+    "###;
+    let sql = crate::compile(query, &options).unwrap();
+    assert_eq!(sql, compile(query).unwrap());
+    assert_display_snapshot!(sql, @r###"
+    WITH table_0 AS (
+      SELECT
+        customer_id,
+        COALESCE(SUM(amount), 0) AS total
+      FROM
+        orders
+      GROUP BY
+        customer_id
+    )
+    SELECT
+      customer_id,
+      total,
+      (total / SUM(total) OVER ()) AS share
+    FROM
+      table_0
+    "###);
+}
+
 #[test]
 fn test_window_functions_03() {
     // lag must be recognized as window function, even outside of group context
@@ -917,68 +1932,223 @@ fn test_window_functions_03() {
 }
 
 #[test]
-fn test_window_functions_04() {
-    // sort does not affects into groups, group undoes sorting
-    let query = r###"
+fn test_window_functions_lead() {
+    // lead is the counterpart of lag and is a window function as well
+    assert_display_snapshot!((compile(r###"
     from daily_orders
-    sort day
-    group month (derive {total_month = rank day})
-    derive {last_week = lag 7 num_orders}
-    "###;
-
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    derive {next = lead 1 value}
+    "###).unwrap()), @r###"
     SELECT
       *,
-      RANK() OVER (PARTITION BY month) AS total_month,
-      LAG(num_orders, 7) OVER () AS last_week
+      LEAD(value, 1) OVER () AS next
     FROM
       daily_orders
     "###);
 }
 
 #[test]
-fn test_window_functions_05() {
-    // sort does not leak out of groups
-    let query = r###"
+fn test_window_functions_ignore_nulls() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.bigquery
     from daily_orders
-    sort day
-    group month (sort num_orders | window expanding:true (derive {rank day}))
-    derive {num_orders_last_week = lag 7 num_orders}
-    "###;
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    derive {
+      first_count = (first num_orders ignore_nulls:true),
+      last_count = (last num_orders ignore_nulls:true),
+    }
+    "###).unwrap()), @r###"
     SELECT
       *,
-      RANK() OVER (
-        PARTITION BY month
-        ORDER BY
-          num_orders
-      ),
-      LAG(num_orders, 7) OVER () AS num_orders_last_week
+      FIRST_VALUE(num_orders IGNORE NULLS) OVER () AS first_count,
+      LAST_VALUE(num_orders IGNORE NULLS) OVER () AS last_count
     FROM
       daily_orders
     "###);
-}
 
-#[test]
-fn test_window_functions_06() {
-    // detect sum as a window function, even without group or window
     assert_display_snapshot!((compile(r###"
-    from foo
-    derive {a = sum b}
-    group c (
-        derive {d = sum b}
-    )
+    prql target:sql.snowflake
+    from daily_orders
+    derive {last_week = (lag 7 num_orders ignore_nulls:true)}
     "###).unwrap()), @r###"
     SELECT
       *,
-      SUM(b) OVER () AS a,
-      SUM(b) OVER (PARTITION BY c) AS d
+      LAG(num_orders, 7) IGNORE NULLS OVER () AS last_week
     FROM
-      foo
+      daily_orders
     "###);
-}
 
-#[test]
+    assert!(compile(
+        r###"
+    prql target:sql.postgres
+    from daily_orders
+    derive {last_count = (last num_orders ignore_nulls:true)}
+    "###
+    )
+    .unwrap_err()
+    .to_string()
+    .contains("`ignore_nulls` of std.last is not supported for dialect postgres"));
+}
+
+#[test]
+fn test_fill() {
+    // forward-fill of prices, separately for each ticker
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.bigquery
+    from prices
+    select {ticker, traded_at, price}
+    group ticker (
+      sort traded_at
+      fill price
+    )
+    "###).unwrap()), @r###"
+    SELECT
+      ticker,
+      traded_at,
+      LAST_VALUE(price IGNORE NULLS) OVER (
+        PARTITION BY ticker
+        ORDER BY
+          traded_at ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW
+      ) AS price
+    FROM
+      prices
+    "###);
+}
+
+#[test]
+fn test_window_functions_04() {
+    // sort does not affects into groups, group undoes sorting
+    let query = r###"
+    from daily_orders
+    sort day
+    group month (derive {total_month = rank day})
+    derive {last_week = lag 7 num_orders}
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      RANK() OVER (PARTITION BY month) AS total_month,
+      LAG(num_orders, 7) OVER () AS last_week
+    FROM
+      daily_orders
+    "###);
+}
+
+#[test]
+fn test_window_functions_05() {
+    // sort does not leak out of groups
+    let query = r###"
+    from daily_orders
+    sort day
+    group month (sort num_orders | window expanding:true (derive {rank day}))
+    derive {num_orders_last_week = lag 7 num_orders}
+    "###;
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      RANK() OVER (
+        PARTITION BY month
+        ORDER BY
+          num_orders
+      ),
+      LAG(num_orders, 7) OVER () AS num_orders_last_week
+    FROM
+      daily_orders
+    "###);
+}
+
+#[test]
+fn test_window_functions_ranking() {
+    // like rank, these use the partition and the sorting of the group
+    let query = r###"
+    from employees
+    group department (
+      sort salary
+      derive {quartile = ntile 4 salary, pct = percent_rank salary}
+    )
+    "###;
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      NTILE(4) OVER (
+        PARTITION BY department
+        ORDER BY
+          salary
+      ) AS quartile,
+      PERCENT_RANK() OVER (
+        PARTITION BY department
+        ORDER BY
+          salary
+      ) AS pct
+    FROM
+      employees
+    "###);
+}
+
+#[test]
+fn test_window_functions_06() {
+    // detect sum as a window function, even without group or window
+    assert_display_snapshot!((compile(r###"
+    from foo
+    derive {a = sum b}
+    group c (
+        derive {d = sum b}
+    )
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      SUM(b) OVER () AS a,
+      SUM(b) OVER (PARTITION BY c) AS d
+    FROM
+      foo
+    "###);
+}
+
+#[test]
+fn test_window_functions_partition_only() {
+    // sorting of the relation does not leak into the window of the group
+    assert_display_snapshot!((compile(r###"
+    from sales
+    sort sold_at
+    group region (derive {total = sum amount})
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      SUM(amount) OVER (PARTITION BY region) AS total
+    FROM
+      sales
+    "###);
+}
+
+#[test]
+fn test_within() {
+    // group collapses the rows of each group
+    assert_display_snapshot!((compile(r###"
+    from employees
+    group department (aggregate {avg_salary = average salary})
+    "###).unwrap()), @r###"
+    SELECT
+      department,
+      AVG(salary) AS avg_salary
+    FROM
+      employees
+    GROUP BY
+      department
+    "###);
+
+    // within keeps all rows
+    assert_display_snapshot!((compile(r###"
+    from employees
+    within department {avg_salary = average salary}
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      AVG(salary) OVER (PARTITION BY department) AS avg_salary
+    FROM
+      employees
+    "###);
+}
+
+#[test]
 fn test_window_functions_07() {
     assert_display_snapshot!((compile(r###"
     from foo
@@ -1010,6 +2180,104 @@ fn test_window_functions_08() {
     "###);
 }
 
+#[test]
+fn test_window_frame_of_offset_functions() {
+    // lag has no frame, even within a window
+    assert_display_snapshot!((compile(r###"
+    from daily_orders
+    sort day
+    window rolling:3 (
+        derive {
+            last_day = lag 1 num_orders,
+            avg_3_days = average num_orders,
+        }
+    )
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      LAG(num_orders, 1) OVER (
+        ORDER BY
+          day
+      ) AS last_day,
+      AVG(num_orders) OVER (
+        ORDER BY
+          day ROWS BETWEEN 2 PRECEDING AND CURRENT ROW
+      ) AS avg_3_days
+    FROM
+      daily_orders
+    ORDER BY
+      day
+    "###);
+
+    // neither have ranking functions
+    assert_display_snapshot!((compile(r###"
+    from employees
+    window expanding:true (
+        derive {
+            salary_rank = rank salary,
+            salary_dense_rank = rank_dense salary,
+            next_salary = lead 1 salary,
+            total_salary = sum salary,
+        }
+    )
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      RANK() OVER () AS salary_rank,
+      DENSE_RANK() OVER () AS salary_dense_rank,
+      LEAD(salary, 1) OVER () AS next_salary,
+      SUM(salary) OVER (ROWS BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW) AS total_salary
+    FROM
+      employees
+    "###);
+}
+
+#[test]
+fn test_window_partition() {
+    assert_display_snapshot!((compile(r###"
+    from trades
+    sort traded_at
+    window partition:{ticker, venue} rolling:3 (
+        derive {avg_price = average price}
+    )
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      AVG(price) OVER (
+        PARTITION BY ticker,
+        venue
+        ORDER BY
+          traded_at ROWS BETWEEN 2 PRECEDING AND CURRENT ROW
+      ) AS avg_price
+    FROM
+      trades
+    ORDER BY
+      traded_at
+    "###);
+
+    // within a group, the window is partitioned by keys of both
+    assert_display_snapshot!((compile(r###"
+    from trades
+    group ticker (
+        sort traded_at
+        window partition:venue rolling:3 (
+            derive {avg_price = average price}
+        )
+    )
+    "###).unwrap()), @r###"
+    SELECT
+      *,
+      AVG(price) OVER (
+        PARTITION BY ticker,
+        venue
+        ORDER BY
+          traded_at ROWS BETWEEN 2 PRECEDING AND CURRENT ROW
+      ) AS avg_price
+    FROM
+      trades
+    "###);
+}
+
 #[test]
 fn test_window_functions_09() {
     assert_display_snapshot!((compile(r###"
@@ -1140,6 +2408,73 @@ fn test_name_resolving() {
     "###);
 }
 
+#[test]
+fn test_derive_across_steps() {
+    assert_display_snapshot!((compile(r###"
+    from numbers
+    derive x = 5
+    derive y = x + 1
+    select {y}
+    "###).unwrap()), @r###"
+    SELECT
+      5 + 1 AS y
+    FROM
+      numbers
+    "###);
+
+    // the earlier derive is not selected, so it is inlined
+    assert_display_snapshot!((compile(r###"
+    from numbers
+    derive x = a * 2
+    derive y = x + 1
+    select {a, y}
+    "###).unwrap()), @r###"
+    SELECT
+      a,
+      a * 2 + 1 AS y
+    FROM
+      numbers
+    "###);
+
+    // inlined columns keep their relation names
+    assert_display_snapshot!((compile(r###"
+    from e = employees
+    join d = departments (==dept_id)
+    derive gross = e.salary + e.bonus
+    derive share = gross / d.budget
+    select {e.name, share}
+    "###).unwrap()), @r###"
+    SELECT
+      e.name,
+      (e.salary + e.bonus) / d.budget AS share
+    FROM
+      employees AS e
+      JOIN departments AS d ON e.dept_id = d.dept_id
+    "###);
+
+    // a reference across a split refers to the column of the CTE
+    assert_display_snapshot!((compile(r###"
+    from employees
+    derive gross = salary + bonus
+    take 10
+    derive net = gross * 0.7
+    select {net}
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
+      SELECT
+        salary + bonus AS gross
+      FROM
+        employees
+      LIMIT
+        10
+    )
+    SELECT
+      gross * 0.7 AS net
+    FROM
+      table_0
+    "###);
+}
+
 #[test]
 fn test_strings() {
     let query = r###"
@@ -1407,63 +2742,58 @@ fn test_take() {
 }
 
 #[test]
-fn test_distinct() {
-    // window functions cannot materialize into where statement: CTE is needed
+fn test_take_oracle() {
+    // Oracle before 12c has no row limiting clause, so rows are limited with
+    // `ROWNUM`
     assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle
     from employees
-    derive {rn = row_number id}
-    filter rn > 2
+    take 10
     "###).unwrap()), @r###"
-    WITH table_0 AS (
-      SELECT
-        *,
-        ROW_NUMBER() OVER () AS rn
-      FROM
-        employees
-    )
     SELECT
       *
     FROM
-      table_0
+      employees
     WHERE
-      rn > 2
+      ROWNUM <= 10
     "###);
 
-    // basic distinct
+    // `ROWNUM` is assigned before ORDER BY, so sorted relations are limited
+    // in an enclosing query
     assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle
     from employees
-    select first_name
-    group first_name (take 1)
+    sort age
+    take 10
     "###).unwrap()), @r###"
     SELECT
-      DISTINCT first_name
+      *
     FROM
-      employees
+      (
+        SELECT
+          *
+        FROM
+          employees
+        ORDER BY
+          age
+      )
+    WHERE
+      ROWNUM <= 10
     "###);
 
-    // distinct on two columns
     assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle
     from employees
-    select {first_name, last_name}
-    group {first_name, last_name} (take 1)
-    "###).unwrap()), @r###"
-    SELECT
-      DISTINCT first_name,
-      last_name
-    FROM
-      employees
-    "###);
-
-    // We want distinct only over first_name and last_name, so we can't use a
-    // `DISTINCT *` here.
-    assert_display_snapshot!((compile(r###"
-    from employees
-    group {first_name, last_name} (take 1)
+    sort age
+    take 5..10
     "###).unwrap()), @r###"
     WITH table_0 AS (
       SELECT
-        *,
-        ROW_NUMBER() OVER (PARTITION BY first_name, last_name) AS _expr_0
+        employees.*,
+        ROW_NUMBER() OVER (
+          ORDER BY
+            age
+        ) AS _expr_0
       FROM
         employees
     )
@@ -1472,24 +2802,29 @@ fn test_distinct() {
     FROM
       table_0
     WHERE
-      _expr_0 <= 1
+      _expr_0 BETWEEN 5 AND 10
+    ORDER BY
+      age
     "###);
 
-    // Check that a different order doesn't stop distinct from being used.
-    assert!(compile(
-        "from employees | select {first_name, last_name} | group {last_name, first_name} (take 1)"
-    )
-    .unwrap()
-    .contains("DISTINCT"));
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle
+    from employees
+    take 5..10
+    "###).unwrap_err()), @r###"
+    Error: The dialect oracle can only skip rows of a sorted relation
+    ↳ Hint: add a `sort` before the `take`
+    "###);
 
-    // head
+    // Oracle rejects a bare `*` next to other columns
     assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle
     from employees
     group department (take 3)
     "###).unwrap()), @r###"
     WITH table_0 AS (
       SELECT
-        *,
+        employees.*,
         ROW_NUMBER() OVER (PARTITION BY department) AS _expr_0
       FROM
         employees
@@ -1503,1686 +2838,3699 @@ fn test_distinct() {
     "###);
 
     assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle12
     from employees
-    group department (sort salary | take 2..3)
+    take 10
     "###).unwrap()), @r###"
-    WITH table_0 AS (
-      SELECT
-        *,
-        ROW_NUMBER() OVER (
-          PARTITION BY department
-          ORDER BY
-            salary
-        ) AS _expr_0
-      FROM
-        employees
-    )
     SELECT
       *
     FROM
-      table_0
-    WHERE
-      _expr_0 BETWEEN 2 AND 3
+      employees
+    FETCH FIRST
+      10 ROWS ONLY
     "###);
 
     assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle12
     from employees
-    group department (sort salary | take 4..4)
+    sort age
+    take 5..10
     "###).unwrap()), @r###"
-    WITH table_0 AS (
-      SELECT
-        *,
-        ROW_NUMBER() OVER (
-          PARTITION BY department
-          ORDER BY
-            salary
-        ) AS _expr_0
-      FROM
-        employees
-    )
     SELECT
       *
     FROM
-      table_0
-    WHERE
-      _expr_0 = 4
+      employees
+    ORDER BY
+      age OFFSET 4 ROWS
+    FETCH FIRST
+      6 ROWS ONLY
     "###);
+}
 
-    assert_display_snapshot!(compile("
-    from invoices
-    select {billing_country, billing_city}
-    group {billing_city} (
-      take 1
+#[test]
+fn test_table_alias_oracle() {
+    // Oracle rejects `AS` before the alias of a table
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.oracle
+    from e = employees
+    join d = departments (==dept_id)
+    select {e.name, d.budget}
+    "###).unwrap()), @r###"
+    SELECT
+      e.name,
+      d.budget
+    FROM
+      employees e
+      JOIN departments d ON e.dept_id = d.dept_id
+    "###);
+}
+
+#[test]
+fn test_oracle() {
+    // Oracle has no USING for joins
+    let options = Options::default()
+        .no_signature()
+        .with_join_style(sql::JoinStyle::Using);
+    assert_display_snapshot!(crate::compile(r###"
+    prql target:sql.oracle
+    from employees
+    join departments (==dept_id)
+    "###, &options).unwrap(), @r###"
+    SELECT
+      employees.*,
+      departments.*
+    FROM
+      employees
+      JOIN departments ON employees.dept_id = departments.dept_id
+    "###);
+
+    // intervals are quoted and cannot be compound
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.oracle
+    from projects
+    derive {
+        first_check_in = start + 10days,
+        renewed_on = start + (1year + 6months),
+    }
+    "###).unwrap(), @r###"
+    SELECT
+      projects.*,
+      start + INTERVAL '10' DAY AS first_check_in,
+      start + INTERVAL '1' YEAR + INTERVAL '6' MONTH AS renewed_on
+    FROM
+      projects
+    "###);
+
+    // CONCAT takes exactly two arguments
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.oracle
+    from people
+    select greeting = f"Hello {first_name} {last_name}"
+    "###).unwrap(), @r###"
+    SELECT
+      'Hello ' || first_name || ' ' || last_name AS greeting
+    FROM
+      people
+    "###);
+
+    // there is no EXCEPT, only MINUS, so set differences become anti-joins
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.oracle
+    from album
+    select {artist_id, title}
+    remove (
+        from artist | select artist_id
     )
-    sort billing_city
-    ").unwrap(), @r###"
+    "###).unwrap(), @r###"
     WITH table_0 AS (
       SELECT
-        billing_country,
-        billing_city,
-        ROW_NUMBER() OVER (PARTITION BY billing_city) AS _expr_0
+        artist_id
       FROM
-        invoices
+        artist
     )
     SELECT
-      billing_country,
-      billing_city
+      album.artist_id,
+      album.title
     FROM
-      table_0
+      album
+      LEFT JOIN table_0 ON album.artist_id = table_0.artist_id
     WHERE
-      _expr_0 <= 1
-    ORDER BY
-      billing_city
+      table_0.artist_id IS NULL
+    "###);
+
+    let sql = compile(
+        r###"
+    prql target:sql.oracle
+    let distinct = rel -> (from t = _param.rel | group {t.*} (take 1))
+    let except = `default_db.bottom` top -> (top | distinct | remove bottom)
+
+    from album
+    select {artist_id, title}
+    except (from artist | select {artist_id, name})
+    "###,
+    )
+    .unwrap();
+    assert!(!sql.contains("EXCEPT"));
+    assert!(sql.contains("IS NULL"));
+
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.oracle
+    from album
+    remove artist
+    "###).unwrap_err(), @r###"
+    Error: The dialect OracleDialect does not support EXCEPT ALL
+    ↳ Hint: providing more column information will allow the query to be translated to an anti-join.
+    "###);
+
+    // recursive CTEs require the column list that `loop` does not know
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.oracle
+    from [{n = 1}]
+    loop (select n = n+1 | filter n<5)
+    "###).unwrap_err(), @r###"
+    Error: `loop` is not supported for dialect oracle, because it does not support recursive CTEs
     "###);
 }
 
 #[test]
-fn test_distinct_on() {
+fn test_unknown_target() {
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.postgress
+
+    from employees
+    "###).unwrap_err(), @r###"
+    Error: target `"sql.postgress"` not found
+    ↳ Hint: available targets are: sql.any, sql.ansi, sql.athena, sql.bigquery, sql.clickhouse, sql.duckdb, sql.generic, sql.mssql, sql.mysql, sql.oracle, sql.oracle12, sql.postgres, sql.redshift, sql.sqlite, sql.snowflake, sql.trino
+    "###);
+}
+
+#[test]
+fn test_take_percentage() {
     assert_display_snapshot!((compile(r###"
     prql target:sql.postgres
 
     from employees
-    group department (
-      sort age
-      take 1
-    )
+    take 10%
+    filter salary > 1000
     "###).unwrap()), @r###"
     SELECT
-      DISTINCT ON (department) *
+      *
     FROM
-      employees
-    ORDER BY
-      department,
-      age
+      employees TABLESAMPLE SYSTEM (10)
+    WHERE
+      salary > 1000
     "###);
 
     assert_display_snapshot!((compile(r###"
-    prql target:sql.duckdb
+    prql target:sql.mssql
 
-    from x
-    select {class, begins}
-    group {begins} (take 1)
+    from e = employees
+    take 10%
+    join d = departments (==dept_id)
     "###).unwrap()), @r###"
     SELECT
-      DISTINCT ON (begins) class,
-      begins
+      e.*,
+      d.*
     FROM
-      x
+      employees AS e TABLESAMPLE SYSTEM (10 PERCENT)
+      JOIN departments AS d ON e.dept_id = d.dept_id
     "###);
-}
 
-#[test]
-fn test_join() {
+    // the clause follows the alias as it is quoted
     assert_display_snapshot!((compile(r###"
-    from x
-    join y (==id)
+    prql target:sql.postgres
+
+    from E = Employees
+    take 10%
     "###).unwrap()), @r###"
     SELECT
-      x.*,
-      y.*
+      *
     FROM
-      x
-      JOIN y ON x.id = y.id
+      "Employees" AS "E" TABLESAMPLE SYSTEM (10)
     "###);
 
-    compile("from x | join y {==x.id}").unwrap_err();
-}
-
-#[test]
-fn test_from_json() {
-    // Test that the SQL generated from the JSON of the PRQL is the same as the raw PRQL
-    let original_prql = r#"from e=employees
-join salaries (==emp_no)
-group {e.emp_no, e.gender} (
-aggregate {
-emp_salary = average salaries.salary
-}
-)
-join de=dept_emp (==emp_no)
-join dm=dept_manager (
-(dm.dept_no == de.dept_no) && s"(de.from_date, de.to_date) OVERLAPS (dm.from_date, dm.to_date)"
-)
-group {dm.emp_no, gender} (
-aggregate {
-salary_avg = average emp_salary,
-salary_sd = stddev emp_salary
-}
-)
-derive mng_no = emp_no
-join managers=employees (==emp_no)
-derive mng_name = s"managers.first_name || ' ' || managers.last_name"
-select {mng_name, managers.gender, salary_avg, salary_sd}"#;
-
-    let mut source_tree = SourceTree::from(original_prql);
-    crate::semantic::load_std_lib(&mut source_tree);
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.sqlite
 
-    let sql_from_prql = crate::parser::parse(&source_tree)
-        .and_then(|ast| crate::semantic::resolve_and_lower(ast, &[]))
-        .and_then(|rq| sql::compile(rq, &Options::default()))
-        .unwrap();
+    from employees
+    take 10%
+    "###).unwrap_err()), @r###"
+    Error: The dialect sqlite does not support sampling a percentage of rows
+    ↳ Hint: use `take` with a number of rows instead
+    "###);
 
-    let sql_from_json = crate::prql_to_pl(original_prql)
-        .and_then(crate::json::from_pl)
-        .and_then(|json| crate::json::to_pl(&json))
-        .and_then(crate::pl_to_rq)
-        .and_then(|rq| crate::rq_to_sql(rq, &Options::default()))
-        .unwrap();
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
 
-    assert_eq!(sql_from_prql, sql_from_json);
+    from employees
+    filter salary > 1000
+    take 10%
+    "###).unwrap_err()), @r###"
+    Error:
+       ╭─[:6:5]
+       │
+     6 │     take 10%
+       │     ────┬───
+       │         ╰───── `take` with a percentage can only be applied directly to a table
+       │
+       │ Help: move it right after `from`
+    ───╯
+    "###);
 }
 
 #[test]
-fn test_f_string() {
-    let query = r###"
+fn test_head_tail() {
+    assert_display_snapshot!((compile(r###"
     from employees
-    derive age = year_born - s'now()'
-    select {
-        f"Hello my name is {prefix}{first_name} {last_name}",
-        f"and I am {age} years old."
-    }
-    "###;
-
-    assert_display_snapshot!(
-      compile(query).unwrap(),
-        @r###"
+    head 5
+    "###).unwrap()), @r###"
     SELECT
-      CONCAT(
-        'Hello my name is ',
-        prefix,
-        first_name,
-        ' ',
-        last_name
-      ),
-      CONCAT('and I am ', year_born - now(), ' years old.')
+      *
     FROM
       employees
-    "###
-    );
-
-    assert_display_snapshot!(
-        crate::compile(
-          query,
-          &Options::default()
-              .no_signature()
-              .with_target(Target::Sql(Some(sql::Dialect::SQLite)))
+    LIMIT
+      5
+    "###);
 
-      ).unwrap(),
-          @r###"
+    assert_display_snapshot!((compile(r###"
+    from employees
+    sort age
+    tail 5
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        employees
+      ORDER BY
+        age DESC
+      LIMIT
+        5
+    )
     SELECT
-      'Hello my name is ' || prefix || first_name || ' ' || last_name,
-      'and I am ' || year_born - now() || ' years old.'
+      *
     FROM
-      employees
-    "###
-    );
-}
+      table_0
+    ORDER BY
+      age
+    "###);
 
-#[test]
-fn test_sql_of_ast_1() {
-    let query = r###"
+    let err = compile(
+        r###"
     from employees
-    filter country == "USA"
-    group {title, country} (
-        aggregate {average salary}
+    tail 5
+    "###,
     )
-    sort title
-    take 20
-    "###;
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("`tail` requires the relation to be sorted"));
+    assert!(err.contains("add a `sort` before `tail`"));
+}
 
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
+#[test]
+fn test_sample() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
+
+    from employees
+    filter salary > 1000
+    sample 10
+    "###).unwrap()), @r###"
     SELECT
-      title,
-      country,
-      AVG(salary)
+      *
     FROM
       employees
     WHERE
-      country = 'USA'
-    GROUP BY
-      title,
-      country
+      salary > 1000
     ORDER BY
-      title
+      RANDOM()
     LIMIT
-      20
-    "###
-    );
-}
+      10
+    "###);
 
-#[test]
-// Confirm that a bare s-string in a table definition works as expected.
-fn test_bare_s_string() {
-    let query = r###"
-    let grouping = s"""
-        SELECT SUM(a)
-        FROM tbl
-        GROUP BY
-          GROUPING SETS
-          ((b, c, d), (d), (b, d))
-      """
-    from grouping
-    "###;
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.mysql
 
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        SUM(a)
-      FROM
-        tbl
-      GROUP BY
-        GROUPING SETS ((b, c, d), (d), (b, d))
-    )
+    from employees
+    sample 10
+    "###).unwrap()), @r###"
     SELECT
       *
     FROM
-      table_0
-    "###
-    );
+      employees
+    ORDER BY
+      RAND()
+    LIMIT
+      10
+    "###);
 
-    // Test that case insensitive SELECT is accepted. We allow it as it is valid SQL.
-    let query = r###"
-    let a = s"select insensitive from rude"
-    from a
-    "###;
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.snowflake
 
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        insensitive
-      from
-        rude
-    )
+    from employees
+    sample 10
+    "###).unwrap()), @r###"
     SELECT
       *
     FROM
-      table_0
-    "###
-    );
+      employees SAMPLE (10 ROWS)
+    "###);
 
-    // Check a mixture of cases for good measure.
-    let query = r###"
-    let a = s"sElEcT insensitive from rude"
-    from a
-    "###;
+    // strict queries are not sorted randomly instead
+    let err = compile(
+        r###"
+    prql target:sql.postgres strict:true
 
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
+    from employees
+    sample 10
+    "###,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("The dialect postgres does not support sampling rows of a table"));
+
+    // rows are sampled before filtering
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.duckdb
+
+    from employees
+    sample 10
+    filter salary > 1000
+    "###).unwrap()), @r###"
     WITH table_0 AS (
       SELECT
-        insensitive
-      from
-        rude
+        *
+      FROM
+        employees TABLESAMPLE RESERVOIR(10 ROWS)
     )
     SELECT
       *
     FROM
       table_0
-    "###
-    );
-
-    // Check SELECT\n.
-    let query = r###"
-    let a = s"
-    SELECT
-      foo
-    FROM
-      bar"
-
-    from a
-    "###;
+    WHERE
+      salary > 1000
+    "###);
+}
 
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-      @r###"
+#[test]
+fn test_distinct() {
+    // window functions cannot materialize into where statement: CTE is needed
+    assert_display_snapshot!((compile(r###"
+    from employees
+    derive {rn = row_number id}
+    filter rn > 2
+    "###).unwrap()), @r###"
     WITH table_0 AS (
       SELECT
-        foo
+        *,
+        ROW_NUMBER() OVER () AS rn
       FROM
-        bar
+        employees
     )
     SELECT
       *
     FROM
       table_0
+    WHERE
+      rn > 2
     "###);
 
-    assert_display_snapshot!(compile(r###"
-    from s"SELECTfoo"
-    "###).unwrap_err(), @r###"
-    Error: s-strings representing a table must start with `SELECT `
-    ↳ Hint: this is a limitation by current compiler implementation
-    "###);
-}
-
-#[test]
-// Confirm that a regular expr_call in a table definition works as expected.
-fn test_table_definition_with_expr_call() {
-    let query = r###"
-    let e = take 4 (from employees)
-    from e
-    "###;
-
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
-    WITH e AS (
+    // row_number can be used without an argument
+    assert_display_snapshot!((compile(r###"
+    from employees
+    derive {rn = row_number}
+    filter rn > 2
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
       SELECT
-        *
+        *,
+        ROW_NUMBER() OVER () AS rn
       FROM
         employees
-      LIMIT
-        4
     )
     SELECT
       *
     FROM
-      e
+      table_0
+    WHERE
+      rn > 2
+    "###);
+    assert_eq!(
+        compile(
+            r###"
+    from employees
+    group department (sort salary | derive {rn = row_number})
+    filter rn <= 3
+    "###
+        )
+        .unwrap(),
+        compile(
+            r###"
+    from employees
+    group department (sort salary | derive {rn = row_number this})
+    filter rn <= 3
     "###
+        )
+        .unwrap()
     );
-}
 
-#[test]
-fn test_sql_of_ast_2() {
-    let query = r###"
+    // basic distinct
+    assert_display_snapshot!((compile(r###"
     from employees
-    aggregate sum_salary = s"sum({salary})"
-    filter sum_salary > 100
-    "###;
-    let sql = compile(query).unwrap();
-    assert_snapshot!(sql, @r###"
+    select first_name
+    group first_name (take 1)
+    "###).unwrap()), @r###"
     SELECT
-      sum(salary) AS sum_salary
+      DISTINCT first_name
     FROM
       employees
-    HAVING
-      sum(salary) > 100
     "###);
-    assert!(sql.to_lowercase().contains(&"having".to_lowercase()));
-}
 
-#[test]
-fn test_prql_to_sql_1() {
-    assert_display_snapshot!(compile(r#"
+    // distinct on two columns
+    assert_display_snapshot!((compile(r###"
     from employees
-    aggregate {
-        count salary,
-        sum salary,
-    }
-    "#).unwrap(),
-        @r###"
+    select {first_name, last_name}
+    group {first_name, last_name} (take 1)
+    "###).unwrap()), @r###"
     SELECT
-      COUNT(*),
-      COALESCE(SUM(salary), 0)
+      DISTINCT first_name,
+      last_name
     FROM
       employees
-    "###
-    );
-    assert_display_snapshot!(compile(r#"
-    prql target:sql.postgres
-    from developers
-    group team (
-        aggregate {
-            skill_width = count_distinct specialty,
-        }
+    "###);
+
+    // We want distinct only over first_name and last_name, so we can't use a
+    // `DISTINCT *` here.
+    assert_display_snapshot!((compile(r###"
+    from employees
+    group {first_name, last_name} (take 1)
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
+      SELECT
+        *,
+        ROW_NUMBER() OVER (PARTITION BY first_name, last_name) AS _expr_0
+      FROM
+        employees
     )
-    "#).unwrap(),
-        @r###"
     SELECT
-      team,
-      COUNT(DISTINCT specialty) AS skill_width
+      *
     FROM
-      developers
-    GROUP BY
-      team
-    "###
-    )
-}
+      table_0
+    WHERE
+      _expr_0 <= 1
+    "###);
 
-#[test]
-fn test_prql_to_sql_2() {
-    let query = r#"
-from employees
-filter country == "USA"                           # Each line transforms the previous result.
-derive {                                         # This adds columns / variables.
-gross_salary = salary + payroll_tax,
-gross_cost = gross_salary + benefits_cost      # Variables can use other variables.
-}
-filter gross_cost > 0
-group {title, country} (
-aggregate  {                                 # `by` are the columns to group by.
-    average salary,                          # These are aggregation calcs run on each group.
-    sum     salary,
-    average gross_salary,
-    sum     gross_salary,
-    average gross_cost,
-    sum_gross_cost = sum gross_cost,
-    ct = count salary,
-}
-)
-sort sum_gross_cost
-filter ct > 200
-take 20
-"#;
+    // Check that a different order doesn't stop distinct from being used.
+    assert!(compile(
+        "from employees | select {first_name, last_name} | group {last_name, first_name} (take 1)"
+    )
+    .unwrap()
+    .contains("DISTINCT"));
 
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql, @r###"
+    // Keys that don't cover all of the selected columns can't use DISTINCT
+    // either, because it would return a row for each distinct `age` too.
+    assert_display_snapshot!((compile(r###"
+    from employees
+    select {first_name, last_name, age}
+    group {first_name, last_name} (take 1)
+    "###).unwrap()), @r###"
     WITH table_0 AS (
       SELECT
-        title,
-        country,
-        salary,
-        salary + payroll_tax + benefits_cost AS _expr_0,
-        salary + payroll_tax AS _expr_1
+        first_name,
+        last_name,
+        age,
+        ROW_NUMBER() OVER (PARTITION BY first_name, last_name) AS _expr_0
       FROM
         employees
-      WHERE
-        country = 'USA'
     )
     SELECT
-      title,
-      country,
-      AVG(salary),
-      COALESCE(SUM(salary), 0),
-      AVG(_expr_1),
-      COALESCE(SUM(_expr_1), 0),
-      AVG(_expr_0),
-      COALESCE(SUM(_expr_0), 0) AS sum_gross_cost,
-      COUNT(*) AS ct
+      first_name,
+      last_name,
+      age
     FROM
       table_0
     WHERE
-      _expr_0 > 0
-    GROUP BY
-      title,
-      country
-    HAVING
-      COUNT(*) > 200
-    ORDER BY
-      sum_gross_cost
-    LIMIT
-      20
+      _expr_0 <= 1
     "###);
-}
 
-#[test]
-fn test_prql_to_sql_table() {
-    // table
-    let query = r#"
-    let newest_employees = (
-        from employees
-        sort tenure
-        take 50
-    )
-    let average_salaries = (
-        from salaries
-        group country (
-            aggregate {
-                average_country_salary = average salary
-            }
-        )
-    )
-    from newest_employees
-    join average_salaries (==country)
-    select {name, salary, average_country_salary}
-    "#;
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
-    WITH newest_employees AS (
+    // head
+    assert_display_snapshot!((compile(r###"
+    from employees
+    group department (take 3)
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
       SELECT
-        *
+        *,
+        ROW_NUMBER() OVER (PARTITION BY department) AS _expr_0
       FROM
         employees
-      ORDER BY
-        tenure
-      LIMIT
-        50
-    ), average_salaries AS (
-      SELECT
-        country,
-        AVG(salary) AS average_country_salary
-      FROM
-        salaries
-      GROUP BY
-        country
     )
     SELECT
-      newest_employees.name,
-      newest_employees.salary,
-      average_salaries.average_country_salary
+      *
     FROM
-      newest_employees
-      JOIN average_salaries ON newest_employees.country = average_salaries.country
-    ORDER BY
-      employees.tenure
-    "###
-    );
-}
-
-#[test]
-fn test_nonatomic() {
-    // A take, then two aggregates
-    let query = r###"
-        from employees
-        take 20
-        filter country == "USA"
-        group {title, country} (
-            aggregate {
-                salary = average salary
-            }
-        )
-        group {title, country} (
-            aggregate {
-                sum_gross_cost = average salary
-            }
-        )
-        sort sum_gross_cost
-    "###;
+      table_0
+    WHERE
+      _expr_0 <= 3
+    "###);
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
-    WITH table_1 AS (
+    assert_display_snapshot!((compile(r###"
+    from employees
+    group department (sort salary | take 2..3)
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
       SELECT
-        title,
-        country,
-        salary
+        *,
+        ROW_NUMBER() OVER (
+          PARTITION BY department
+          ORDER BY
+            salary
+        ) AS _expr_0
       FROM
         employees
-      LIMIT
-        20
-    ), table_0 AS (
-      SELECT
-        title,
-        country,
-        AVG(salary) AS _expr_0
-      FROM
-        table_1
-      WHERE
-        country = 'USA'
-      GROUP BY
-        title,
-        country
     )
     SELECT
-      title,
-      country,
-      AVG(_expr_0) AS sum_gross_cost
+      *
     FROM
       table_0
-    GROUP BY
-      title,
-      country
+    WHERE
+      _expr_0 BETWEEN 2 AND 3
+    "###);
+
+    assert_display_snapshot!((compile(r###"
+    from employees
+    group department (sort salary | take 4..4)
+    "###).unwrap()), @r###"
+    WITH table_0 AS (
+      SELECT
+        *,
+        ROW_NUMBER() OVER (
+          PARTITION BY department
+          ORDER BY
+            salary
+        ) AS _expr_0
+      FROM
+        employees
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    WHERE
+      _expr_0 = 4
+    "###);
+
+    assert_display_snapshot!(compile("
+    from invoices
+    select {billing_country, billing_city}
+    group {billing_city} (
+      take 1
+    )
+    sort billing_city
+    ").unwrap(), @r###"
+    WITH table_0 AS (
+      SELECT
+        billing_country,
+        billing_city,
+        ROW_NUMBER() OVER (PARTITION BY billing_city) AS _expr_0
+      FROM
+        invoices
+    )
+    SELECT
+      billing_country,
+      billing_city
+    FROM
+      table_0
+    WHERE
+      _expr_0 <= 1
     ORDER BY
-      sum_gross_cost
+      billing_city
     "###);
+}
 
-    // A aggregate, then sort and filter
-    let query = r###"
-        from employees
-        group {title, country} (
-            aggregate {
-                sum_gross_cost = average salary
-            }
-        )
-        sort sum_gross_cost
-        filter sum_gross_cost > 0
-    "###;
+#[test]
+fn test_distinct_on() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    from employees
+    group department (
+      sort age
+      take 1
+    )
+    "###).unwrap()), @r###"
     SELECT
-      title,
-      country,
-      AVG(salary) AS sum_gross_cost
+      DISTINCT ON (department) *
     FROM
       employees
-    GROUP BY
-      title,
-      country
-    HAVING
-      AVG(salary) > 0
     ORDER BY
-      sum_gross_cost
+      department,
+      age
+    "###);
+
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.duckdb
+
+    from x
+    select {class, begins}
+    group {begins} (take 1)
+    "###).unwrap()), @r###"
+    SELECT
+      DISTINCT ON (begins) class,
+      begins
+    FROM
+      x
+    "###);
+}
+
+#[test]
+fn test_distinct_on_transform() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
+
+    from employees
+    sort {department, -salary}
+    distinct_on {department}
+    "###).unwrap()), @r###"
+    SELECT
+      DISTINCT ON (department) *
+    FROM
+      employees
+    ORDER BY
+      department,
+      salary DESC
+    "###);
+
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.postgres
+
+    from employees
+    sort {-salary}
+    distinct_on {department}
+    "###).unwrap_err()), @r###"
+    Error:
+       ╭─[:5:5]
+       │
+     5 │     distinct_on {department}
+       │     ────────────┬───────────
+       │                 ╰───────────── `distinct_on` requires a preceding `sort` that begins with the same columns
+       │
+       │ Help: add `sort` before `distinct_on`
+    ───╯
+    "###);
+}
+
+#[test]
+fn test_join() {
+    assert_display_snapshot!((compile(r###"
+    from x
+    join y (==id)
+    "###).unwrap()), @r###"
+    SELECT
+      x.*,
+      y.*
+    FROM
+      x
+      JOIN y ON x.id = y.id
+    "###);
+
+    // join on expressions computed from both relations
+    assert_display_snapshot!((compile(r###"
+    from a
+    join b (a.x + 1 == b.y && that.z < this.w * 2)
+    "###).unwrap()), @r###"
+    SELECT
+      a.*,
+      b.*
+    FROM
+      a
+      JOIN b ON a.x + 1 = b.y
+      AND b.z < a.w * 2
+    "###);
+
+    compile("from x | join y {==x.id}").unwrap_err();
+}
+
+#[test]
+fn test_join_style() {
+    let query = r#"
+    from x
+    join y (==id)
+    join side:left z (x.a == z.b)
+    "#;
+
+    // by default, all conditions use ON
+    let options = Options::default().no_signature();
+    assert_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    SELECT
+      x.*,
+      y.*,
+      z.*
+    FROM
+      x
+      JOIN y ON x.id = y.id
+      LEFT JOIN z ON x.a = z.b
+    "###);
+
+    // the shorthand can be expressed with USING
+    let shorthand = options
+        .clone()
+        .with_join_style(sql::JoinStyle::UsingForShorthand);
+    assert_snapshot!(crate::compile(query, &shorthand).unwrap(), @r###"
+    SELECT
+      *
+    FROM
+      x
+      JOIN y USING(id)
+      LEFT JOIN z ON x.a = z.b
+    "###);
+
+    // only conditions that compare columns of the same name can use USING
+    let using = options.with_join_style(sql::JoinStyle::Using);
+    assert_snapshot!(crate::compile(query, &using).unwrap(), @r###"
+    SELECT
+      *
+    FROM
+      x
+      JOIN y USING(id)
+      LEFT JOIN z ON x.a = z.b
+    "###);
+
+    // not supported by MS SQL
+    let mssql = using.with_target(Target::Sql(Some(sql::Dialect::MsSql)));
+    assert_snapshot!(crate::compile(query, &mssql).unwrap(), @r###"
+    SELECT
+      x.*,
+      y.*,
+      z.*
+    FROM
+      x
+      JOIN y ON x.id = y.id
+      LEFT JOIN z ON x.a = z.b
+    "###);
+}
+
+#[test]
+fn test_join_self_equality() {
+    // `==id` is a shorthand for `this.id == that.id`
+    let shorthand = "from x | join side:left y (==id)";
+    let expanded = "from x | join side:left y (this.id == that.id)";
+
+    // by default, both are expressed with ON
+    let options = Options::default().no_signature();
+    let sql = crate::compile(shorthand, &options).unwrap();
+    assert_eq!(sql, crate::compile(expanded, &options).unwrap());
+    assert_snapshot!(sql, @r###"
+    SELECT
+      x.*,
+      y.*
+    FROM
+      x
+      LEFT JOIN y ON x.id = y.id
+    "###);
+
+    // `UsingForShorthand` only expresses the shorthand with USING
+    let shorthand_only = options
+        .clone()
+        .with_join_style(sql::JoinStyle::UsingForShorthand);
+    assert_snapshot!(crate::compile(shorthand, &shorthand_only).unwrap(), @r###"
+    SELECT
+      *
+    FROM
+      x
+      LEFT JOIN y USING(id)
+    "###);
+    assert_snapshot!(crate::compile(expanded, &shorthand_only).unwrap(), @r###"
+    SELECT
+      x.*,
+      y.*
+    FROM
+      x
+      LEFT JOIN y ON x.id = y.id
+    "###);
+
+    let using = options.with_join_style(sql::JoinStyle::Using);
+    let sql = crate::compile(shorthand, &using).unwrap();
+    assert_eq!(sql, crate::compile(expanded, &using).unwrap());
+    assert_snapshot!(sql, @r###"
+    SELECT
+      *
+    FROM
+      x
+      LEFT JOIN y USING(id)
+    "###);
+}
+
+#[test]
+fn test_join_using_three_way() {
+    // both x and y may have `id`, so `USING(id)` would be ambiguous in the
+    // last join
+    let query = r###"
+    from x
+    join y (x.id == y.id)
+    join side:left z (y.id == z.id)
+    "###;
+
+    let using = Options::default()
+        .no_signature()
+        .with_join_style(sql::JoinStyle::Using);
+    assert_snapshot!(crate::compile(query, &using).unwrap(), @r###"
+    SELECT
+      *
+    FROM
+      x
+      JOIN y USING(id)
+      LEFT JOIN z ON y.id = z.id
+    "###);
+}
+
+#[test]
+fn test_join_using_wildcards() {
+    // with USING, `*` contains the join column only once, while `a.*, b.*`
+    // would contain it twice
+    let options = Options::default()
+        .no_signature()
+        .with_join_style(sql::JoinStyle::Using);
+    assert_snapshot!(crate::compile("from a | join b (==id)", &options).unwrap(), @r###"
+    SELECT
+      *
+    FROM
+      a
+      JOIN b USING(id)
+    "###);
+
+    // columns that are selected explicitly are kept as they are
+    assert_snapshot!(crate::compile("from a | join b (==id) | select {a.id, b.c}", &options).unwrap(), @r###"
+    SELECT
+      a.id,
+      b.c
+    FROM
+      a
+      JOIN b USING(id)
     "###);
 }
 
 #[test]
-/// Confirm a nonatomic table works.
-fn test_nonatomic_table() {
-    // A take, then two aggregates
-    let query = r###"
-    let a = (
-        from employees
-        take 50
-        group country (aggregate {s"count(*)"})
+fn test_from_json() {
+    // Test that the SQL generated from the JSON of the PRQL is the same as the raw PRQL
+    let original_prql = r#"from e=employees
+join salaries (==emp_no)
+group {e.emp_no, e.gender} (
+aggregate {
+emp_salary = average salaries.salary
+}
+)
+join de=dept_emp (==emp_no)
+join dm=dept_manager (
+(dm.dept_no == de.dept_no) && s"(de.from_date, de.to_date) OVERLAPS (dm.from_date, dm.to_date)"
+)
+group {dm.emp_no, gender} (
+aggregate {
+salary_avg = average emp_salary,
+salary_sd = stddev emp_salary
+}
+)
+derive mng_no = emp_no
+join managers=employees (==emp_no)
+derive mng_name = s"managers.first_name || ' ' || managers.last_name"
+select {mng_name, managers.gender, salary_avg, salary_sd}"#;
+
+    let mut source_tree = SourceTree::from(original_prql);
+    crate::semantic::load_std_lib(&mut source_tree);
+
+    let sql_from_prql = crate::parser::parse(&source_tree)
+        .and_then(|ast| crate::semantic::resolve_and_lower(ast, &[]))
+        .and_then(|rq| sql::compile(rq, &Options::default()))
+        .unwrap();
+
+    let sql_from_json = crate::prql_to_pl(original_prql)
+        .and_then(crate::json::from_pl)
+        .and_then(|json| crate::json::to_pl(&json))
+        .and_then(crate::pl_to_rq)
+        .and_then(|rq| crate::rq_to_sql(rq, &Options::default()))
+        .unwrap();
+
+    assert_eq!(sql_from_prql, sql_from_json);
+
+    // Formatting the PL deserialized from JSON must give the same result as
+    // formatting the original PL, otherwise something is lost in serialization.
+    let corpus = [
+        original_prql,
+        r#"
+        prql target:sql.postgres version:"0.9"
+
+        @{binding_strength=1}
+        let add_one = x:0 -> x + 1
+
+        from e=employees
+        derive {
+          full_name = f"{first_name} {last_name}",
+          age = s"DATE_PART('year', {birth_date})",
+          next_week = hired_at + 7days - 3hours,
+          since = @2020-01-01,
+          at = @2020-01-01T13:19:55.000+0800,
+          pi = 3.14,
+          big = -9223372036854775807,
+          missing = null,
+          is_active = !(status == "terminated") && true,
+          level = case {salary > 100000 => "senior", true => "junior"},
+        }
+        filter (age | in 18..40)
+        filter (country | in ["USA", "UK"])
+        join side:left salaries (==emp_no)
+        group {e.dept} (window rows:-2..0 (derive {avg = average salary}))
+        sort {-age, name}
+        select !{temp}
+        take 1..10
+        "#,
+        r#"
+        let sales = (
+          from s"SELECT * FROM sales WHERE amount > $1"
+          select {amount = amount ?? 0, region}
+        )
+
+        from sales
+        aggregate {total = sum amount}
+        "#,
+    ];
+
+    for prql in corpus {
+        let pl = crate::prql_to_pl(prql).unwrap();
+        let formatted = crate::pl_to_prql(pl.clone()).unwrap();
+
+        let round_tripped = crate::json::from_pl(pl)
+            .and_then(|json| crate::json::to_pl(&json))
+            .and_then(crate::pl_to_prql)
+            .unwrap();
+
+        assert_eq!(formatted, round_tripped);
+    }
+}
+
+#[test]
+fn test_f_string() {
+    let query = r###"
+    from employees
+    derive age = year_born - s'now()'
+    select {
+        f"Hello my name is {prefix}{first_name} {last_name}",
+        f"and I am {age} years old."
+    }
+    "###;
+
+    assert_display_snapshot!(
+      compile(query).unwrap(),
+        @r###"
+    SELECT
+      CONCAT(
+        'Hello my name is ',
+        prefix,
+        first_name,
+        ' ',
+        last_name
+      ),
+      CONCAT('and I am ', year_born - now(), ' years old.')
+    FROM
+      employees
+    "###
+    );
+
+    assert_display_snapshot!(
+        crate::compile(
+          query,
+          &Options::default()
+              .no_signature()
+              .with_target(Target::Sql(Some(sql::Dialect::SQLite)))
+
+      ).unwrap(),
+          @r###"
+    SELECT
+      'Hello my name is ' || prefix || first_name || ' ' || last_name,
+      'and I am ' || year_born - now() || ' years old.'
+    FROM
+      employees
+    "###
+    );
+}
+
+#[test]
+fn test_s_string_references() {
+    // interpolated names are resolved against the frame, like any other name
+    assert_display_snapshot!((compile(r###"
+    from employees
+    select {id, total = salary}
+    derive rounded = s"ROUND({total}, 2)"
+    "###).unwrap()), @r###"
+    SELECT
+      id,
+      salary AS total,
+      ROUND(salary, 2) AS rounded
+    FROM
+      employees
+    "###);
+
+    assert_display_snapshot!(compile(r###"
+    from employees
+    select {id, total = salary}
+    derive c = s"count({missing})"
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:4:24]
+       │
+     4 │     derive c = s"count({missing})"
+       │                        ────┬────
+       │                            ╰────── Unknown name
+    ───╯
+    "###);
+}
+
+#[test]
+fn test_sql_of_ast_1() {
+    let query = r###"
+    from employees
+    filter country == "USA"
+    group {title, country} (
+        aggregate {average salary}
+    )
+    sort title
+    take 20
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    SELECT
+      title,
+      country,
+      AVG(salary)
+    FROM
+      employees
+    WHERE
+      country = 'USA'
+    GROUP BY
+      title,
+      country
+    ORDER BY
+      title
+    LIMIT
+      20
+    "###
+    );
+}
+
+#[test]
+// Confirm that a bare s-string in a table definition works as expected.
+fn test_bare_s_string() {
+    let query = r###"
+    let grouping = s"""
+        SELECT SUM(a)
+        FROM tbl
+        GROUP BY
+          GROUPING SETS
+          ((b, c, d), (d), (b, d))
+      """
+    from grouping
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        SUM(a)
+      FROM
+        tbl
+      GROUP BY
+        GROUPING SETS ((b, c, d), (d), (b, d))
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "###
+    );
+
+    // Test that case insensitive SELECT is accepted. We allow it as it is valid SQL.
+    let query = r###"
+    let a = s"select insensitive from rude"
+    from a
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        insensitive
+      from
+        rude
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "###
+    );
+
+    // Check a mixture of cases for good measure.
+    let query = r###"
+    let a = s"sElEcT insensitive from rude"
+    from a
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        insensitive
+      from
+        rude
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "###
+    );
+
+    // Check SELECT\n.
+    let query = r###"
+    let a = s"
+    SELECT
+      foo
+    FROM
+      bar"
+
+    from a
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+      @r###"
+    WITH table_0 AS (
+      SELECT
+        foo
+      FROM
+        bar
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    "###);
+
+    assert_display_snapshot!(compile(r###"
+    from s"SELECTfoo"
+    "###).unwrap_err(), @r###"
+    Error: s-strings representing a table must start with `SELECT `
+    ↳ Hint: this is a limitation by current compiler implementation
+    "###);
+}
+
+#[test]
+// Confirm that a regular expr_call in a table definition works as expected.
+fn test_table_definition_with_expr_call() {
+    let query = r###"
+    let e = take 4 (from employees)
+    from e
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    WITH e AS (
+      SELECT
+        *
+      FROM
+        employees
+      LIMIT
+        4
+    )
+    SELECT
+      *
+    FROM
+      e
+    "###
+    );
+}
+
+#[test]
+fn test_sql_of_ast_2() {
+    let query = r###"
+    from employees
+    aggregate sum_salary = s"sum({salary})"
+    filter sum_salary > 100
+    "###;
+    let sql = compile(query).unwrap();
+    assert_snapshot!(sql, @r###"
+    SELECT
+      sum(salary) AS sum_salary
+    FROM
+      employees
+    HAVING
+      sum(salary) > 100
+    "###);
+    assert!(sql.to_lowercase().contains(&"having".to_lowercase()));
+}
+
+#[test]
+fn test_filter_on_group_key() {
+    // conditions on grouping keys are applied before the aggregation
+    assert_snapshot!(compile(r###"
+    from t
+    group {a} (aggregate {s = sum x})
+    filter a == 5
+    "###).unwrap(), @r###"
+    SELECT
+      a,
+      COALESCE(SUM(x), 0) AS s
+    FROM
+      t
+    WHERE
+      a = 5
+    GROUP BY
+      a
+    "###);
+
+    // a condition on both a key and an aggregate is split
+    assert_snapshot!(compile(r###"
+    from t
+    filter x > 0
+    group {a, b} (aggregate {s = sum x})
+    filter a == 5 && s > 10
+    "###).unwrap(), @r###"
+    SELECT
+      a,
+      b,
+      COALESCE(SUM(x), 0) AS s
+    FROM
+      t
+    WHERE
+      x > 0
+      AND a = 5
+    GROUP BY
+      a,
+      b
+    HAVING
+      COALESCE(SUM(x), 0) > 10
+    "###);
+
+    // without keys, the aggregation always produces a row, so constant
+    // conditions must stay in HAVING
+    assert_snapshot!(compile(r###"
+    from t
+    aggregate {s = sum x}
+    filter false
+    "###).unwrap(), @r###"
+    SELECT
+      COALESCE(SUM(x), 0) AS s
+    FROM
+      t
+    HAVING
+      false
+    "###);
+}
+
+#[test]
+fn test_prql_to_sql_1() {
+    assert_display_snapshot!(compile(r#"
+    from employees
+    aggregate {
+        count salary,
+        sum salary,
+    }
+    "#).unwrap(),
+        @r###"
+    SELECT
+      COUNT(*),
+      COALESCE(SUM(salary), 0)
+    FROM
+      employees
+    "###
+    );
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.postgres
+    from developers
+    group team (
+        aggregate {
+            skill_width = count_distinct specialty,
+        }
+    )
+    "#).unwrap(),
+        @r###"
+    SELECT
+      team,
+      COUNT(DISTINCT specialty) AS skill_width
+    FROM
+      developers
+    GROUP BY
+      team
+    "###
+    )
+}
+
+#[test]
+fn test_prql_to_sql_2() {
+    let query = r#"
+from employees
+filter country == "USA"                           # Each line transforms the previous result.
+derive {                                         # This adds columns / variables.
+gross_salary = salary + payroll_tax,
+gross_cost = gross_salary + benefits_cost      # Variables can use other variables.
+}
+filter gross_cost > 0
+group {title, country} (
+aggregate  {                                 # `by` are the columns to group by.
+    average salary,                          # These are aggregation calcs run on each group.
+    sum     salary,
+    average gross_salary,
+    sum     gross_salary,
+    average gross_cost,
+    sum_gross_cost = sum gross_cost,
+    ct = count salary,
+}
+)
+sort sum_gross_cost
+filter ct > 200
+take 20
+"#;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql, @r###"
+    WITH table_0 AS (
+      SELECT
+        title,
+        country,
+        salary,
+        salary + payroll_tax + benefits_cost AS _expr_0,
+        salary + payroll_tax AS _expr_1
+      FROM
+        employees
+      WHERE
+        country = 'USA'
+    )
+    SELECT
+      title,
+      country,
+      AVG(salary),
+      COALESCE(SUM(salary), 0),
+      AVG(_expr_1),
+      COALESCE(SUM(_expr_1), 0),
+      AVG(_expr_0),
+      COALESCE(SUM(_expr_0), 0) AS sum_gross_cost,
+      COUNT(*) AS ct
+    FROM
+      table_0
+    WHERE
+      _expr_0 > 0
+    GROUP BY
+      title,
+      country
+    HAVING
+      COUNT(*) > 200
+    ORDER BY
+      sum_gross_cost
+    LIMIT
+      20
+    "###);
+}
+
+#[test]
+fn test_comments_in_lists() {
+    // comments may follow an item, either before or after its comma, and may
+    // take up whole lines between the items
+    assert_display_snapshot!(compile(r#"
+    from employees
+    select {      # the columns we need
+      first_name, # comment after a comma
+      last_name   # comment before a comma
+      ,
+      # comment on its own line
+      age,
+    }
+    filter age > 18
+    "#).unwrap(), @r###"
+    SELECT
+      first_name,
+      last_name,
+      age
+    FROM
+      employees
+    WHERE
+      age > 18
+    "###);
+}
+
+#[test]
+fn test_prql_to_sql_table() {
+    // table
+    let query = r#"
+    let newest_employees = (
+        from employees
+        sort tenure
+        take 50
+    )
+    let average_salaries = (
+        from salaries
+        group country (
+            aggregate {
+                average_country_salary = average salary
+            }
+        )
+    )
+    from newest_employees
+    join average_salaries (==country)
+    select {name, salary, average_country_salary}
+    "#;
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    WITH newest_employees AS (
+      SELECT
+        *
+      FROM
+        employees
+      ORDER BY
+        tenure
+      LIMIT
+        50
+    ), average_salaries AS (
+      SELECT
+        country,
+        AVG(salary) AS average_country_salary
+      FROM
+        salaries
+      GROUP BY
+        country
+    )
+    SELECT
+      newest_employees.name,
+      newest_employees.salary,
+      average_salaries.average_country_salary
+    FROM
+      newest_employees
+      JOIN average_salaries ON newest_employees.country = average_salaries.country
+    ORDER BY
+      employees.tenure
+    "###
+    );
+}
+
+#[test]
+fn test_nonatomic() {
+    // A take, then two aggregates
+    let query = r###"
+        from employees
+        take 20
+        filter country == "USA"
+        group {title, country} (
+            aggregate {
+                salary = average salary
+            }
+        )
+        group {title, country} (
+            aggregate {
+                sum_gross_cost = average salary
+            }
+        )
+        sort sum_gross_cost
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    WITH table_1 AS (
+      SELECT
+        title,
+        country,
+        salary
+      FROM
+        employees
+      LIMIT
+        20
+    ), table_0 AS (
+      SELECT
+        title,
+        country,
+        AVG(salary) AS _expr_0
+      FROM
+        table_1
+      WHERE
+        country = 'USA'
+      GROUP BY
+        title,
+        country
+    )
+    SELECT
+      title,
+      country,
+      AVG(_expr_0) AS sum_gross_cost
+    FROM
+      table_0
+    GROUP BY
+      title,
+      country
+    ORDER BY
+      sum_gross_cost
+    "###);
+
+    // A aggregate, then sort and filter
+    let query = r###"
+        from employees
+        group {title, country} (
+            aggregate {
+                sum_gross_cost = average salary
+            }
+        )
+        sort sum_gross_cost
+        filter sum_gross_cost > 0
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      title,
+      country,
+      AVG(salary) AS sum_gross_cost
+    FROM
+      employees
+    GROUP BY
+      title,
+      country
+    HAVING
+      AVG(salary) > 0
+    ORDER BY
+      sum_gross_cost
+    "###);
+}
+
+#[test]
+/// Confirm a nonatomic table works.
+fn test_nonatomic_table() {
+    // A take, then two aggregates
+    let query = r###"
+    let a = (
+        from employees
+        take 50
+        group country (aggregate {s"count(*)"})
+    )
+    from a
+    join b (==country)
+    select {name, salary, average_country_salary}
+"###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    WITH table_0 AS (
+      SELECT
+        country
+      FROM
+        employees
+      LIMIT
+        50
+    ), a AS (
+      SELECT
+        country,
+        count(*)
+      FROM
+        table_0
+      GROUP BY
+        country
+    )
+    SELECT
+      b.name,
+      b.salary,
+      b.average_country_salary
+    FROM
+      a
+      JOIN b ON a.country = b.country
+    "###);
+}
+
+#[test]
+fn test_table_names_between_splits() {
+    let prql = r###"
+    from employees
+    join d=department (==dept_no)
+    take 10
+    derive emp_no = employees.emp_no
+    join s=salaries (==emp_no)
+    select {employees.emp_no, d.name, s.salary}
+    "###;
+    let result = compile(prql).unwrap();
+    assert_display_snapshot!(result, @r###"
+    WITH table_0 AS (
+      SELECT
+        employees.emp_no,
+        d.name
+      FROM
+        employees
+        JOIN department AS d ON employees.dept_no = d.dept_no
+      LIMIT
+        10
+    )
+    SELECT
+      table_0.emp_no,
+      table_0.name,
+      s.salary
+    FROM
+      table_0
+      JOIN salaries AS s ON table_0.emp_no = s.emp_no
+    "###);
+
+    let prql = r###"
+    from e=employees
+    take 10
+    join salaries (==emp_no)
+    select {e.*, salaries.salary}
+    "###;
+    let result = compile(prql).unwrap();
+    assert_display_snapshot!(result, @r###"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        employees AS e
+      LIMIT
+        10
+    )
+    SELECT
+      table_0.*,
+      salaries.salary
+    FROM
+      table_0
+      JOIN salaries ON table_0.emp_no = salaries.emp_no
+    "###);
+}
+
+#[test]
+fn test_table_alias() {
+    // Alias on from
+    let query = r###"
+        from e = employees
+        join salaries side:left (salaries.emp_no == e.emp_no)
+        group {e.emp_no} (
+            aggregate {
+                emp_salary = average salaries.salary
+            }
+        )
+        select {emp_no, emp_salary}
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      e.emp_no,
+      AVG(salaries.salary) AS emp_salary
+    FROM
+      employees AS e
+      LEFT JOIN salaries ON salaries.emp_no = e.emp_no
+    GROUP BY
+      e.emp_no
+    "###);
+
+    assert_display_snapshot!((compile(r###"
+    from e=employees
+    select e.first_name
+    filter e.first_name == "Fred"
+    "###).unwrap()), @r###"
+    SELECT
+      first_name
+    FROM
+      employees AS e
+    WHERE
+      first_name = 'Fred'
+    "###);
+}
+
+#[test]
+fn test_targets() {
+    // Generic
+    let query = r###"
+    prql target:sql.generic
+    from Employees
+    select {FirstName, `last name`}
+    take 3
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      "FirstName",
+      "last name"
+    FROM
+      "Employees"
+    LIMIT
+      3
+    "###);
+
+    // SQL server
+    let query = r###"
+    prql target:sql.mssql
+    from Employees
+    select {FirstName, `last name`}
+    take 3
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      TOP (3) "FirstName",
+      "last name"
+    FROM
+      "Employees"
+    "###);
+
+    // MySQL
+    let query = r###"
+    prql target:sql.mysql
+    from Employees
+    select {FirstName, `last name`}
+    take 3
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      `FirstName`,
+      `last name`
+    FROM
+      `Employees`
+    LIMIT
+      3
+    "###);
+}
+
+#[test]
+fn test_mssql_ranged_take() {
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.mssql
+    from employees
+    sort age
+    take 5..10
+    "###).unwrap()), @r###"
+    SELECT
+      *
+    FROM
+      employees
+    ORDER BY
+      age OFFSET 4 ROWS
+    FETCH FIRST
+      6 ROWS ONLY
+    "###);
+
+    assert_display_snapshot!((compile(r###"
+    prql target:sql.mssql
+    from employees
+    take 5..10
+    "###).unwrap_err()), @r###"
+    Error: The dialect mssql can only skip rows of a sorted relation
+    ↳ Hint: add a `sort` before the `take`
+    "###);
+}
+
+#[test]
+fn test_quoting_style() {
+    let query = r###"
+    from Employees
+    select {FirstName, `last name`, `from`}
+    "###;
+
+    let options = Options::default().no_signature();
+    assert_display_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    SELECT
+      "FirstName",
+      "last name",
+      "from"
+    FROM
+      "Employees"
+    "###);
+
+    let options = options.with_quoting_style(sql::QuotingStyle::Minimal);
+    assert_display_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    SELECT
+      FirstName,
+      "last name",
+      "from"
+    FROM
+      Employees
+    "###);
+}
+
+#[test]
+fn test_ident_case_folding() {
+    // Postgres folds unquoted identifiers to lowercase, so any uppercase
+    // letter needs quoting to be preserved
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.postgres
+    from Employees
+    select {FirstName, LAST_NAME, salary}
+    "###).unwrap(), @r###"
+    SELECT
+      "FirstName",
+      "LAST_NAME",
+      salary
+    FROM
+      "Employees"
+    "###);
+
+    // Oracle and Snowflake fold to uppercase, so uppercase identifiers can
+    // stay bare
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.oracle
+    from Employees
+    select {FirstName, LAST_NAME, salary}
+    "###).unwrap(), @r###"
+    SELECT
+      "FirstName",
+      LAST_NAME,
+      salary
+    FROM
+      "Employees"
+    "###);
+
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.snowflake
+    from EMPLOYEES
+    select {FirstName, LAST_NAME, salary}
+    "###).unwrap(), @r###"
+    SELECT
+      "FirstName",
+      LAST_NAME,
+      salary
+    FROM
+      EMPLOYEES
+    "###);
+}
+
+#[test]
+fn test_target_redshift() {
+    let query = r###"
+    prql target:sql.redshift
+
+    from employees
+    group department (
+        aggregate {names = concat_array name}
+    )
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      department,
+      COALESCE(LISTAGG(name, ''), '') AS names
+    FROM
+      employees
+    GROUP BY
+      department
+    "###);
+
+    let query = r###"
+    prql target:sql.redshift
+
+    from projects
+    derive first_check_in = start + 10days
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      start + INTERVAL '10' DAY AS first_check_in
+    FROM
+      projects
+    "###);
+
+    let options = Options::default()
+        .no_signature()
+        .with_target(Target::Sql(Some(sql::Dialect::Redshift)))
+        .with_explain(sql::Explain::Analyze);
+    assert_display_snapshot!(crate::compile("from projects", &options).unwrap_err(), @r###"
+    Error: The dialect redshift does not support EXPLAIN ANALYZE
+    "###);
+}
+
+#[test]
+fn test_target_trino() {
+    let query = r###"
+    prql target:sql.trino
+
+    from employees
+    select {first_name, `last name`}
+    take 10
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      first_name,
+      "last name"
+    FROM
+      employees
+    LIMIT
+      10
+    "###);
+
+    // the offset precedes the number of rows
+    let query = r###"
+    prql target:sql.trino
+
+    from employees
+    sort age
+    take 5..10
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *
+    FROM
+      employees
+    ORDER BY
+      age OFFSET 4 ROWS
+    FETCH FIRST
+      6 ROWS ONLY
+    "###);
+
+    // intervals have a single unit
+    let query = r###"
+    prql target:sql.trino
+
+    from projects
+    derive {
+        first_check_in = start + 10days,
+        deadline = start + 1years + 6months,
+    }
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      start + INTERVAL '10' DAY AS first_check_in,
+      start + INTERVAL '1' YEAR + INTERVAL '6' MONTH AS deadline
+    FROM
+      projects
+    "###);
+}
+
+#[test]
+fn test_target_athena() {
+    let query = r###"
+    prql target:sql.athena
+
+    from events
+    filter (event_name ~= "^page_")
+    select {`event name` = event_name, received_at}
+    take 10
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      event_name AS "event name",
+      received_at
+    FROM
+      events
+    WHERE
+      REGEXP_LIKE(event_name, '^page_')
+    LIMIT
+      10
+    "###);
+
+    // recursive CTEs are not supported
+    let query = r###"
+    prql target:sql.athena
+
+    from [{n = 1}]
+    loop (select n = n+1 | filter n<5)
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap_err()), @r###"
+    Error: `loop` is not supported for dialect athena, because it does not support recursive CTEs
+    "###);
+}
+
+#[test]
+fn test_target_clickhouse() {
+    let query = r###"
+    prql target:sql.clickhouse
+
+    from github_json
+    derive {event_type_dotted = `event.type`}
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      `event.type` AS event_type_dotted
+    FROM
+      github_json
+    "###);
+}
+
+#[test]
+fn test_ident_escaping() {
+    // Generic
+    let query = r###"
+    from `anim"ls`
+    derive {`čebela` = BeeName, medved = `bear's_name`}
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      "BeeName" AS "čebela",
+      "bear's_name" AS medved
+    FROM
+      "anim""ls"
+    "###);
+
+    // MySQL
+    let query = r###"
+    prql target:sql.mysql
+
+    from `anim"ls`
+    derive {`čebela` = BeeName, medved = `bear's_name`}
+    "###;
+
+    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    SELECT
+      *,
+      `BeeName` AS `čebela`,
+      `bear's_name` AS medved
+    FROM
+      `anim"ls`
+    "###);
+}
+
+#[test]
+fn test_literal() {
+    let query = r###"
+    from employees
+    derive {always_true = true}
+    "###;
+
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    SELECT
+      *,
+      true AS always_true
+    FROM
+      employees
+    "###
+    );
+}
+
+#[test]
+fn test_same_column_names() {
+    // #820
+    let query = r###"
+let x = (
+from x_table
+select only_in_x = foo
+)
+
+let y = (
+from y_table
+select foo
+)
+
+from x
+join y (foo == only_in_x)
+"###;
+
+    assert_display_snapshot!(compile(query).unwrap(),
+        @r###"
+    WITH x AS (
+      SELECT
+        foo AS only_in_x
+      FROM
+        x_table
+    ),
+    y AS (
+      SELECT
+        foo
+      FROM
+        y_table
+    )
+    SELECT
+      x.only_in_x,
+      y.foo
+    FROM
+      x
+      JOIN y ON y.foo = x.only_in_x
+    "###
+    );
+}
+
+#[test]
+fn test_double_aggregate() {
+    // #941
+    let query = r###"
+    from numbers
+    group {type} (
+        aggregate {
+            total_amt = sum amount,
+        }
+        aggregate {
+            max amount
+        }
+    )
+    "###;
+
+    compile(query).unwrap_err();
+
+    let query = r###"
+    from numbers
+    group {`type`} (
+        aggregate {
+            total_amt = sum amount,
+            max amount
+        }
+    )
+    "###;
+
+    assert_display_snapshot!(compile(query).unwrap(),
+        @r###"
+    SELECT
+      type,
+      COALESCE(SUM(amount), 0) AS total_amt,
+      MAX(amount)
+    FROM
+      numbers
+    GROUP BY
+      type
+    "###
+    );
+}
+
+#[test]
+fn test_normalize_names() {
+    assert_display_snapshot!(compile(r###"
+    from employees
+    select {FirstName, lastName, `Employee ID`, HTTPStatus, salary_usd}
+    normalize_names case:snake
+    "###).unwrap(), @r###"
+    SELECT
+      "FirstName" AS first_name,
+      "lastName" AS last_name,
+      "Employee ID" AS employee_id,
+      "HTTPStatus" AS http_status,
+      salary_usd
+    FROM
+      employees
+    "###);
+
+    assert_display_snapshot!(compile(r###"
+    from employees
+    select {first_name, `Employee ID`}
+    normalize_names case:camel
+    "###).unwrap(), @r###"
+    SELECT
+      first_name AS "firstName",
+      "Employee ID" AS "employeeId"
+    FROM
+      employees
+    "###);
+
+    // columns of `employees` are not known
+    assert_display_snapshot!(compile(r###"
+    from employees
+    normalize_names case:upper
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:2:10]
+       │
+     2 │     from employees
+       │          ────┬────
+       │              ╰────── normalize_names requires all columns of the relation to be known
+       │
+       │ Help: select the columns before normalizing their names
+    ───╯
+    "###);
+}
+
+#[test]
+fn test_rename_all() {
+    assert_display_snapshot!(compile(r###"
+    let e = (from employees | select {id, name})
+
+    from e
+    rename_all prefix:"l_"
+    "###).unwrap(), @r###"
+    WITH e AS (
+      SELECT
+        id,
+        name
+      FROM
+        employees
+    )
+    SELECT
+      id AS l_id,
+      name AS l_name
+    FROM
+      e
+    "###);
+
+    // columns of `employees` are not known
+    assert_display_snapshot!(compile(r###"
+    from employees
+    rename_all prefix:"l_"
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:2:10]
+       │
+     2 │     from employees
+       │          ────┬────
+       │              ╰────── rename_all requires all columns of the relation to be known
+       │
+       │ Help: select the columns before renaming them
+    ───╯
+    "###);
+}
+
+#[test]
+fn test_casting() {
+    assert_display_snapshot!(compile(r###"
+    from x
+    select {a}
+    derive {
+        c = (a | as int) / 10
+    }
+    "###).unwrap(),
+        @r###"
+    SELECT
+      a,
+      (CAST(a AS int) / 10) AS c
+    FROM
+      x
+    "###
+    );
+
+    assert_display_snapshot!(compile(r###"
+    from x
+    select {id, amount<decimal>, total = price<float>}
+    "###).unwrap(),
+        @r###"
+    SELECT
+      id,
+      CAST(amount AS decimal) AS amount,
+      CAST(price AS float) AS total
+    FROM
+      x
+    "###
+    );
+}
+
+#[test]
+fn test_safe_cast() {
+    let query = r###"
+    from x
+    select {id, amount = (amount | as int64), total = price<float64>}
+    "###;
+
+    let options = |dialect| {
+        Options::default()
+            .no_signature()
+            .with_target(Target::Sql(Some(dialect)))
+            .with_safe_cast(true)
+    };
+
+    assert_display_snapshot!(crate::compile(query, &options(sql::Dialect::BigQuery)).unwrap(), @r###"
+    SELECT
+      id,
+      SAFE_CAST(amount AS int64) AS amount,
+      SAFE_CAST(price AS float64) AS total
+    FROM
+      x
+    "###);
+
+    // dialects without safe casts emit plain casts
+    assert_display_snapshot!(crate::compile(query, &options(sql::Dialect::Postgres)).unwrap(), @r###"
+    SELECT
+      id,
+      CAST(amount AS int64) AS amount,
+      CAST(price AS float64) AS total
+    FROM
+      x
+    "###);
+}
+
+#[test]
+fn test_type_annotation() {
+    assert_display_snapshot!(compile(r###"
+    from orders
+    derive {amount<float> = price * qty}
+    "###).unwrap(),
+        @r###"
+    SELECT
+      *,
+      price * qty AS amount
+    FROM
+      orders
+    "###
+    );
+
+    assert_display_snapshot!(compile(r###"
+    from orders
+    derive {label<float> = f"{price} USD"}
+    "###).unwrap_err(),
+        @r###"
+    Error:
+       ╭─[:3:28]
+       │
+     3 │     derive {label<float> = f"{price} USD"}
+       │                            ───────┬──────
+       │                                   ╰──────── expected type `float`, but found type `text`
+    ───╯
+    "###
+    );
+}
+
+#[test]
+fn test_toposort() {
+    // #1183
+
+    assert_display_snapshot!(compile(r###"
+    let b = (
+        from somesource
+    )
+
+    let a = (
+        from b
+    )
+
+    from b
+    "###).unwrap(),
+        @r###"
+    WITH b AS (
+      SELECT
+        *
+      FROM
+        somesource
+    )
+    SELECT
+      *
+    FROM
+      b
+    "###
+    );
+
+    // CTEs follow dependencies, regardless of the order of declarations
+    let query = r###"
+    let c = (from b | take 10)
+    let b = (from a | filter age > 20)
+    let a = (from employees | filter salary > 10)
+
+    from c
+    "###;
+    let sql = compile(query).unwrap();
+    assert_display_snapshot!(sql,
+        @r###"
+    WITH a AS (
+      SELECT
+        *
+      FROM
+        employees
+      WHERE
+        salary > 10
+    ),
+    b AS (
+      SELECT
+        *
+      FROM
+        a
+      WHERE
+        age > 20
+    ),
+    c AS (
+      SELECT
+        *
+      FROM
+        b
+      LIMIT
+        10
+    )
+    SELECT
+      *
+    FROM
+      c
+    "###
+    );
+
+    // ... and the order is stable
+    for _ in 0..10 {
+        assert_eq!(compile(query).unwrap(), sql);
+    }
+}
+
+#[test]
+fn test_inline_tables() {
+    assert_display_snapshot!(compile(r###"
+    (
+        from employees
+        select {emp_id, name, surname, `type`, amount}
+    )
+    join s = (from salaries | select {emp_id, salary}) (==emp_id)
+    "###).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        emp_id,
+        salary
+      FROM
+        salaries
+    )
+    SELECT
+      employees.emp_id,
+      employees.name,
+      employees.surname,
+      employees.type,
+      employees.amount,
+      table_0.emp_id,
+      table_0.salary
+    FROM
+      employees
+      JOIN table_0 ON employees.emp_id = table_0.emp_id
+    "###
+    );
+}
+
+#[test]
+fn test_filter_and_select_unchanged_alias() {
+    // #1185
+
+    assert_display_snapshot!(compile(r###"
+    from account
+    filter account.name != null
+    select {name = account.name}
+    "###).unwrap(),
+        @r###"
+    SELECT
+      name
+    FROM
+      account
+    WHERE
+      name IS NOT NULL
+    "###
+    );
+}
+
+#[test]
+fn test_filter_and_select_changed_alias() {
+    // #1185
+    assert_display_snapshot!(compile(r###"
+    from account
+    filter account.name != null
+    select {renamed_name = account.name}
+    "###).unwrap(),
+        @r###"
+    SELECT
+      name AS renamed_name
+    FROM
+      account
+    WHERE
+      name IS NOT NULL
+    "###
+    );
+
+    // #1207
+    assert_display_snapshot!(compile(r###"
+    from x
+    filter name != "Bob"
+    select name = name ?? "Default"
+    "###).unwrap(),
+        @r###"
+    SELECT
+      COALESCE(name, 'Default') AS name
+    FROM
+      x
+    WHERE
+      name <> 'Bob'
+    "###
+    );
+}
+
+#[test]
+fn test_unused_alias() {
+    // #1308
+    assert_display_snapshot!(compile(r###"
+    from account
+    select n = {account.name}
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:3:16]
+       │
+     3 │     select n = {account.name}
+       │                ───────┬──────
+       │                       ╰──────── unexpected assign to `n`
+       │
+       │ Help: move assign into the tuple: `[n = ...]`
+    ───╯
+    "###)
+}
+
+#[test]
+fn test_table_s_string() {
+    assert_display_snapshot!(compile(r###"
+    let main <relation> = s"SELECT DISTINCT ON first_name, age FROM employees ORDER BY age ASC"
+    "###).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        DISTINCT ON first_name,
+        age
+      FROM
+        employees
+      ORDER BY
+        age ASC
     )
-    from a
-    join b (==country)
-    select {name, salary, average_country_salary}
-"###;
+    SELECT
+      *
+    FROM
+      table_0
+    "###
+    );
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    assert_display_snapshot!(compile(r###"
+    from s"""
+        SELECT DISTINCT ON first_name, id, age FROM employees ORDER BY age ASC
+    """
+    join s = s"SELECT * FROM salaries" (==id)
+    "###).unwrap(),
+        @r###"
     WITH table_0 AS (
       SELECT
-        country
+        DISTINCT ON first_name,
+        id,
+        age
       FROM
         employees
-      LIMIT
-        50
-    ), a AS (
+      ORDER BY
+        age ASC
+    ),
+    table_1 AS (
       SELECT
-        country,
-        count(*)
+        *
       FROM
-        table_0
-      GROUP BY
-        country
+        salaries
     )
     SELECT
-      b.name,
-      b.salary,
-      b.average_country_salary
+      table_0.*,
+      table_1.*
     FROM
-      a
-      JOIN b ON a.country = b.country
-    "###);
-}
+      table_0
+      JOIN table_1 ON table_0.id = table_1.id
+    "###
+    );
 
-#[test]
-fn test_table_names_between_splits() {
-    let prql = r###"
-    from employees
-    join d=department (==dept_no)
-    take 10
-    derive emp_no = employees.emp_no
-    join s=salaries (==emp_no)
-    select {employees.emp_no, d.name, s.salary}
-    "###;
-    let result = compile(prql).unwrap();
-    assert_display_snapshot!(result, @r###"
+    assert_display_snapshot!(compile(r###"
+    from s"""SELECT * FROM employees"""
+    filter country == "USA"
+    "###).unwrap(),
+        @r###"
     WITH table_0 AS (
       SELECT
-        employees.emp_no,
-        d.name
+        *
       FROM
         employees
-        JOIN department AS d ON employees.dept_no = d.dept_no
-      LIMIT
-        10
     )
     SELECT
-      table_0.emp_no,
-      table_0.name,
-      s.salary
+      *
     FROM
       table_0
-      JOIN salaries AS s ON table_0.emp_no = s.emp_no
-    "###);
+    WHERE
+      country = 'USA'
+    "###
+    );
 
-    let prql = r###"
-    from e=employees
-    take 10
-    join salaries (==emp_no)
-    select {e.*, salaries.salary}
-    "###;
-    let result = compile(prql).unwrap();
-    assert_display_snapshot!(result, @r###"
+    assert_display_snapshot!(compile(r###"
+    from e=s"""SELECT * FROM employees"""
+    filter e.country == "USA"
+    "###).unwrap(),
+        @r###"
     WITH table_0 AS (
       SELECT
         *
       FROM
-        employees AS e
-      LIMIT
-        10
+        employees
     )
     SELECT
-      table_0.*,
-      salaries.salary
+      *
     FROM
       table_0
-      JOIN salaries ON table_0.emp_no = salaries.emp_no
-    "###);
-}
+    WHERE
+      country = 'USA'
+    "###
+    );
 
-#[test]
-fn test_table_alias() {
-    // Alias on from
-    let query = r###"
-        from e = employees
-        join salaries side:left (salaries.emp_no == e.emp_no)
-        group {e.emp_no} (
-            aggregate {
-                emp_salary = average salaries.salary
-            }
-        )
-        select {emp_no, emp_salary}
-    "###;
+    assert_display_snapshot!(compile(r###"
+    let weeks_between = start end -> s"SELECT generate_series({start}, {end}, '1 week') as date"
+    let current_week = -> s"date(date_trunc('week', current_date))"
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    weeks_between @2022-06-03 (current_week + 4)
+    "###).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        generate_series(
+          DATE '2022-06-03',
+          date(date_trunc('week', current_date)) + 4,
+          '1 week'
+        ) as date
+    )
     SELECT
-      e.emp_no,
-      AVG(salaries.salary) AS emp_salary
+      *
     FROM
-      employees AS e
-      LEFT JOIN salaries ON salaries.emp_no = e.emp_no
-    GROUP BY
-      e.emp_no
-    "###);
+      table_0
+    "###
+    );
 
-    assert_display_snapshot!((compile(r###"
-    from e=employees
-    select e.first_name
-    filter e.first_name == "Fred"
-    "###).unwrap()), @r###"
+    assert_display_snapshot!(compile(r###"
+    s"SELECT * FROM {default_db.x}"
+    "###).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        x
+    )
     SELECT
-      first_name
+      *
     FROM
-      employees AS e
-    WHERE
-      first_name = 'Fred'
-    "###);
+      table_0
+    "###
+    );
 }
 
 #[test]
-fn test_targets() {
-    // Generic
-    let query = r###"
-    prql target:sql.generic
-    from Employees
-    select {FirstName, `last name`}
-    take 3
-    "###;
+fn test_direct_table_references() {
+    assert_display_snapshot!(compile(
+        r###"
+    from x
+    select s"{x}.field"
+    "###,
+    )
+    .unwrap_err(), @r###"
+    Error:
+       ╭─[:3:14]
+       │
+     3 │     select s"{x}.field"
+       │              ─┬─
+       │               ╰─── table instance cannot be referenced directly
+       │
+       │ Help: did you forget to specify the column name?
+    ───╯
+    "###);
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
-    SELECT
-      "FirstName",
-      "last name"
-    FROM
-      "Employees"
-    LIMIT
-      3
+    assert_display_snapshot!(compile(
+        r###"
+    from x
+    select x
+    "###,
+    )
+    .unwrap_err(), @r###"
+    Error:
+       ╭─[:3:12]
+       │
+     3 │     select x
+       │            ┬
+       │            ╰── table instance cannot be referenced directly
+       │
+       │ Help: did you forget to specify the column name?
+    ───╯
     "###);
+}
 
-    // SQL server
-    let query = r###"
-    prql target:sql.mssql
-    from Employees
-    select {FirstName, `last name`}
-    take 3
-    "###;
-
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+#[test]
+fn test_name_shadowing() {
+    assert_display_snapshot!(compile(
+        r###"
+    from x
+    select {a, a, a = a + 1}
+    "###).unwrap(),
+        @r###"
     SELECT
-      TOP (3) "FirstName",
-      "last name"
+      a AS _expr_0,
+      a AS _expr_0,
+      a + 1 AS a
     FROM
-      "Employees"
-    "###);
-
-    // MySQL
-    let query = r###"
-    prql target:sql.mysql
-    from Employees
-    select {FirstName, `last name`}
-    take 3
-    "###;
+      x
+    "###
+    );
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    assert_display_snapshot!(compile(
+        r###"
+    from x
+    select a
+    derive a
+    derive a = a + 1
+    derive a = a + 2
+    "###).unwrap(),
+        @r###"
     SELECT
-      `FirstName`,
-      `last name`
+      a AS _expr_0,
+      a AS _expr_0,
+      a + 1,
+      a + 1 + 2 AS a
     FROM
-      `Employees`
-    LIMIT
-      3
-    "###);
+      x
+    "###
+    );
 }
 
 #[test]
-fn test_target_clickhouse() {
-    let query = r###"
-    prql target:sql.clickhouse
+fn test_group_all() {
+    assert_display_snapshot!(compile(
+        r###"
+    prql target:sql.sqlite
 
-    from github_json
-    derive {event_type_dotted = `event.type`}
-    "###;
+    from a=albums
+    group a.* (aggregate {count this})
+        "###).unwrap_err(), @r###"
+    Error: Target dialect does not support * in this position.
+    "###);
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
-    SELECT
-      *,
-      `event.type` AS event_type_dotted
-    FROM
-      github_json
+    assert_display_snapshot!(compile(
+        r###"
+    from e=albums
+    group !{genre_id} (aggregate {count this})
+        "###).unwrap_err(), @r###"
+    Error: Excluding columns not supported as this position
     "###);
 }
 
 #[test]
-fn test_ident_escaping() {
-    // Generic
+fn test_bool_aggregates() {
     let query = r###"
-    from `anim"ls`
-    derive {`čebela` = BeeName, medved = `bear's_name`}
+    from invoices
+    group {customer_id} (
+        aggregate {has_error = any is_error, all_paid = all paid}
+    )
     "###;
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
-    SELECT
-      *,
-      "BeeName" AS "čebela",
-      "bear's_name" AS medved
+    assert_display_snapshot!(compile(query).unwrap(), @r###"
+    SELECT
+      customer_id,
+      COALESCE(
+        MAX(
+          CASE
+            WHEN is_error THEN 1
+            WHEN NOT is_error THEN 0
+          END
+        ) <> 0,
+        FALSE
+      ) AS has_error,
+      COALESCE(
+        MIN(
+          CASE
+            WHEN paid THEN 1
+            WHEN NOT paid THEN 0
+          END
+        ) = 1,
+        TRUE
+      ) AS all_paid
     FROM
-      "anim""ls"
+      invoices
+    GROUP BY
+      customer_id
     "###);
 
-    // MySQL
-    let query = r###"
-    prql target:sql.mysql
-
-    from `anim"ls`
-    derive {`čebela` = BeeName, medved = `bear's_name`}
-    "###;
+    // the emulation is a boolean, so it can be filtered on
+    assert_display_snapshot!(compile(r###"
+    from invoices
+    group {customer_id} (
+        aggregate {all_paid = all paid}
+    )
+    filter all_paid
+    "###).unwrap(), @r###"
+    SELECT
+      customer_id,
+      COALESCE(
+        MIN(
+          CASE
+            WHEN paid THEN 1
+            WHEN NOT paid THEN 0
+          END
+        ) = 1,
+        TRUE
+      ) AS all_paid
+    FROM
+      invoices
+    GROUP BY
+      customer_id
+    HAVING
+      COALESCE(
+        MIN(
+          CASE
+            WHEN paid THEN 1
+            WHEN NOT paid THEN 0
+          END
+        ) = 1,
+        TRUE
+      )
+    "###);
 
-    assert_display_snapshot!((compile(query).unwrap()), @r###"
+    assert_display_snapshot!(compile(&format!("prql target:sql.postgres\n{query}")).unwrap(), @r###"
     SELECT
-      *,
-      `BeeName` AS `čebela`,
-      `bear's_name` AS medved
+      customer_id,
+      COALESCE(BOOL_OR(is_error), FALSE) AS has_error,
+      COALESCE(BOOL_AND(paid), TRUE) AS all_paid
     FROM
-      `anim"ls`
+      invoices
+    GROUP BY
+      customer_id
     "###);
+
+    // the emulation over 0 and 1 is rejected by strict queries
+    let err = compile(&format!("prql target:sql.mssql strict:true\n{query}"))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("is not supported for dialect mssql"));
+    assert!(err.contains("remove `strict:true` to emulate it"));
 }
 
 #[test]
-fn test_literal() {
-    let query = r###"
-    from employees
-    derive {always_true = true}
-    "###;
-
-    let sql = compile(query).unwrap();
-    assert_display_snapshot!(sql,
-        @r###"
+fn test_group_empty_key() {
+    // an empty key aggregates the whole relation into a single row
+    assert_display_snapshot!(compile(
+        r###"
+    from tracks
+    group {} (aggregate {sum length})
+        "###).unwrap(), @r###"
     SELECT
-      *,
-      true AS always_true
+      SUM(length)
     FROM
-      employees
-    "###
+      tracks
+    "###);
+
+    assert_eq!(
+        compile("from tracks | group {} (aggregate {total = sum length})").unwrap(),
+        compile("from tracks | aggregate {total = sum length}").unwrap()
     );
 }
 
 #[test]
-fn test_same_column_names() {
-    // #820
-    let query = r###"
-let x = (
-from x_table
-select only_in_x = foo
-)
-
-let y = (
-from y_table
-select foo
-)
-
-from x
-join y (foo == only_in_x)
-"###;
-
-    assert_display_snapshot!(compile(query).unwrap(),
+fn test_output_column_deduplication() {
+    // #1249
+    assert_display_snapshot!(compile(
+        r###"
+    from report
+    derive r = s"RANK() OVER ()"
+    filter r == 1
+        "###).unwrap(),
         @r###"
-    WITH x AS (
-      SELECT
-        foo AS only_in_x
-      FROM
-        x_table
-    ),
-    y AS (
+    WITH table_0 AS (
       SELECT
-        foo
+        *,
+        RANK() OVER () AS r
       FROM
-        y_table
+        report
     )
     SELECT
-      x.only_in_x,
-      y.foo
+      *
     FROM
-      x
-      JOIN y ON y.foo = x.only_in_x
+      table_0
+    WHERE
+      r = 1
     "###
     );
 }
 
 #[test]
-fn test_double_aggregate() {
-    // #941
-    let query = r###"
-    from numbers
-    group {type} (
-        aggregate {
-            total_amt = sum amount,
-        }
-        aggregate {
-            max amount
-        }
-    )
-    "###;
-
-    compile(query).unwrap_err();
-
-    let query = r###"
-    from numbers
-    group {`type`} (
-        aggregate {
-            total_amt = sum amount,
-            max amount
-        }
-    )
-    "###;
+fn test_case() {
+    assert_display_snapshot!(compile(
+        r###"
+    from employees
+    derive display_name = case {
+        nickname != null => nickname,
+        true => f'{first_name} {last_name}'
+    }
+        "###).unwrap(),
+        @r###"
+    SELECT
+      *,
+      CASE
+        WHEN nickname IS NOT NULL THEN nickname
+        ELSE CONCAT(first_name, ' ', last_name)
+      END AS display_name
+    FROM
+      employees
+    "###
+    );
 
-    assert_display_snapshot!(compile(query).unwrap(),
+    assert_display_snapshot!(compile(
+        r###"
+    from employees
+    derive display_name = case {
+        nickname != null => nickname,
+        first_name != null => f'{first_name} {last_name}'
+    }
+        "###).unwrap(),
         @r###"
     SELECT
-      type,
-      COALESCE(SUM(amount), 0) AS total_amt,
-      MAX(amount)
+      *,
+      CASE
+        WHEN nickname IS NOT NULL THEN nickname
+        WHEN first_name IS NOT NULL THEN CONCAT(first_name, ' ', last_name)
+        ELSE NULL
+      END AS display_name
     FROM
-      numbers
-    GROUP BY
-      type
+      employees
     "###
     );
-}
 
-#[test]
-fn test_casting() {
-    assert_display_snapshot!(compile(r###"
-    from x
-    select {a}
-    derive {
-        c = (a | as int) / 10
+    assert_display_snapshot!(compile(
+        r###"
+    from tracks
+    select category = case {
+        length > avg_length => 'long'
     }
-    "###).unwrap(),
+    group category (aggregate {count this})
+        "###).unwrap(),
         @r###"
+    WITH table_0 AS (
+      SELECT
+        CASE
+          WHEN length > avg_length THEN 'long'
+          ELSE NULL
+        END AS category,
+        length,
+        avg_length
+      FROM
+        tracks
+    )
     SELECT
-      a,
-      (CAST(a AS int) / 10) AS c
+      category,
+      COUNT(*)
     FROM
-      x
+      table_0
+    GROUP BY
+      category
     "###
     );
 }
 
 #[test]
-fn test_toposort() {
-    // #1183
+fn test_sql_options() {
+    let options = Options::default();
+    let sql = crate::compile("from x", &options).unwrap();
 
-    assert_display_snapshot!(compile(r###"
-    let b = (
-        from somesource
-    )
+    assert!(sql.contains('\n'));
+    assert!(sql.contains("-- Generated by"));
 
-    let a = (
-        from b
+    let options = Options::default().no_signature().no_format();
+    let sql = crate::compile("from x", &options).unwrap();
+
+    assert!(!sql.contains('\n'));
+    assert!(!sql.contains("-- Generated by"));
+}
+
+#[test]
+fn test_inline_single_use_ctes() {
+    let query = r#"
+    let rich = (
+        from employees
+        filter salary > 100000
     )
 
-    from b
-    "###).unwrap(),
-        @r###"
-    WITH b AS (
+    from rich
+    select {name, salary}
+    "#;
+
+    let options = Options::default().no_signature();
+    assert_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    WITH rich AS (
       SELECT
         *
       FROM
-        somesource
+        employees
+      WHERE
+        salary > 100000
     )
     SELECT
-      *
+      name,
+      salary
     FROM
-      b
-    "###
-    );
+      rich
+    "###);
+
+    let options = options.with_inline_single_use_ctes(true);
+    assert_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    SELECT
+      name,
+      salary
+    FROM
+      (
+        SELECT
+          *
+        FROM
+          employees
+        WHERE
+          salary > 100000
+      ) AS rich
+    "###);
+
+    // relations that are referenced more than once are still CTEs
+    let query = "let rich = (from employees | filter salary > 100000)\nfrom rich\nappend rich";
+    assert!(crate::compile(query, &options)
+        .unwrap()
+        .starts_with("WITH rich AS ("));
 }
 
 #[test]
-fn test_inline_tables() {
-    assert_display_snapshot!(compile(r###"
-    (
-        from employees
-        select {emp_id, name, surname, `type`, amount}
-    )
-    join s = (from salaries | select {emp_id, salary}) (==emp_id)
-    "###).unwrap(),
-        @r###"
-    WITH table_0 AS (
+fn test_explain_splits() {
+    let query = r#"
+    from employees
+    take 10
+    aggregate {total = sum salary}
+    "#;
+
+    let options = Options::default().no_signature().with_explain_splits(true);
+    assert_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    WITH -- boundary: aggregate after take
+    table_0 AS (
       SELECT
-        emp_id,
         salary
       FROM
-        salaries
+        employees
+      LIMIT
+        10
     )
     SELECT
-      employees.emp_id,
-      employees.name,
-      employees.surname,
-      employees.type,
-      employees.amount,
-      table_0.emp_id,
-      table_0.salary
+      COALESCE(SUM(salary), 0) AS total
     FROM
-      employees
-      JOIN table_0 ON employees.emp_id = table_0.emp_id
-    "###
-    );
+      table_0
+    "###);
+
+    // without the option, there are no comments
+    let options = Options::default().no_signature();
+    assert!(!crate::compile(query, &options).unwrap().contains("--"));
 }
 
 #[test]
-fn test_filter_and_select_unchanged_alias() {
-    // #1185
+fn test_wrap_in_parens() {
+    let query = r#"
+    let rich = (from employees | filter salary > 100000)
+    from rich
+    select {name, salary}
+    "#;
 
-    assert_display_snapshot!(compile(r###"
-    from account
-    filter account.name != null
-    select {name = account.name}
-    "###).unwrap(),
-        @r###"
+    // the WITH clause is wrapped as well
+    let options = Options::default()
+        .no_signature()
+        .no_format()
+        .with_wrap_in_parens(true);
+    assert_eq!(
+        crate::compile(query, &options).unwrap(),
+        "(WITH rich AS (SELECT * FROM employees WHERE salary > 100000) SELECT name, salary FROM rich)"
+    );
+
+    // formatted SQL has no trailing new line
+    let options = options.with_format(true);
+    assert_snapshot!(crate::compile(query, &options).unwrap(), @r###"
+    (WITH rich AS (
+      SELECT
+        *
+      FROM
+        employees
+      WHERE
+        salary > 100000
+    )
     SELECT
-      name
+      name,
+      salary
     FROM
-      account
-    WHERE
-      name IS NOT NULL
-    "###
+      rich)
+    "###);
+
+    // the signature comment does not comment out the closing parenthesis
+    let options = options.with_signature_comment(true);
+    assert!(crate::compile(query, &options)
+        .unwrap()
+        .ends_with("(https://prql-lang.org)\n)"));
+}
+
+#[test]
+fn test_signature_comment() {
+    let sql = crate::compile("prql target:sql.sqlite\nfrom x", &Options::default()).unwrap();
+    let signature = sql.lines().last().unwrap();
+
+    assert_eq!(
+        signature,
+        format!(
+            "-- Generated by PRQL compiler version:{} target:sql.sqlite (https://prql-lang.org)",
+            *crate::COMPILER_VERSION
+        )
     );
 }
 
 #[test]
-fn test_filter_and_select_changed_alias() {
-    // #1185
-    assert_display_snapshot!(compile(r###"
-    from account
-    filter account.name != null
-    select {renamed_name = account.name}
-    "###).unwrap(),
-        @r###"
+fn test_explain() {
+    let query = r#"
+    let newest = (from employees | sort {-hired_at} | take 10)
+    from newest
+    select {name}
+    "#;
+    let options = Options::default()
+        .no_signature()
+        .with_target(Target::Sql(Some(sql::Dialect::Postgres)));
+
+    // EXPLAIN goes before the CTEs
+    let analyze = options.clone().with_explain(sql::Explain::Analyze);
+    assert_snapshot!(crate::compile(query, &analyze).unwrap(), @r###"
+    EXPLAIN ANALYZE
+    WITH newest AS (
+      SELECT
+        *
+      FROM
+        employees
+      ORDER BY
+        hired_at DESC
+      LIMIT
+        10
+    )
     SELECT
-      name AS renamed_name
+      name
     FROM
-      account
-    WHERE
-      name IS NOT NULL
-    "###
-    );
+      newest
+    "###);
 
-    // #1207
-    assert_display_snapshot!(compile(r###"
+    let plan = options.with_explain(sql::Explain::Plan).no_format();
+    assert_snapshot!(crate::compile("from employees", &plan).unwrap(), @"EXPLAIN SELECT * FROM employees");
+
+    let options = Options::default()
+        .no_signature()
+        .with_target(Target::Sql(Some(sql::Dialect::MsSql)))
+        .with_explain(sql::Explain::Plan);
+    assert_display_snapshot!(crate::compile("from employees", &options).unwrap_err(), @r###"
+    Error: The dialect mssql does not support EXPLAIN
+    "###);
+}
+
+#[test]
+fn test_static_analysis() {
+    assert_display_snapshot!(compile(
+        r###"
     from x
-    filter name != "Bob"
-    select name = name ?? "Default"
-    "###).unwrap(),
+    select {
+        a = (- (-3)),
+        b = !(!(!(!(!(true))))),
+        a3 = null ?? y,
+
+        a3 = case {
+            false == true => 1,
+            7 == 3 => 2,
+            7 == y => 3,
+            7.3 == 7.3 => 4,
+            z => 5,
+            true => 6
+        },
+    }
+        "###).unwrap(),
         @r###"
     SELECT
-      COALESCE(name, 'Default') AS name
+      3 AS a,
+      false AS b,
+      y,
+      CASE
+        WHEN 7 = y THEN 3
+        ELSE 4
+      END AS a3
     FROM
       x
-    WHERE
-      name <> 'Bob'
     "###
     );
 }
 
 #[test]
-fn test_unused_alias() {
-    // #1308
-    assert_display_snapshot!(compile(r###"
-    from account
-    select n = {account.name}
-    "###).unwrap_err(), @r###"
-    Error:
-       ╭─[:3:16]
-       │
-     3 │     select n = {account.name}
-       │                ───────┬──────
-       │                       ╰──────── unexpected assign to `n`
-       │
-       │ Help: move assign into the tuple: `[n = ...]`
-    ───╯
-    "###)
+fn test_closures_and_pipelines() {
+    assert_display_snapshot!(compile(
+        r###"
+    let addthree = a b c -> s"{a} || {b} || {c}"
+    let arg = myarg myfunc <func> -> ( myfunc myarg )
+
+    from y
+    select x = (
+        addthree "apples"
+        arg "bananas"
+        arg "citrus"
+    )
+        "###).unwrap(),
+        @r###"
+    SELECT
+      'apples' || 'bananas' || 'citrus' AS x
+    FROM
+      y
+    "###
+    );
 }
 
 #[test]
-fn test_table_s_string() {
-    assert_display_snapshot!(compile(r###"
-    let main <relation> = s"SELECT DISTINCT ON first_name, age FROM employees ORDER BY age ASC"
-    "###).unwrap(),
+fn test_functions_returning_tuples() {
+    // the columns of a returned tuple are added to the relation
+    assert_display_snapshot!(compile(
+        r###"
+    let bounds = x -> {low = x - 1, high = x + 1}
+
+    from measurements
+    derive {
+      spread = max_value - min_value,
+      bounds reading,
+    }
+    select {spread, low, high}
+        "###).unwrap(),
         @r###"
-    WITH table_0 AS (
-      SELECT
-        DISTINCT ON first_name,
-        age
-      FROM
-        employees
-      ORDER BY
-        age ASC
-    )
     SELECT
-      *
+      max_value - min_value AS spread,
+      reading - 1 AS low,
+      reading + 1 AS high
     FROM
-      table_0
+      measurements
     "###
     );
+}
 
+#[test]
+fn test_named_args_with_defaults() {
     assert_display_snapshot!(compile(r###"
-    from s"""
-        SELECT DISTINCT ON first_name, id, age FROM employees ORDER BY age ASC
-    """
-    join s = s"SELECT * FROM salaries" (==id)
+    let greet = name greeting:"Hello" -> f"{greeting} {name}"
+
+    from people
+    select {
+      hello = greet first_name,
+      hi = greet greeting:"Hi" first_name,
+    }
     "###).unwrap(),
         @r###"
-    WITH table_0 AS (
-      SELECT
-        DISTINCT ON first_name,
-        id,
-        age
-      FROM
-        employees
-      ORDER BY
-        age ASC
-    ),
-    table_1 AS (
-      SELECT
-        *
-      FROM
-        salaries
-    )
     SELECT
-      table_0.*,
-      table_1.*
+      CONCAT('Hello', ' ', first_name) AS hello,
+      CONCAT('Hi', ' ', first_name) AS hi
     FROM
-      table_0
-      JOIN table_1 ON table_0.id = table_1.id
+      people
     "###
     );
 
-    assert_display_snapshot!(compile(r###"
-    from s"""SELECT * FROM employees"""
-    filter country == "USA"
-    "###).unwrap(),
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        *
-      FROM
-        employees
+    let err = compile(
+        r###"
+    let greet = name greeting:"Hello" -> f"{greeting} {name}"
+
+    from people
+    select {hi = greet greeting:"Hi"}
+    "###,
     )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("`greet` is missing required arguments: `name`"));
+}
+
+#[test]
+fn test_basic_agg() {
+    assert_display_snapshot!(compile(r#"
+    from employees
+    aggregate {
+      count salary,
+      count this,
+    }
+    "#).unwrap(),
+        @r###"
     SELECT
-      *
+      COUNT(*),
+      COUNT(*)
     FROM
-      table_0
-    WHERE
-      country = 'USA'
+      employees
     "###
     );
+}
 
+#[test]
+fn test_concat_agg() {
     assert_display_snapshot!(compile(r###"
-    from e=s"""SELECT * FROM employees"""
-    filter e.country == "USA"
-    "###).unwrap(),
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        *
-      FROM
-        employees
+    prql target:sql.postgres
+    from employees
+    group department (
+        aggregate {names = concat_agg first_name}
     )
+    "###).unwrap(), @r###"
     SELECT
-      *
+      department,
+      COALESCE(STRING_AGG(first_name, ','), '') AS names
     FROM
-      table_0
-    WHERE
-      country = 'USA'
-    "###
-    );
+      employees
+    GROUP BY
+      department
+    "###);
 
     assert_display_snapshot!(compile(r###"
-    let weeks_between = start end -> s"SELECT generate_series({start}, {end}, '1 week') as date"
-    let current_week = -> s"date(date_trunc('week', current_date))"
+    prql target:sql.mysql
+    from employees
+    group department (
+        aggregate {names = concat_agg first_name}
+    )
+    "###).unwrap(), @r###"
+    SELECT
+      department,
+      COALESCE(GROUP_CONCAT(first_name SEPARATOR ','), '') AS names
+    FROM
+      employees
+    GROUP BY
+      department
+    "###);
 
-    weeks_between @2022-06-03 (current_week + 4)
-    "###).unwrap(),
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        generate_series(
-          DATE '2022-06-03',
-          date(date_trunc('week', current_date)) + 4,
-          '1 week'
-        ) as date
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.sqlite
+    from employees
+    group department (
+        aggregate {names = concat_agg first_name}
     )
+    "###).unwrap(), @r###"
     SELECT
-      *
+      department,
+      COALESCE(GROUP_CONCAT(first_name, ','), '') AS names
     FROM
-      table_0
-    "###
-    );
+      employees
+    GROUP BY
+      department
+    "###);
 
     assert_display_snapshot!(compile(r###"
-    s"SELECT * FROM {default_db.x}"
-    "###).unwrap(),
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        *
-      FROM
-        x
+    prql target:sql.oracle
+    from employees
+    group department (
+        aggregate {names = concat_agg first_name separator:"; "}
     )
+    "###).unwrap(), @r###"
     SELECT
-      *
+      department,
+      COALESCE(LISTAGG(first_name, '; '), '') AS names
     FROM
-      table_0
-    "###
-    );
+      employees
+    GROUP BY
+      department
+    "###);
+
+    assert_display_snapshot!(compile(r###"
+    prql target:sql.redshift
+    from employees
+    group department (
+        aggregate {names = concat_agg first_name separator:"; "}
+    )
+    "###).unwrap(), @r###"
+    SELECT
+      department,
+      COALESCE(LISTAGG(first_name, '; '), '') AS names
+    FROM
+      employees
+    GROUP BY
+      department
+    "###);
 }
 
 #[test]
-fn test_direct_table_references() {
-    assert_display_snapshot!(compile(
-        r###"
-    from x
-    select s"{x}.field"
-    "###,
-    )
-    .unwrap_err(), @r###"
+fn test_wildcard_after_aggregate() {
+    // the frame after `aggregate` contains only its outputs
+    assert_display_snapshot!(compile(r#"
+    from e=employees
+    aggregate {total = sum salary}
+    select this.*
+    "#).unwrap(),
+        @r###"
+    SELECT
+      SUM(salary) AS total
+    FROM
+      employees AS e
+    "###
+    );
+
+    assert_display_snapshot!(compile(r#"
+    from e=employees
+    aggregate {total = sum salary}
+    select e.*
+    "#).unwrap_err(),
+        @r###"
     Error:
-       ╭─[:3:14]
+       ╭─[:4:12]
        │
-     3 │     select s"{x}.field"
-       │              ─┬─
-       │               ╰─── table instance cannot be referenced directly
+     4 │     select e.*
+       │            ─┬─
+       │             ╰─── Unknown relation e.*
        │
-       │ Help: did you forget to specify the column name?
+       │ Help: after `aggregate`, only the aggregated columns are available as `this.*`
     ───╯
-    "###);
+    "###
+    );
 
-    assert_display_snapshot!(compile(
-        r###"
-    from x
-    select x
-    "###,
-    )
-    .unwrap_err(), @r###"
+    // ... which is not the reason for other unknown relations
+    assert_display_snapshot!(compile(r#"
+    from e=employees
+    select f.*
+    "#).unwrap_err(),
+        @r###"
     Error:
        ╭─[:3:12]
        │
-     3 │     select x
-       │            ┬
-       │            ╰── table instance cannot be referenced directly
-       │
-       │ Help: did you forget to specify the column name?
+     3 │     select f.*
+       │            ─┬─
+       │             ╰─── Unknown relation f.*
     ───╯
-    "###);
+    "###
+    );
 }
 
 #[test]
-fn test_name_shadowing() {
-    assert_display_snapshot!(compile(
-        r###"
-    from x
-    select {a, a, a = a + 1}
-    "###).unwrap(),
+fn test_exclude_columns() {
+    assert_display_snapshot!(compile(r#"
+    from tracks
+    select {track_id, title, composer, bytes}
+    select !{title, composer}
+    "#).unwrap(),
         @r###"
     SELECT
-      a AS _expr_0,
-      a AS _expr_0,
-      a + 1 AS a
+      track_id,
+      bytes
+    FROM
+      tracks
+    "###
+    );
+
+    assert_display_snapshot!(compile(r#"
+    from tracks
+    select {track_id, title, composer, bytes}
+    group !{title, composer} (aggregate {count this})
+    "#).unwrap(),
+        @r###"
+    SELECT
+      track_id,
+      bytes,
+      COUNT(*)
     FROM
-      x
+      tracks
+    GROUP BY
+      track_id,
+      bytes
     "###
     );
 
-    assert_display_snapshot!(compile(
-        r###"
-    from x
-    select a
-    derive a
-    derive a = a + 1
-    derive a = a + 2
-    "###).unwrap(),
+    assert_display_snapshot!(compile(r#"
+    from artists
+    derive nick = name
+    select !{artists.*}
+    "#).unwrap(),
         @r###"
     SELECT
-      a AS _expr_0,
-      a AS _expr_0,
-      a + 1,
-      a + 1 + 2 AS a
+      name AS nick
     FROM
-      x
+      artists
     "###
     );
-}
-
-#[test]
-fn test_group_all() {
-    assert_display_snapshot!(compile(
-        r###"
-    prql target:sql.sqlite
-
-    from a=albums
-    group a.* (aggregate {count this})
-        "###).unwrap_err(), @r###"
-    Error: Target dialect does not support * in this position.
-    "###);
-
-    assert_display_snapshot!(compile(
-        r###"
-    from e=albums
-    group !{genre_id} (aggregate {count this})
-        "###).unwrap_err(), @r###"
-    Error: Excluding columns not supported as this position
-    "###);
-}
 
-#[test]
-fn test_output_column_deduplication() {
-    // #1249
-    assert_display_snapshot!(compile(
-        r###"
-    from report
-    derive r = s"RANK() OVER ()"
-    filter r == 1
-        "###).unwrap(),
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.bigquery
+    from tracks
+    select !{milliseconds,bytes}
+    "#).unwrap(),
         @r###"
-    WITH table_0 AS (
-      SELECT
-        *,
-        RANK() OVER () AS r
-      FROM
-        report
-    )
     SELECT
       *
+    EXCEPT
+      (milliseconds, bytes)
     FROM
-      table_0
-    WHERE
-      r = 1
+      tracks
     "###
     );
-}
 
-#[test]
-fn test_case() {
-    assert_display_snapshot!(compile(
-        r###"
-    from employees
-    derive display_name = case {
-        nickname != null => nickname,
-        true => f'{first_name} {last_name}'
-    }
-        "###).unwrap(),
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.snowflake
+    from tracks
+    select !{milliseconds,bytes}
+    "#).unwrap(),
         @r###"
     SELECT
-      *,
-      CASE
-        WHEN nickname IS NOT NULL THEN nickname
-        ELSE CONCAT(first_name, ' ', last_name)
-      END AS display_name
+      * EXCLUDE (milliseconds, bytes)
     FROM
-      employees
+      tracks
     "###
     );
 
-    assert_display_snapshot!(compile(
-        r###"
-    from employees
-    derive display_name = case {
-        nickname != null => nickname,
-        first_name != null => f'{first_name} {last_name}'
-    }
-        "###).unwrap(),
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.duckdb
+    from tracks
+    select !{milliseconds,bytes}
+    "#).unwrap(),
         @r###"
     SELECT
-      *,
-      CASE
-        WHEN nickname IS NOT NULL THEN nickname
-        WHEN first_name IS NOT NULL THEN CONCAT(first_name, ' ', last_name)
-        ELSE NULL
-      END AS display_name
+      * EXCLUDE (milliseconds, bytes)
     FROM
-      employees
+      tracks
     "###
     );
 
-    assert_display_snapshot!(compile(
-        r###"
-    from tracks
-    select category = case {
-        length > avg_length => 'long'
-    }
-    group category (aggregate {count this})
-        "###).unwrap(),
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.duckdb
+    from s"SELECT * FROM foo"
+    select !{bar}
+    "#).unwrap(),
         @r###"
     WITH table_0 AS (
       SELECT
-        CASE
-          WHEN length > avg_length THEN 'long'
-          ELSE NULL
-        END AS category,
-        length,
-        avg_length
+        *
       FROM
-        tracks
+        foo
     )
     SELECT
-      category,
-      COUNT(*)
+      * EXCLUDE (bar)
     FROM
       table_0
-    GROUP BY
-      category
     "###
     );
-}
-
-#[test]
-fn test_sql_options() {
-    let options = Options::default();
-    let sql = crate::compile("from x", &options).unwrap();
-
-    assert!(sql.contains('\n'));
-    assert!(sql.contains("-- Generated by"));
 
-    let options = Options::default().no_signature().no_format();
-    let sql = crate::compile("from x", &options).unwrap();
-
-    assert!(!sql.contains('\n'));
-    assert!(!sql.contains("-- Generated by"));
-}
-
-#[test]
-fn test_static_analysis() {
-    assert_display_snapshot!(compile(
-        r###"
-    from x
-    select {
-        a = (- (-3)),
-        b = !(!(!(!(!(true))))),
-        a3 = null ?? y,
+    // columns of a relation declared with `let` are known, so they can be
+    // listed explicitly, including the derived ones
+    assert_display_snapshot!(compile(r#"
+    let songs = (
+      from tracks
+      select {track_id, title, composer, bytes}
+    )
 
-        a3 = case {
-            false == true => 1,
-            7 == 3 => 2,
-            7 == y => 3,
-            7.3 == 7.3 => 4,
-            z => 5,
-            true => 6
-        },
-    }
-        "###).unwrap(),
+    from songs
+    derive bits = bytes * 8
+    select !{bytes, composer}
+    "#).unwrap(),
         @r###"
-    SELECT
-      3 AS a,
-      false AS b,
-      y,
-      CASE
-        WHEN 7 = y THEN 3
-        ELSE 4
-      END AS a3
-    FROM
-      x
-    "###
-    );
-}
-
-#[test]
-fn test_closures_and_pipelines() {
-    assert_display_snapshot!(compile(
-        r###"
-    let addthree = a b c -> s"{a} || {b} || {c}"
-    let arg = myarg myfunc <func> -> ( myfunc myarg )
-
-    from y
-    select x = (
-        addthree "apples"
-        arg "bananas"
-        arg "citrus"
+    WITH songs AS (
+      SELECT
+        track_id,
+        title,
+        composer,
+        bytes
+      FROM
+        tracks
     )
-        "###).unwrap(),
-        @r###"
     SELECT
-      'apples' || 'bananas' || 'citrus' AS x
+      track_id,
+      title,
+      bytes * 8 AS bits
     FROM
-      y
+      songs
     "###
     );
-}
 
-#[test]
-fn test_basic_agg() {
+    // columns of tracks are not known, so they cannot be listed explicitly
     assert_display_snapshot!(compile(r#"
-    from employees
-    aggregate {
-      count salary,
-      count this,
-    }
-    "#).unwrap(),
+    from tracks
+    select !{milliseconds,bytes}
+    "#).unwrap_err(),
         @r###"
-    SELECT
-      COUNT(*),
-      COUNT(*)
-    FROM
-      employees
+    Error: Excluding columns milliseconds, bytes is not supported by dialect generic
+    ↳ Hint: specify columns of the relation, for example with `select`
     "###
     );
 }
 
 #[test]
-fn test_exclude_columns() {
+fn test_exclude_generated_columns() {
+    // columns introduced by the compiler are included with the star on
+    // dialects that cannot exclude them, instead of being reported as
+    // exclusions the query requested
+    let queries = [
+        // row numbers of `tail` within groups
+        "from employees | group department (sort age | tail 2)",
+        // row numbers of `distinct_on`
+        "from employees | sort {department, -salary} | distinct_on {department}",
+        // keys of `tail` that are carried over its split
+        "from employees | sort {(age + 1)} | tail 3",
+        // keys of `sample` that are carried over its split
+        "from employees | derive bonus = salary * 2 | sample 5 | sort bonus | take 3",
+        // columns of `keep` and `drop` that are carried over a split
+        "from employees | derive bonus = salary * 2 | take 10 | filter bonus > 100 | drop bonus",
+        "from employees | derive {bonus = salary * 2, rank = age + 1} | keep {bonus} | take 10 | filter rank > 1 | select !{rank}",
+    ];
+    for query in queries {
+        let sql = compile(&format!("prql target:sql.sqlite\n{query}")).unwrap();
+        assert!(!sql.contains("EXCLUDE"), "{sql}");
+    }
+
+    // columns that the query excluded still cannot be expressed
     assert_display_snapshot!(compile(r#"
-    from tracks
-    select {track_id, title, composer, bytes}
-    select !{title, composer}
-    "#).unwrap(),
-        @r###"
-    SELECT
-      track_id,
-      bytes
-    FROM
-      tracks
+    prql target:sql.sqlite
+    from tracks
+    take 10
+    filter bytes > 100
+    select !{bytes}
+    "#).unwrap_err(),
+        @r###"
+    Error: Excluding columns bytes is not supported by dialect sqlite
+    ↳ Hint: specify columns of the relation, for example with `select`
     "###
     );
+}
 
+#[test]
+fn test_drop() {
     assert_display_snapshot!(compile(r#"
     from tracks
     select {track_id, title, composer, bytes}
-    group !{title, composer} (aggregate {count this})
+    drop {title, composer}
     "#).unwrap(),
         @r###"
     SELECT
-      track_id,
-      bytes,
-      COUNT(*)
-    FROM
-      tracks
-    GROUP BY
       track_id,
       bytes
-    "###
-    );
-
-    assert_display_snapshot!(compile(r#"
-    from artists
-    derive nick = name
-    select !{artists.*}
-    "#).unwrap(),
-        @r###"
-    SELECT
-      name AS nick
     FROM
-      artists
+      tracks
     "###
     );
 
     assert_display_snapshot!(compile(r#"
     prql target:sql.bigquery
     from tracks
-    select !{milliseconds,bytes}
+    drop bytes
     "#).unwrap(),
         @r###"
     SELECT
       *
     EXCEPT
-      (milliseconds, bytes)
+      (bytes)
     FROM
       tracks
     "###
     );
+}
 
+#[test]
+fn test_keep() {
     assert_display_snapshot!(compile(r#"
-    prql target:sql.snowflake
-    from tracks
-    select !{milliseconds,bytes}
+    from employees
+    derive {bonus = salary * 0.1}
+    keep {bonus}
+    filter bonus > 1000
+    select {name}
     "#).unwrap(),
         @r###"
     SELECT
-      * EXCLUDE (milliseconds, bytes)
+      name,
+      salary * 0.1 AS bonus
     FROM
-      tracks
+      employees
+    WHERE
+      salary * 0.1 > 1000
     "###
     );
 
+    // columns that are selected already are not duplicated
     assert_display_snapshot!(compile(r#"
-    prql target:sql.duckdb
-    from tracks
-    select !{milliseconds,bytes}
+    from employees
+    keep {name}
+    select {name, age}
     "#).unwrap(),
         @r###"
     SELECT
-      * EXCLUDE (milliseconds, bytes)
+      name,
+      age
     FROM
-      tracks
+      employees
     "###
     );
 
-    assert_display_snapshot!(compile(r#"
-    prql target:sql.duckdb
-    from s"SELECT * FROM foo"
-    select !{bar}
-    "#).unwrap(),
-        @r###"
-    WITH table_0 AS (
-      SELECT
-        *
-      FROM
-        foo
+    let err = compile(
+        r#"
+    from employees
+    keep {bonus = salary * 0.1}
+    "#,
     )
-    SELECT
-      * EXCLUDE (bar)
-    FROM
-      table_0
-    "###
-    );
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("`keep` expected names of columns"));
 }
 
 #[test]
@@ -3454,6 +6802,17 @@ fn test_loop() {
       4
     "###
     );
+
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.clickhouse
+
+    from [{n = 1}]
+    loop (select n = n+1 | filter n<5)
+    "#).unwrap_err(),
+        @r###"
+    Error: `loop` is not supported for dialect clickhouse, because it does not support recursive CTEs
+    "###
+    );
 }
 
 #[test]
@@ -3616,6 +6975,110 @@ fn test_upper() {
     );
 }
 
+#[test]
+fn test_replace() {
+    assert_display_snapshot!(compile(r#"
+    from tracks
+    derive {clean_title = (replace title "&" "and")}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      *,
+      REPLACE(title, '&', 'and') AS clean_title
+    FROM
+      tracks
+    "###
+    );
+}
+
+#[test]
+fn test_substring() {
+    assert_display_snapshot!(compile(r#"
+    from tracks
+    select {initial = (substring name 1 1)}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      SUBSTRING(name, 1, 1) AS initial
+    FROM
+      tracks
+    "###
+    );
+
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.sqlite
+
+    from tracks
+    select {initial = (substring name 1 1)}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      SUBSTR(name, 1, 1) AS initial
+    FROM
+      tracks
+    "###
+    );
+}
+
+#[test]
+fn test_starts_with_contains() {
+    assert_display_snapshot!(compile(r#"
+    from tracks
+    filter (starts_with name "A")
+    filter (contains name "50%_off!")
+    filter (starts_with name prefix)
+    filter (contains name keyword)
+    select {name}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      name
+    FROM
+      tracks
+    WHERE
+      name LIKE 'A%'
+      AND name LIKE '%50!%!_off!!%' ESCAPE '!'
+      AND name LIKE CONCAT(prefix, '%')
+      AND name LIKE CONCAT('%', keyword, '%')
+    "###
+    );
+
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.sqlite
+
+    from tracks
+    filter (starts_with name prefix)
+    select {name}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      name
+    FROM
+      tracks
+    WHERE
+      name LIKE prefix || '%'
+    "###
+    );
+
+    // BigQuery has no ESCAPE, so wildcards are escaped with a backslash
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.bigquery
+
+    from tracks
+    filter (contains name "50%")
+    select {name}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      name
+    FROM
+      tracks
+    WHERE
+      name LIKE '%50\\%%'
+    "###
+    );
+}
+
 #[test]
 fn test_1535() {
     assert_display_snapshot!(compile(r#"
@@ -3630,6 +7093,48 @@ fn test_1535() {
     );
 }
 
+#[test]
+fn test_from_pipeline() {
+    // an atomic pipeline is flattened into the query
+    assert_display_snapshot!(compile(r#"
+    from (from employees | filter active)
+    select {name}
+    "#).unwrap(),
+        @r###"
+    SELECT
+      name
+    FROM
+      employees
+    WHERE
+      active
+    "###
+    );
+
+    assert_display_snapshot!(compile(r#"
+    from (from employees | take 10)
+    filter salary > 100
+    select {name}
+    "#).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        name,
+        salary
+      FROM
+        employees
+      LIMIT
+        10
+    )
+    SELECT
+      name
+    FROM
+      table_0
+    WHERE
+      salary > 100
+    "###
+    );
+}
+
 #[test]
 fn test_read_parquet_duckdb() {
     assert_display_snapshot!(compile(r#"
@@ -3661,6 +7166,41 @@ fn test_read_parquet_duckdb() {
     // TODO: `from x=(read_parquet 'x.parquet')` currently fails
 }
 
+#[test]
+fn test_read_csv_duckdb() {
+    assert_display_snapshot!(compile(r#"
+    prql target:sql.duckdb
+
+    from (read_csv 'x.csv')
+    take 5
+    "#).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        *
+      FROM
+        read_csv_auto('x.csv')
+    )
+    SELECT
+      *
+    FROM
+      table_0
+    LIMIT
+      5
+    "###
+    );
+
+    // paths must be known when the query is planned
+    let err = compile(
+        r#"
+    from (read_csv s"'x.csv'")
+    "#,
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(err.contains("`read_csv` expected a string literal of the path"));
+}
+
 #[test]
 fn test_excess_columns() {
     // https://github.com/PRQL/prql/issues/2079
@@ -3686,6 +7226,29 @@ fn test_excess_columns() {
       _expr_0
     "###
     );
+
+    // columns that are only needed by ORDER BY are kept in the CTE
+    assert_display_snapshot!(compile(r#"
+    from users
+    sort secret
+    select {public}
+    "#).unwrap(),
+        @r###"
+    WITH table_0 AS (
+      SELECT
+        public,
+        secret
+      FROM
+        users
+    )
+    SELECT
+      public
+    FROM
+      table_0
+    ORDER BY
+      secret
+    "###
+    );
 }
 
 #[test]
@@ -4066,3 +7629,66 @@ fn test_conflicting_names_at_split() {
       JOIN workflow AS w ON table_0.workflow_id = w.id
     "###);
 }
+
+#[test]
+fn test_compile_models() {
+    let models = crate::compile_models(
+        r###"
+    let customers = (
+        from raw_customers
+        select {customer_id, email}
+    )
+
+    let orders = (
+        from raw_orders
+        join customers (==customer_id)
+        select {order_id, customers.email}
+    )
+    "###,
+        &Options::default().no_signature(),
+    )
+    .unwrap();
+
+    let names: Vec<_> = models.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["customers", "orders"]);
+
+    assert_display_snapshot!(models[0].1, @r###"
+    SELECT
+      customer_id,
+      email
+    FROM
+      raw_customers
+    "###);
+
+    assert_display_snapshot!(models[1].1, @r###"
+    SELECT
+      raw_orders.order_id,
+      customers.email
+    FROM
+      raw_orders
+      JOIN customers ON raw_orders.customer_id = customers.customer_id
+    "###);
+}
+
+#[test]
+fn test_compile_stages() {
+    let query = r###"
+    let long_tracks = (from tracks | filter milliseconds > 300000)
+    from long_tracks
+    join albums (==album_id)
+    select {long_tracks.name, albums.title}
+    "###;
+    let options = Options::default().no_signature();
+
+    let (pl, rq, sql) = crate::compile_stages(query, &options).unwrap();
+
+    // each stage matches the result of running it alone
+    assert_eq!(pl, crate::prql_to_pl(query).unwrap());
+    assert_eq!(rq, crate::pl_to_rq(pl).unwrap());
+    assert_eq!(sql, crate::rq_to_sql(rq, &options).unwrap());
+    assert_eq!(sql, crate::compile(query, &options).unwrap());
+
+    // errors refer to the source
+    let err = crate::compile_stages("from x | select y | filter z", &options).unwrap_err();
+    assert!(err.to_string().contains("Unknown name"));
+}
@@ -62,7 +62,7 @@ fn test_errors() {
        │
      3 │     take 1.8
        │          ─┬─
-       │           ╰─── `take` expected int or range, but found 1.8
+       │           ╰─── `take` expected int, range or percentage, but found 1.8
     ───╯
     "###);
 
@@ -249,3 +249,72 @@ fn test_ambiguous_inference() {
     ───╯
     "###);
 }
+
+#[test]
+fn test_parse_recovering() {
+    // each of the malformed transforms is reported, and the rest of the query
+    // is still parsed
+    let (stmts, errors) = crate::prql_to_pl_recovering(
+        r###"
+    from tracks
+    select {title, length +}
+    derive {x = (1 *)}
+    take 10
+    "###,
+    );
+
+    assert!(stmts.is_some());
+    assert_eq!(errors.inner.len(), 2);
+
+    let lines: Vec<_> = (errors.inner.iter())
+        .map(|e| e.location.as_ref().unwrap().start.0)
+        .collect();
+    assert_eq!(lines[0] + 1, lines[1]);
+}
+
+#[test]
+fn test_empty_query() {
+    for query in ["", "   ", "\n\n", "# just a comment\n"] {
+        assert_display_snapshot!(compile(query).unwrap_err(), @r###"
+        Error: empty query
+        ↳ Hint: a query starts with a relation, for example `from employees`
+        "###);
+    }
+}
+
+#[test]
+fn test_unknown_interval_unit() {
+    assert_display_snapshot!(compile(r###"
+    from projects
+    derive due = start + 10fortnights
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:3:26]
+       │
+     3 │     derive due = start + 10fortnights
+       │                          ──────┬─────
+       │                                ╰─────── unknown unit `fortnights`
+       │
+       │ Help: expected one of microseconds, milliseconds, seconds, minutes, hours, days, weeks, months, years
+    ───╯
+    "###);
+}
+
+#[test]
+fn test_sort_unknown_column() {
+    assert_display_snapshot!(compile(r###"
+    from employees
+    select {name, age}
+    sort salary
+    "###).unwrap_err(), @r###"
+    Error:
+       ╭─[:4:10]
+       │
+     4 │     sort salary
+       │          ───┬──
+       │             ╰──── Unknown name
+       │
+       │ Help: available columns: [employees.name, employees.age]
+    ───╯
+    "###);
+}
@@ -115,6 +115,10 @@ pub static VALID_IDENT: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"^((\*)|(^[a-z_\$][a-z0-9_\$]*))$").unwrap()
 });
 
+/// Same as [VALID_IDENT], but also allows uppercase letters.
+pub static VALID_IDENT_ANY_CASE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^((\*)|(^[a-zA-Z_\$][a-zA-Z0-9_\$]*))$").unwrap());
+
 #[test]
 fn test_write_ident_part() {
     assert!(!VALID_IDENT.is_match(""));
@@ -24,14 +24,29 @@ pub fn expr() -> impl Parser<Token, Expr, Error = PError> + Clone {
 
         let nested_expr = pipeline(lambda_func(expr.clone()).or(func_call(expr.clone()))).boxed();
 
-        let tuple = ident_part()
-            .then_ignore(ctrl('='))
+        // column with a type annotation, which is cast to that type
+        let typed_ident = ident()
+            .map_with_span(|ident, span| into_expr(ExprKind::Ident(ident), span))
+            .then(type_expr())
+            .then_ignore(choice((ctrl(','), ctrl('}'), new_line())).rewind())
+            .map_with_span(|(column, ty), span| {
+                let alias = column.kind.as_ident().map(|i| i.name.clone());
+                let name = Expr::new(ExprKind::Ident(Ident::from_path(vec!["std", "as"])));
+                let func_call = FuncCall {
+                    name: Box::new(name),
+                    args: vec![ty, column],
+                    named_args: HashMap::new(),
+                };
+                Expr {
+                    alias,
+                    ..into_expr(ExprKind::FuncCall(func_call), span)
+                }
+            });
+
+        let tuple = alias()
             .or_not()
-            .then(nested_expr.clone())
-            .map(|(alias, mut expr)| {
-                expr.alias = alias.or(expr.alias);
-                expr
-            })
+            .then(typed_ident.or(nested_expr.clone()))
+            .map(apply_alias)
             .padded_by(new_line().repeated())
             .separated_by(ctrl(','))
             .allow_trailing()
@@ -262,6 +277,39 @@ where
         .boxed()
 }
 
+/// Alias of an expression, optionally followed by a type that the expression
+/// must have, i.e. `amount<float> = price * qty`.
+fn alias() -> impl Parser<Token, (String, Option<Expr>), Error = PError> {
+    ident_part()
+        .then(type_expr().or_not())
+        .then_ignore(ctrl('='))
+}
+
+fn apply_alias((alias, mut expr): (Option<(String, Option<Expr>)>, Expr)) -> Expr {
+    let Some((alias, ty)) = alias else {
+        return expr;
+    };
+
+    if let Some(ty) = ty {
+        let span = expr.span;
+        let name = Expr::new(ExprKind::Ident(Ident::from_path(vec![
+            "std",
+            "assert_type",
+        ])));
+        let func_call = FuncCall {
+            name: Box::new(name),
+            args: vec![ty, expr],
+            named_args: HashMap::new(),
+        };
+        expr = Expr {
+            span,
+            ..Expr::new(ExprKind::FuncCall(func_call))
+        };
+    }
+    expr.alias = Some(alias);
+    expr
+}
+
 fn func_call<E>(expr: E) -> impl Parser<Token, Expr, Error = PError>
 where
     E: Parser<Token, Expr, Error = PError> + Clone,
@@ -273,15 +321,10 @@ where
         .then_ignore(ctrl(':'))
         .then(expr.clone());
 
-    let positional_arg =
-        ident_part()
-            .then_ignore(ctrl('='))
-            .or_not()
-            .then(expr)
-            .map(|(alias, mut expr)| {
-                expr.alias = alias.or(expr.alias);
-                (None, expr)
-            });
+    let positional_arg = alias()
+        .or_not()
+        .then(expr)
+        .map(|aliased| (None, apply_alias(aliased)));
 
     func_name
         .then(named_arg.or(positional_arg).repeated())
@@ -31,6 +31,10 @@ pub enum Token {
     Coalesce,    // ??
     DivInt,      // //
     Annotate,    // @
+
+    /// Text of a comment, without the leading `#`.
+    /// Comments are not passed to the parser, but they are needed for formatting.
+    Comment(String),
 }
 
 pub fn lexer() -> impl Parser<char, Vec<(Token, std::ops::Range<usize>)>, Error = Cheap<char>> {
@@ -94,11 +98,13 @@ pub fn lexer() -> impl Parser<char, Vec<(Token, std::ops::Range<usize>)>, Error
     ))
     .recover_with(skip_then_retry_until([]).skip_start());
 
-    let comment = just('#').then(none_of('\n').repeated());
+    let comment = just('#')
+        .ignore_then(none_of('\n').repeated().collect::<String>())
+        .map(Token::Comment)
+        .map_with_span(|tok, span| (tok, span));
     let comments = comment
         .separated_by(new_line.then(whitespace.clone().or_not()))
-        .at_least(1)
-        .ignored();
+        .at_least(1);
 
     let range = (whitespace.clone().or_not())
         .then_ignore(just(".."))
@@ -111,19 +117,18 @@ pub fn lexer() -> impl Parser<char, Vec<(Token, std::ops::Range<usize>)>, Error
 
     // range needs to consume leading whitespace,
     // so whitespace following a token must not be consumed
-    let ignored = comments.clone().or(whitespace).repeated();
-
-    comments
-        .or_not()
-        .ignore_then(choice((
-            range,
-            ignored
-                .clone()
-                .ignore_then(token.map_with_span(|tok, span| (tok, span))),
-        )))
-        .repeated()
-        .then_ignore(ignored)
-        .then_ignore(end())
+    choice((
+        range.map(|tok| vec![tok]),
+        whitespace.clone().or_not().ignore_then(comments),
+        whitespace
+            .clone()
+            .or_not()
+            .ignore_then(token.map_with_span(|tok, span| vec![(tok, span)])),
+    ))
+    .repeated()
+    .flatten()
+    .then_ignore(whitespace.or_not())
+    .then_ignore(end())
 }
 
 pub fn ident_part() -> impl Parser<char, String, Error = Cheap<char>> {
@@ -188,23 +193,19 @@ fn literal() -> impl Parser<char, Literal, Error = Cheap<char>> {
 
     let null = just("null").to(Literal::Null).then_ignore(end_expr());
 
+    // units are validated by the parser, which can report unknown ones
+    let unit = filter(|c: &char| c.is_ascii_alphabetic())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .or(just("%").map(|u| u.to_string()));
+
     let value_and_unit = integer
-        .then(choice((
-            just("microseconds"),
-            just("milliseconds"),
-            just("seconds"),
-            just("minutes"),
-            just("hours"),
-            just("days"),
-            just("weeks"),
-            just("months"),
-            just("years"),
-        )))
+        .then(unit)
         .then_ignore(end_expr())
         .try_map(|(number, unit), span| {
             let str = number.into_iter().filter(|c| *c != '_').collect::<String>();
             if let Ok(n) = str.parse::<i64>() {
-                let unit = unit.to_string();
                 Ok(ValueAndUnit { n, unit })
             } else {
                 Err(Cheap::expected_input_found(span, None, None))
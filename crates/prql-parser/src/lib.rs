@@ -25,6 +25,20 @@ pub enum Error {
 
 /// Build PRQL AST from a PRQL query string.
 pub fn parse_source(source: &str, source_id: u16) -> Result<Vec<Stmt>, Vec<Error>> {
+    let (ast, errors) = parse_source_recovering(source, source_id);
+
+    if errors.is_empty() {
+        Ok(ast.unwrap_or_default())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Build PRQL AST from a PRQL query string, recovering from syntax errors.
+///
+/// Returns the AST of the parts of the source that could be parsed, along
+/// with all of the errors, so they can be reported at once.
+pub fn parse_source_recovering(source: &str, source_id: u16) -> (Option<Vec<Stmt>>, Vec<Error>) {
     let mut errors = Vec::new();
 
     let (tokens, lex_errors) = ::chumsky::Parser::parse_recovery(&lexer::lexer(), source);
@@ -43,11 +57,7 @@ pub fn parse_source(source: &str, source_id: u16) -> Result<Vec<Stmt>, Vec<Error
         None
     };
 
-    if errors.is_empty() {
-        Ok(ast.unwrap_or_default())
-    } else {
-        Err(errors)
-    }
+    (ast, errors)
 }
 
 /// Helper that does not track source_ids
@@ -63,6 +73,7 @@ fn prepare_stream(
 ) -> Stream<Token, ParserSpan, impl Iterator<Item = (Token, ParserSpan)> + Sized> {
     let tokens = tokens
         .into_iter()
+        .filter(|(t, _)| !matches!(t, Token::Comment(_)))
         .map(move |(t, s)| (t, ParserSpan::new(source_id, s)));
     let len = source.chars().count();
     let eoi = ParserSpan(Span {
@@ -1467,6 +1478,24 @@ Canada
                   - d
         "###);
 
+        assert_yaml_snapshot!(parse_expr(r#"a || b && c"#).unwrap(), @r###"
+        ---
+        Binary:
+          left:
+            Ident:
+              - a
+          op: Or
+          right:
+            Binary:
+              left:
+                Ident:
+                  - b
+              op: And
+              right:
+                Ident:
+                  - c
+        "###);
+
         assert_yaml_snapshot!(parse_expr(r#"a && b + c || (d e) && f"#).unwrap(), @r###"
         ---
         Binary:
@@ -61,20 +61,29 @@ fn query_def() -> impl Parser<Token, Stmt, Error = PError> {
             // TODO: `QueryDef` is currently implemented as `version` & `other`
             // fields. We want to raise an error if an unsupported field is
             // used, to avoid confusion (e.g. if someone passes `dialect`). So
-            // at the moment we implement this as having a HashMap with 0 or 1
-            // entries... We can decide how to implement `QueryDef` later, and
+            // at the moment we implement this as having a HashMap with an
+            // entry for each known field... We can decide how to implement `QueryDef` later, and
             // have this awkward construction in the meantime.
-            let other = args
+            let mut other = HashMap::new();
+            let target = args
                 .remove("target")
                 .map(|v| match v.kind {
                     ExprKind::Ident(value) => Ok(value.to_string()),
                     _ => Err("target must be a string literal".to_string()),
                 })
                 .transpose()
-                .map_err(|msg| Simple::custom(span, msg))?
-                .map_or_else(HashMap::new, |x| {
-                    HashMap::from_iter(vec![("target".to_string(), x)])
-                });
+                .map_err(|msg| Simple::custom(span, msg))?;
+            other.extend(target.map(|x| ("target".to_string(), x)));
+
+            let strict = args
+                .remove("strict")
+                .map(|v| match v.kind {
+                    ExprKind::Literal(Literal::Boolean(value)) => Ok(value.to_string()),
+                    _ => Err("strict must be a boolean".to_string()),
+                })
+                .transpose()
+                .map_err(|msg| Simple::custom(span, msg))?;
+            other.extend(strict.map(|x| ("strict".to_string(), x)));
 
             if !args.is_empty() {
                 return Err(Simple::custom(
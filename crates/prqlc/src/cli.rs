@@ -17,12 +17,12 @@ use std::process::exit;
 use std::str::FromStr;
 
 use prql_compiler::semantic;
-use prql_compiler::semantic::reporting::{collect_frames, label_references};
+use prql_compiler::semantic::reporting::{collect_frames, explain_types, label_references};
 use prql_compiler::{downcast, Options, Target};
-use prql_compiler::{ir::pl::Lineage, ir::Span};
 use prql_compiler::{
-    pl_to_prql, pl_to_rq_tree, prql_to_pl, prql_to_pl_tree, rq_to_sql, SourceTree,
+    format_prql, pl_to_rq_tree, prql_to_pl, prql_to_pl_tree, rq_to_sql, SourceTree,
 };
+use prql_compiler::{ir::pl::Lineage, ir::Span};
 
 use crate::watch;
 
@@ -79,7 +79,15 @@ enum Command {
     #[command(name = "fmt")]
     Format {
         #[arg(value_parser, default_value = "-", value_hint(ValueHint::AnyPath))]
-        input: clio::ClioPath,
+        inputs: Vec<clio::ClioPath>,
+
+        /// Write the formatted code to stdout rather than back to the files
+        #[arg(long)]
+        stdout: bool,
+
+        /// Don't write anything, but fail if any of the files isn't formatted
+        #[arg(long)]
+        check: bool,
     },
 
     #[command(subcommand)]
@@ -128,6 +136,14 @@ enum Command {
         /// Target to compile to
         #[arg(short, long, default_value = "sql.any", env = "PRQLC_TARGET")]
         target: String,
+
+        /// Output to produce: SQL, PL AST as JSON, or formatted PRQL
+        #[arg(value_enum, long, default_value = "sql")]
+        to: CompileOutput,
+
+        /// Print the columns of the result with their types, instead of SQL
+        #[arg(long)]
+        explain_types: bool,
     },
 
     /// Watch a directory and compile .prql files to .sql files
@@ -183,6 +199,13 @@ enum Format {
     Yaml,
 }
 
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum CompileOutput {
+    Sql,
+    Json,
+    Prql,
+}
+
 impl Command {
     /// Entrypoint called by [`main`]
     pub fn run(&mut self) -> Result<()> {
@@ -190,29 +213,12 @@ impl Command {
             Command::Watch(command) => watch::run(command),
             Command::ListTargets => self.list_targets(),
             // Format is handled differently to the other IO commands, since it
-            // always writes to the same output.
-            Command::Format { input } => {
-                let sources = read_files(input)?;
-
-                if sources.sources.len() != 1 {
-                    let paths = sources
-                        .sources
-                        .keys()
-                        .map(|x| format!("`{}`", x.display()))
-                        .sorted()
-                        .join(", ");
-                    bail!(
-                        "Currently `fmt` only works with a single source, but found multiple sources: {paths:?}"
-                    )
-                }
-                let (_, source) = sources.sources.into_iter().next().unwrap();
-
-                let ast = prql_to_pl(&source)?;
-
-                let mut output: Output = Output::new(input.path())?;
-                output.write_all(&pl_to_prql(ast)?.into_bytes())?;
-                Ok(())
-            }
+            // writes back to its inputs.
+            Command::Format {
+                inputs,
+                stdout,
+                check,
+            } => format_files(inputs, *stdout, *check),
             Command::ShellCompletion { shell } => {
                 shell.generate(&mut Cli::command(), &mut std::io::stdout());
                 Ok(())
@@ -279,16 +285,7 @@ impl Command {
                 out
             }
             Command::Debug(DebugCommand::Annotate(_)) => {
-                let (_, source) = sources.sources.clone().into_iter().exactly_one().or_else(
-                    |_| bail!(
-                        "Currently `annotate` only works with a single source, but found multiple sources: {:?}",
-                        sources.sources.keys()
-                            .map(|x| x.display().to_string())
-                            .sorted()
-                            .map(|x| format!("`{x}`"))
-                            .join(", ")
-                    )
-                )?;
+                let source = single_source(sources, "annotate")?;
 
                 // TODO: potentially if there is code performing a role beyond
                 // presentation, it should be a library function; and we could
@@ -342,10 +339,42 @@ impl Command {
                     Format::Yaml => serde_yaml::to_string(&ir)?.into_bytes(),
                 }
             }
+            Command::SQLCompile {
+                explain_types: true,
+                ..
+            } => {
+                semantic::load_std_lib(sources);
+                let stmts = prql_to_pl_tree(sources)?;
+
+                let ctx = semantic::resolve(stmts, Default::default())
+                    .map_err(downcast)
+                    .map_err(|e| e.composed(sources))?;
+
+                let Ok((main, _)) = ctx.find_main_rel(&main_path) else {
+                    bail!("Missing main pipeline");
+                };
+                let main = main.clone().into_relation_var().unwrap();
+
+                explain_types(*main).into_bytes()
+            }
+            Command::SQLCompile {
+                to: CompileOutput::Json,
+                ..
+            } => {
+                let stmts = prql_to_pl(&single_source(sources, "--to json")?)?;
+                let mut json = prql_compiler::json::from_pl(stmts)?;
+                json.push('\n');
+                json.into_bytes()
+            }
+            Command::SQLCompile {
+                to: CompileOutput::Prql,
+                ..
+            } => format_source(&single_source(sources, "--to prql")?)?.into_bytes(),
             Command::SQLCompile {
                 signature_comment,
                 format,
                 target,
+                to: CompileOutput::Sql,
                 ..
             } => {
                 semantic::load_std_lib(sources);
@@ -450,6 +479,74 @@ impl Command {
     }
 }
 
+fn format_files(inputs: &mut [clio::ClioPath], stdout: bool, check: bool) -> Result<()> {
+    let mut unformatted = Vec::new();
+
+    for input in inputs {
+        let root = input.path().to_path_buf();
+        let is_std = input.is_std();
+        let sources = read_files(input)?;
+
+        for (path, source) in sources.sources.into_iter().sorted() {
+            // a single file is its own root, so it has an empty relative path
+            let path = if path.as_os_str().is_empty() {
+                root.clone()
+            } else {
+                root.join(path)
+            };
+
+            let formatted = format_source(&source)?;
+
+            if check {
+                if formatted != source {
+                    unformatted.push(path);
+                }
+            } else if stdout || is_std {
+                std::io::stdout().write_all(formatted.as_bytes())?;
+            } else if formatted != source {
+                std::fs::write(&path, formatted)?;
+                eprintln!("Formatted {}", path.display());
+            }
+        }
+    }
+
+    if !unformatted.is_empty() {
+        for path in &unformatted {
+            eprintln!("Would reformat {}", path.display());
+        }
+        bail!(
+            "{} file(s) would be reformatted, run `prqlc fmt` to fix",
+            unformatted.len()
+        )
+    }
+    Ok(())
+}
+
+/// Returns the only source of the tree, since some commands cannot handle
+/// multiple files.
+fn single_source(sources: &SourceTree, command: &str) -> Result<String> {
+    let (_, source) = sources.sources.clone().into_iter().exactly_one().or_else(
+        |_| bail!(
+            "Currently `{command}` only works with a single source, but found multiple sources: {:?}",
+            sources.sources.keys()
+                .map(|x| x.display().to_string())
+                .sorted()
+                .map(|x| format!("`{x}`"))
+                .join(", ")
+        )
+    )?;
+    Ok(source)
+}
+
+/// Formats PRQL source, making sure that it ends with a new line.
+fn format_source(source: &str) -> Result<String> {
+    let mut formatted = format_prql(source)?;
+    if !formatted.ends_with('\n') {
+        formatted.push('\n');
+    }
+    Ok(formatted)
+}
+
 fn read_files(input: &mut clio::ClioPath) -> Result<SourceTree> {
     let root = input.path();
 
@@ -530,6 +627,23 @@ sort full
         "###);
     }
 
+    #[test]
+    fn format_idempotent() {
+        let source = r#"
+from tracks
+filter (genre == "rock" && length > 100)
+derive {minutes = length / 60, title_upper = s"UPPER({title})"}
+group artist (aggregate {total = sum minutes})
+sort {-total}
+take 20
+        "#;
+
+        let once = format_source(source).unwrap();
+        let twice = format_source(&once).unwrap();
+        assert_eq!(once, twice);
+        assert!(once.ends_with('\n'));
+    }
+
     /// Check we get an error on a bad input
     #[test]
     fn compile() {
@@ -541,6 +655,8 @@ sort full
                 signature_comment: false,
                 format: true,
                 target: "sql.any".to_string(),
+                to: CompileOutput::Sql,
+                explain_types: false,
             },
             &mut "asdf".into(),
             "",
@@ -565,6 +681,8 @@ sort full
                 signature_comment: false,
                 format: true,
                 target: "sql.any".to_string(),
+                to: CompileOutput::Sql,
+                explain_types: false,
             },
             &mut SourceTree::new([
                 ("Project.prql".into(), "orders.x | select y".to_string()),
@@ -591,6 +709,32 @@ sort full
         "###);
     }
 
+    #[test]
+    fn compile_explain_types() {
+        let output = Command::execute(
+            &Command::SQLCompile {
+                io_args: IoArgs::default(),
+                signature_comment: false,
+                format: true,
+                target: "sql.any".to_string(),
+                to: CompileOutput::Sql,
+                explain_types: true,
+            },
+            &mut "from employees | derive {bonus = 5} | select {name, bonus}".into(),
+            "",
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let rows = output
+            .lines()
+            .map(|l| l.split_whitespace().collect_vec())
+            .collect_vec();
+        assert_eq!(rows[0], vec!["column", "type", "source"]);
+        assert_eq!(rows[1][0], "name");
+        assert_eq!(rows[2], vec!["bonus", "int", "derived"]);
+    }
+
     #[test]
     fn parse() {
         let output = Command::execute(
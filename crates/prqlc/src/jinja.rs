@@ -18,7 +18,9 @@
 use std::collections::HashMap;
 
 use anyhow::Result;
-use minijinja::machinery::{Span, Token};
+use itertools::Itertools;
+use minijinja::machinery::Token;
+use prql_compiler::ir::rq::{RelationColumn, RelationKind};
 use regex::Regex;
 
 const ANCHOR_PREFIX: &str = "_jinja_";
@@ -26,7 +28,7 @@ const ANCHOR_PREFIX: &str = "_jinja_";
 #[derive(Debug)]
 pub enum JinjaBlock<'a> {
     Data(&'a str),
-    Interpolation(Vec<(Token<'a>, Span)>),
+    Interpolation(&'a str, Vec<Token<'a>>),
 }
 
 #[derive(Default)]
@@ -41,21 +43,31 @@ pub fn pre_process(source: &str) -> Result<(String, JinjaContext)> {
     let mut blocks = Vec::new();
     let mut current_block = Vec::new();
 
+    // Template data is a slice of the source, so an interpolation is everything
+    // between two of them. This keeps quotes, braces and whitespace within the
+    // interpolation as they were written.
+    let mut data_end = 0;
     for res in minijinja::machinery::tokenize(source, false) {
-        let (token, span) = res?;
+        let (token, _) = res?;
 
         if let Token::TemplateData(data) = token {
+            let data_start = data.as_ptr() as usize - source.as_ptr() as usize;
             if !current_block.is_empty() {
-                blocks.push(JinjaBlock::Interpolation(current_block));
+                let text = &source[data_end..data_start];
+                blocks.push(JinjaBlock::Interpolation(text, current_block));
                 current_block = Vec::new();
             }
-            blocks.push(JinjaBlock::Data(data))
+            blocks.push(JinjaBlock::Data(data));
+            data_end = data_start + data.len();
         } else {
-            current_block.push((token, span));
+            current_block.push(token);
         }
     }
     if !current_block.is_empty() {
-        blocks.push(JinjaBlock::Interpolation(current_block));
+        blocks.push(JinjaBlock::Interpolation(
+            &source[data_end..],
+            current_block,
+        ));
     }
 
     let mut anchored_source = String::new();
@@ -64,20 +76,23 @@ pub fn pre_process(source: &str) -> Result<(String, JinjaContext)> {
     for block in blocks {
         match block {
             JinjaBlock::Data(data) => anchored_source += data,
-            JinjaBlock::Interpolation(block) => {
-                let (tokens, spans): (Vec<_>, _) = block.into_iter().unzip();
-
-                let source_span = find_span(source, spans);
+            JinjaBlock::Interpolation(text, tokens) => {
+                // whitespace that was trimmed from the data by `{{-` and `-}}`
+                let leading = &text[..text.len() - text.trim_start().len()];
+                let trailing = &text[text.trim_end().len()..];
+                let text = text.trim();
 
                 if let Some(Token::Ident("config" | "set")) = tokens.get(1) {
-                    context.header.push(source_span);
+                    context.header.push(text);
                 } else {
                     let id = format!("{ANCHOR_PREFIX}{next_anchor_id}");
                     next_anchor_id += 1;
 
+                    anchored_source += leading;
                     anchored_source += &id;
+                    anchored_source += trailing;
 
-                    context.anchor_map.insert(id, source_span);
+                    context.anchor_map.insert(id, text);
                 }
             }
         }
@@ -86,32 +101,38 @@ pub fn pre_process(source: &str) -> Result<(String, JinjaContext)> {
     Ok((anchored_source, context))
 }
 
-fn find_span(source: &str, spans: Vec<Span>) -> &str {
-    let start = spans.first().unwrap();
-    let end = spans.last().unwrap();
-
-    let mut start_index = 0;
-    let mut end_index = source.len();
-
-    let mut line = 1;
-    let mut col = 0;
-    for (index, char) in source.chars().enumerate() {
-        if char == '\n' {
-            line += 1;
-            col = 0;
+/// Find columns that are referenced from templated relations.
+///
+/// The compiler cannot know which columns a template provides, so it assumes
+/// that they exist. Returns a warning for each templated relation whose columns
+/// are referenced. Queries that fail to compile produce no warnings, as their
+/// errors are reported by the compilation itself.
+pub fn check_templated_relations(prql: &str, context: &JinjaContext) -> Vec<String> {
+    let Ok(rq) = prql_compiler::prql_to_pl(prql).and_then(prql_compiler::pl_to_rq) else {
+        return Vec::new();
+    };
+
+    let mut warnings = Vec::new();
+    for table in rq.tables {
+        let RelationKind::ExternRef(ident) = &table.relation.kind else {
             continue;
-        } else {
-            col += 1;
-        }
-
-        if line == start.start_line && col == start.start_col {
-            start_index = index;
-        }
-        if line == end.end_line && col == end.end_col {
-            end_index = index + 1;
+        };
+        let Some(template) = context.anchor_map.get(&ident.name) else {
+            continue;
+        };
+
+        let columns = (table.relation.columns.iter())
+            .filter_map(|c| c.as_single().cloned().flatten())
+            .map(|name| format!("`{name}`"))
+            .collect_vec();
+        if !columns.is_empty() {
+            warnings.push(format!(
+                "columns {} of {template} cannot be checked, because the relation is a template",
+                columns.join(", ")
+            ));
         }
     }
-    &source[start_index..end_index]
+    warnings
 }
 
 /// Replace anchors with their values.
@@ -143,35 +164,67 @@ pub fn post_process(source: &str, context: JinjaContext) -> String {
 
 #[cfg(test)]
 mod test {
-    use super::Span;
+    use insta::assert_snapshot;
+
+    fn compile(source: &str) -> String {
+        let (prql, context) = super::pre_process(source).unwrap();
+        let options = prql_compiler::Options::default().no_signature();
+        let sql = prql_compiler::compile(&prql, &options).unwrap();
+        super::post_process(&sql, context)
+    }
+
+    fn check(source: &str) -> Vec<String> {
+        let (prql, context) = super::pre_process(source).unwrap();
+        super::check_templated_relations(&prql, &context)
+    }
 
     #[test]
-    fn test_find_span() {
-        let text = r#"line 1 col 13
-        line 2 col 21
-        some text line 3 col 31 more text
-        "#;
+    fn test_templates_in_relations() {
+        assert_snapshot!(compile(r#"
+{{ config(materialized='view', tags=["nightly"]) }}
+
+from {{ source('jaffle_shop', 'orders') }}
+join {{ ref('stg_customers') }} (==customer_id)
+"#), @r###"
+        {{ config(materialized='view', tags=["nightly"]) }}
+        SELECT
+          {{ source('jaffle_shop', 'orders') }}.*,
+          {{ ref('stg_customers') }}.*
+        FROM
+          {{ source('jaffle_shop', 'orders') }}
+          JOIN {{ ref('stg_customers') }} ON {{ source('jaffle_shop', 'orders') }}.customer_id = {{ ref('stg_customers') }}.customer_id
+        "###);
+    }
 
+    #[test]
+    fn test_templates_in_columns() {
+        assert_snapshot!(compile(r#"
+from {{ ref('orders') }}
+derive {is_recent = ordered_at > {{ var('start_date', '2020-01-01') }}}
+"#), @r###"
+        SELECT
+          *,
+          ordered_at > {{ var('start_date', '2020-01-01') }} AS is_recent
+        FROM
+          {{ ref('orders') }}
+        "###);
+    }
+
+    #[test]
+    fn test_check_templated_relations() {
         assert_eq!(
-            super::find_span(
-                text,
-                vec![
-                    Span {
-                        start_line: 2,
-                        start_col: 9,
-                        end_line: 12_123_123,
-                        end_col: 2_930_293,
-                    },
-                    Span {
-                        start_line: 7_893_648,
-                        start_col: 79678,
-                        end_line: 3,
-                        end_col: 31,
-                    }
-                ]
+            check(
+                r#"
+from {{ ref('orders') }}
+filter status == 'shipped'
+select {order_id, amount}
+"#
             ),
-            r#"line 2 col 21
-        some text line 3 col 31"#
+            vec![
+                "columns `status`, `order_id`, `amount` of {{ ref('orders') }} cannot be checked, because the relation is a template"
+            ]
         );
+
+        assert!(check("from {{ ref('orders') }}").is_empty());
     }
 }
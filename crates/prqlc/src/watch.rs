@@ -29,6 +29,7 @@ pub fn run(command: &mut WatchArgs) -> Result<()> {
         signature_comment: !command.no_signature,
         // TODO: potentially offer this as an arg?
         color: true,
+        ..Default::default()
     };
     let path = Path::new(&command.path);
 
@@ -119,6 +120,9 @@ fn compile_path(path: &Path, opt: &prql_compiler::Options) -> Result<()> {
 
     // compile
     println!("Compiling {}", prql_path.display());
+    for warning in jinja::check_templated_relations(&prql_string, &jinja_context) {
+        println!("Warning: {warning}");
+    }
     let sql_string = match prql_compiler::compile(&prql_string, opt) {
         Ok(sql_string) => sql_string,
         Err(err) => {
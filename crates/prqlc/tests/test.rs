@@ -3,8 +3,9 @@
 use insta_cmd::get_cargo_bin;
 use insta_cmd::{assert_cmd_snapshot, StdinCommand};
 use std::env::current_dir;
+use std::io::Write;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 #[cfg(not(windows))] // Windows has slightly different output (e.g. `prqlc.exe`), so we exclude.
 #[test]
@@ -46,15 +47,20 @@ fn get_targets() {
     ----- stdout -----
     sql.any
     sql.ansi
+    sql.athena
     sql.bigquery
     sql.clickhouse
     sql.duckdb
     sql.generic
     sql.mssql
     sql.mysql
+    sql.oracle
+    sql.oracle12
     sql.postgres
+    sql.redshift
     sql.sqlite
     sql.snowflake
+    sql.trino
 
     ----- stderr -----
     "###);
@@ -79,6 +85,131 @@ fn compile() {
     "###);
 }
 
+#[test]
+fn compile_stdin() {
+    // without an input file, the query is read from stdin
+    let mut cmd = StdinCommand::new(
+        get_cargo_bin("prqlc"),
+        "from tracks\nfilter length > 300\nselect {title, length}\n",
+    );
+    normalize_prqlc(&mut cmd);
+    cmd.args(["compile", "--hide-signature-comment"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      title,
+      length
+    FROM
+      tracks
+    WHERE
+      length > 300
+
+    ----- stderr -----
+    "###);
+
+    // errors are written to stderr, with a non-zero exit code
+    let mut child = prqlc_command()
+        .arg("compile")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    (child.stdin.take().unwrap())
+        .write_all(b"from tracks | select {a} | derive c = x")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Unknown name"));
+}
+
+#[test]
+fn compile_error_color() {
+    let compile_error = |color: &str| {
+        let mut child = Command::new(get_cargo_bin("prqlc"))
+            .env_remove("CLICOLOR_FORCE")
+            .env_remove("NO_COLOR")
+            .args([&format!("--color={color}"), "compile"])
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        (child.stdin.take().unwrap())
+            .write_all(b"from tracks | select {a} | derive c = x")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    assert!(!compile_error("never").contains('\x1b'));
+    assert!(compile_error("always").contains('\x1b'));
+}
+
+#[test]
+fn compile_to() {
+    let mut cmd = StdinCommand::new(get_cargo_bin("prqlc"), "from tracks | take 20");
+    normalize_prqlc(&mut cmd);
+    cmd.args(["compile", "--hide-signature-comment", "--to", "sql"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    SELECT
+      *
+    FROM
+      tracks
+    LIMIT
+      20
+
+    ----- stderr -----
+    "###);
+
+    let mut cmd = StdinCommand::new(get_cargo_bin("prqlc"), "from tracks | take 20");
+    normalize_prqlc(&mut cmd);
+    cmd.args(["compile", "--to", "json"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    [{"Main":{"Pipeline":{"exprs":[{"FuncCall":{"name":{"Ident":["from"]},"args":[{"Ident":["tracks"]}]}},{"FuncCall":{"name":{"Ident":["take"]},"args":[{"Literal":{"Integer":20}}]}}]}},"annotations":[]}]
+
+    ----- stderr -----
+    "###);
+
+    let mut cmd = StdinCommand::new(get_cargo_bin("prqlc"), "from tracks | take 20");
+    normalize_prqlc(&mut cmd);
+    cmd.args(["compile", "--to", "prql"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    from tracks
+    take 20
+
+    ----- stderr -----
+    "###);
+
+    // the input can also be a file
+    let path = std::env::temp_dir().join("prqlc_compile_to.prql");
+    std::fs::write(&path, "from tracks | take 20").unwrap();
+    let mut cmd = prqlc_command();
+    cmd.args(["compile", "--to", "prql", path.to_str().unwrap()]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    from tracks
+    take 20
+
+    ----- stderr -----
+    "###);
+}
+
 #[cfg(not(windows))] // Windows has slightly different output (e.g. `prqlc.exe`), so we exclude.
 #[test]
 fn compile_help() {
@@ -118,6 +249,15 @@ fn compile_help() {
               [env: PRQLC_TARGET=]
               [default: sql.any]
 
+          --to <TO>
+              Output to produce: SQL, PL AST as JSON, or formatted PRQL
+              
+              [default: sql]
+              [possible values: sql, json, prql]
+
+          --explain-types
+              Print the columns of the result with their types, instead of SQL
+
           --color <WHEN>
               Controls when to use color
               
@@ -235,6 +375,54 @@ fn format() {
     "###);
 }
 
+#[test]
+fn format_check() {
+    let mut cmd = StdinCommand::new(get_cargo_bin("prqlc"), "from tracks | take 20");
+    normalize_prqlc(&mut cmd);
+    cmd.args(["fmt", "--check"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: false
+    exit_code: 1
+    ----- stdout -----
+
+    ----- stderr -----
+    Would reformat -
+    1 file(s) would be reformatted, run `prqlc fmt` to fix
+    "###);
+
+    let mut cmd = StdinCommand::new(get_cargo_bin("prqlc"), "from tracks\ntake 20\n");
+    normalize_prqlc(&mut cmd);
+    cmd.args(["fmt", "--check"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+
+    ----- stderr -----
+    "###);
+}
+
+#[test]
+fn format_comments() {
+    let mut cmd = StdinCommand::new(
+        get_cargo_bin("prqlc"),
+        "# all tracks\nfrom tracks\n# a few of them\ntake 20 # for now\n",
+    );
+    normalize_prqlc(&mut cmd);
+    cmd.args(["fmt"]);
+    assert_cmd_snapshot!(cmd, @r###"
+    success: true
+    exit_code: 0
+    ----- stdout -----
+    # all tracks
+    from tracks
+    # a few of them
+    take 20  # for now
+
+    ----- stderr -----
+    "###);
+}
+
 #[test]
 fn shell_completion() {
     for shell in ["bash", "fish", "powershell", "zsh"].iter() {
@@ -14,38 +14,54 @@ fn test_expr_ast_code_matches() {
             &read_to_string("../../crates/prql-compiler/src/ir/pl/expr.rs").unwrap(),
         ), @r###"
     @@ .. @@
+    -#[derive(
+    -    Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, JsonSchema, strum::AsRefStr,
+    -)]
+    +#[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, strum::AsRefStr)]
+    @@ .. @@
     -    Pipeline(Pipeline),
     @@ .. @@
     -    Binary(BinaryExpr),
     -    Unary(UnaryExpr),
     @@ .. @@
-    -}
-    -
-    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    -#[serde(rename_all = "snake_case")]
     -pub struct BinaryExpr {
     -    pub left: Box<Expr>,
     -    pub op: BinOp,
     -    pub right: Box<Expr>,
-    @@ .. @@
-    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    -}
+    -
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    -#[serde(rename_all = "snake_case")]
     -pub struct UnaryExpr {
     -    pub op: UnOp,
     -    pub expr: Box<Expr>,
     -}
     -
     @@ .. @@
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
     -    pub return_ty: Option<Box<Expr>>,
     +    pub return_ty: Option<TyOrExpr>,
     @@ .. @@
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
     -    pub ty: Option<Box<Expr>>,
     +    pub ty: Option<TyOrExpr>,
     @@ .. @@
+    -}
+    -
     -/// A value and a series of functions that are to be applied to that value one after another.
-    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    -#[serde(rename_all = "snake_case")]
     -pub struct Pipeline {
     -    pub exprs: Vec<Expr>,
-    -}
-    -
     @@ .. @@
     -
     -impl From<Vec<Expr>> for Pipeline {
@@ -67,10 +83,25 @@ fn test_stmt_ast_code_matches() {
             &read_to_string("../../crates/prql-compiler/src/ir/pl/stmt.rs").unwrap(),
         ), @r###"
     @@ .. @@
+    -#[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, EnumAsInner, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
     -    Main(Box<Expr>),
     @@ .. @@
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
     -    pub name: String,
     +    pub name: Option<String>,
+    @@ .. @@
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
+    -#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+    @@ .. @@
+    -#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+    +#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
     "###
     )
 }